@@ -1,5 +1,13 @@
-use crate::popups::{OpenProjectPopup, Popup};
+use crate::archive::ARCHIVE_EXTENSION;
+use crate::config::AppConfig;
+use crate::launchers;
+use crate::popups::{
+    BatchOperation, BatchPopup, DuplicatesPopup, ExcludePatternsPopup, FileBrowserPopup, JobsPopup,
+    LibrariesPopup, MoveDialogPopup, OpenProjectPopup, Popup, SizeBreakdownPopup,
+    SubdirectoriesPopup,
+};
 use crate::project::{ProjectDirectoryEntryState, ProjectState};
+use crate::steam::{self, SteamLibrary};
 use crate::sync::CancellationToken;
 use crate::widgets::{TextInput, TextInputState};
 use crossterm::event;
@@ -27,6 +35,10 @@ pub const APP_TITLE: &str = concat!(
     " "
 );
 
+/// Logged when there's nowhere to move entries to.
+pub const NO_LIBRARY_ERROR: &str =
+    "No destination library configured! Add one under Project › Destination libraries.";
+
 /// The focus state of the application.
 ///
 /// This is used to determine which part of the application has focus.
@@ -41,23 +53,34 @@ pub enum FocusState {
 
 /// The menu actions that can be performed.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum MenuAction<'a> {
+pub enum MenuAction {
     Open,
-    OpenRecent(&'a str),
+    Browse,
+    OpenRecent(PathBuf),
+    OpenSteamLibrary(PathBuf),
+    OpenLauncherLibrary(PathBuf),
     CloseProj,
+    ExcludePatterns,
+    Libraries,
     Exit,
 }
 
-pub struct MoverrApp<'a> {
+pub struct MoverrApp {
     terminate: CancellationToken,
+    pub config: AppConfig,
+    /// Steam libraries found when the application started.
+    pub steam_libraries: Vec<SteamLibrary>,
+    /// Directories other launchers installed games in when the application started, with the
+    /// launcher installing there.
+    launcher_roots: Vec<(&'static str, PathBuf)>,
     pub project_state: Option<ProjectState>,
     pub focus: FocusState,
-    pub menu: MenuState<Option<MenuAction<'a>>>,
+    pub menu: MenuState<Option<MenuAction>>,
     pub logger_state: TuiWidgetState,
     pub popup: Option<Box<dyn Popup>>,
 }
 
-impl MoverrApp<'_> {
+impl MoverrApp {
     pub const FRAMERATE: u8 = 30;
 
     pub const fn get_frame_duration() -> Duration {
@@ -66,37 +89,19 @@ impl MoverrApp<'_> {
     }
 
     pub fn new() -> Self {
+        let config = AppConfig::load_or_default();
+        let steam_libraries = steam::find_libraries();
+        let launcher_roots = launchers::install_roots(&launchers::installed_games());
         let mut new = Self {
             terminate: CancellationToken::new(),
             project_state: None,
             focus: FocusState::Project,
-            menu: MenuState::new(vec![
-                MenuItem::group(
-                    "File",
-                    vec![
-                        MenuItem::item("Open", Some(MenuAction::Open)),
-                        MenuItem::item("Close", Some(MenuAction::CloseProj)),
-                        // MenuItem::group(
-                        //     "Open recent",
-                        //     vec![
-                        //         MenuItem::item("File 1", Some(MenuAction::OpenRecent("file1"))),
-                        //         MenuItem::item("File 2", Some(MenuAction::OpenRecent("file2"))),
-                        //         MenuItem::item("File 3", Some(MenuAction::OpenRecent("file3"))),
-                        //     ],
-                        // ),
-                        MenuItem::item("Exit", Some(MenuAction::Exit)),
-                    ],
-                ),
-                MenuItem::group(
-                    "About",
-                    vec![
-                        MenuItem::item(env!("CARGO_PKG_NAME"), None),
-                        MenuItem::item(env!("CARGO_PKG_VERSION"), None),
-                    ],
-                ),
-            ]),
+            menu: build_menu(&config.recent_projects, &steam_libraries, &launcher_roots),
+            steam_libraries,
+            launcher_roots,
             logger_state: Default::default(),
             popup: None,
+            config,
         };
 
         // Check if a project was passed as an argument. If so, try to open it.
@@ -131,11 +136,20 @@ impl MoverrApp<'_> {
             return Err("Project already opened!".to_string());
         }
 
-        let project_state = ProjectState::open(directory);
+        let project_state = ProjectState::open(directory, &self.config);
 
         match project_state {
-            Ok(project_state) => {
+            Ok(mut project_state) => {
                 project_state.start_calc();
+                self.config.add_recent_project(&project_state.directory);
+                if let Err(err) = self.config.save() {
+                    warn!("Failed to save config: {}", err);
+                }
+                self.menu = build_menu(
+                    &self.config.recent_projects,
+                    &self.steam_libraries,
+                    &self.launcher_roots,
+                );
                 self.project_state = Some(project_state);
                 Ok(self.project_state.as_ref().unwrap())
             }
@@ -201,6 +215,85 @@ impl MoverrApp<'_> {
     }
 }
 
+/// Build the menu bar, with the recently opened projects under `File › Open recent` and the Steam
+/// libraries under `File › Steam libraries`.
+fn build_menu(
+    recent_projects: &[PathBuf],
+    steam_libraries: &[SteamLibrary],
+    launcher_roots: &[(&'static str, PathBuf)],
+) -> MenuState<Option<MenuAction>> {
+    let recent_items = if recent_projects.is_empty() {
+        vec![MenuItem::item("No recent projects", None)]
+    } else {
+        recent_projects
+            .iter()
+            .map(|path| {
+                MenuItem::item(
+                    path.display().to_string(),
+                    Some(MenuAction::OpenRecent(path.clone())),
+                )
+            })
+            .collect()
+    };
+
+    let steam_items = if steam_libraries.is_empty() {
+        vec![MenuItem::item("No Steam libraries found", None)]
+    } else {
+        steam_libraries
+            .iter()
+            .map(|library| {
+                MenuItem::item(
+                    library.display_name(),
+                    Some(MenuAction::OpenSteamLibrary(library.common())),
+                )
+            })
+            .collect()
+    };
+
+    let launcher_items = if launcher_roots.is_empty() {
+        vec![MenuItem::item("No launcher libraries found", None)]
+    } else {
+        launcher_roots
+            .iter()
+            .map(|(launcher, path)| {
+                MenuItem::item(
+                    format!("{}: {}", launcher, path.display()),
+                    Some(MenuAction::OpenLauncherLibrary(path.clone())),
+                )
+            })
+            .collect()
+    };
+
+    MenuState::new(vec![
+        MenuItem::group(
+            "File",
+            vec![
+                MenuItem::item("Open", Some(MenuAction::Open)),
+                MenuItem::item("Browse…", Some(MenuAction::Browse)),
+                MenuItem::group("Open recent", recent_items),
+                MenuItem::group("Steam libraries", steam_items),
+                MenuItem::group("Launcher libraries", launcher_items),
+                MenuItem::item("Close", Some(MenuAction::CloseProj)),
+                MenuItem::item("Exit", Some(MenuAction::Exit)),
+            ],
+        ),
+        MenuItem::group(
+            "Project",
+            vec![
+                MenuItem::item("Exclude patterns", Some(MenuAction::ExcludePatterns)),
+                MenuItem::item("Destination libraries", Some(MenuAction::Libraries)),
+            ],
+        ),
+        MenuItem::group(
+            "About",
+            vec![
+                MenuItem::item(env!("CARGO_PKG_NAME"), None),
+                MenuItem::item(env!("CARGO_PKG_VERSION"), None),
+            ],
+        ),
+    ])
+}
+
 fn draw_app(frame: &mut Frame, state: &mut MoverrApp) {
     let main_block = Block::default()
         .title(APP_TITLE)
@@ -322,6 +415,9 @@ fn handle_term_event(event: Event, state: &mut MoverrApp) {
             }
             FocusState::Project => {
                 if let Some(ref mut project) = state.project_state {
+                    if project.handle_filter_key_event(key_event) {
+                        return;
+                    }
                     if let KeyEvent {
                         kind: KeyEventKind::Press,
                         modifiers: KeyModifiers::NONE,
@@ -346,19 +442,226 @@ fn handle_term_event(event: Event, state: &mut MoverrApp) {
                                 project.table_state.select_last();
                                 return;
                             }
+                            KeyCode::Char('s') => {
+                                project.cycle_sort_key();
+                                return;
+                            }
+                            KeyCode::Char('o') => {
+                                project.toggle_sort_order();
+                                return;
+                            }
+                            KeyCode::Char('/') => {
+                                project.start_filter();
+                                return;
+                            }
+                            KeyCode::Char(' ') => {
+                                project.toggle_mark();
+                                return;
+                            }
+                            KeyCode::Char('m') => {
+                                project.mark_all();
+                                return;
+                            }
+                            KeyCode::Char('i') => {
+                                project.invert_marks();
+                                return;
+                            }
+                            KeyCode::Char('u') => {
+                                project.clear_marks();
+                                return;
+                            }
+                            KeyCode::Right if project.has_marks() => {
+                                match MoveDialogPopup::for_marked(project, &state.steam_libraries) {
+                                    Some(popup) if popup.has_destinations() => {
+                                        state.open_popup(Box::new(popup)).unwrap();
+                                    }
+                                    Some(_) => error!("{}", NO_LIBRARY_ERROR),
+                                    None => error!("None of the marked directories can be moved!"),
+                                }
+                                return;
+                            }
+                            KeyCode::Left | KeyCode::Char('r') | KeyCode::Char('a')
+                                if project.has_marks() =>
+                            {
+                                let operation = match code {
+                                    KeyCode::Left => BatchOperation::MoveBack,
+                                    KeyCode::Char('r') => BatchOperation::Rescan,
+                                    _ => BatchOperation::Verify,
+                                };
+                                match BatchPopup::new(project, operation) {
+                                    Some(popup) => state.open_popup(Box::new(popup)).unwrap(),
+                                    None => warn!(
+                                        "{} doesn't apply to any of the marked directories!",
+                                        operation.label()
+                                    ),
+                                }
+                                return;
+                            }
+                            KeyCode::Char('e') => {
+                                if let Some(entry) = project.selected_entry() {
+                                    match entry {
+                                        crate::project::ProjectEntry::Directory(dir) => {
+                                            if matches!(
+                                                *dir.state.lock().unwrap(),
+                                                ProjectDirectoryEntryState::InOriginalLocation
+                                            ) {
+                                                let patterns = project
+                                                    .settings
+                                                    .lock()
+                                                    .unwrap()
+                                                    .entry(&dir.name)
+                                                    .map(|entry| entry.exclude.clone())
+                                                    .unwrap_or_default();
+                                                let popup = ExcludePatternsPopup::new(
+                                                    Some(dir.name.clone()),
+                                                    &patterns,
+                                                );
+                                                state.open_popup(Box::new(popup)).unwrap();
+                                            } else {
+                                                warn!(
+                                                    "Exclude patterns of {:?} can only be changed before moving it!",
+                                                    dir.name
+                                                );
+                                            }
+                                        }
+                                        crate::project::ProjectEntry::File(file) => {
+                                            warn!("Selected file: {:?}. Nothing to do!", file);
+                                        }
+                                    }
+                                } else {
+                                    warn!("No entry selected!");
+                                }
+                                return;
+                            }
+                            KeyCode::Enter => {
+                                if let Some(entry) = project.selected_entry() {
+                                    match entry {
+                                        crate::project::ProjectEntry::Directory(dir) => {
+                                            if dir.can_change_subdirectories() {
+                                                let popup =
+                                                    SubdirectoriesPopup::new(project, &dir.name);
+                                                state.open_popup(Box::new(popup)).unwrap();
+                                            } else {
+                                                warn!(
+                                                    "Subdirectories of {:?} can only be moved while it's in its original location!",
+                                                    dir.name
+                                                );
+                                            }
+                                        }
+                                        crate::project::ProjectEntry::File(file) => {
+                                            warn!("Selected file: {:?}. Nothing to do!", file);
+                                        }
+                                    }
+                                } else {
+                                    warn!("No entry selected!");
+                                }
+                                return;
+                            }
+                            KeyCode::Char('r') => {
+                                let selected_name = project
+                                    .selected_entry()
+                                    .map(|entry| entry.name().to_string());
+                                if let Some(name) = selected_name {
+                                    info!(target: "project", "Rescanning {}", name);
+                                    project.rescan(&name);
+                                } else {
+                                    warn!("No entry selected!");
+                                }
+                                return;
+                            }
+                            KeyCode::Char('b') => {
+                                match project.selected_entry() {
+                                    Some(crate::project::ProjectEntry::Directory(dir)) => {
+                                        let popup = SizeBreakdownPopup::new(
+                                            &dir.name,
+                                            project.directory.join(&dir.name),
+                                            dir.stats().and_then(Result::ok),
+                                        );
+                                        state.open_popup(Box::new(popup)).unwrap();
+                                    }
+                                    Some(crate::project::ProjectEntry::File(file)) => {
+                                        warn!("Selected file: {:?}. Nothing to do!", file);
+                                    }
+                                    None => warn!("No entry selected!"),
+                                }
+                                return;
+                            }
+                            KeyCode::Char('j') => {
+                                let popup = JobsPopup::new(project.job_queue.clone());
+                                state.open_popup(Box::new(popup)).unwrap();
+                                return;
+                            }
+                            KeyCode::Char('d') => {
+                                let popup = DuplicatesPopup::new(
+                                    project.directory.clone(),
+                                    project.locations(),
+                                );
+                                state.open_popup(Box::new(popup)).unwrap();
+                                return;
+                            }
+                            KeyCode::Char('z') => {
+                                if let Some(crate::project::ProjectEntry::Directory(dir)) =
+                                    project.selected_entry()
+                                {
+                                    let res = if dir.is_archived() {
+                                        dir.try_start_restore(project)
+                                    } else if let Some(library) = project.default_library() {
+                                        dir.try_start_archive(
+                                            project,
+                                            library.path.join(format!(
+                                                "{}.{}",
+                                                dir.name, ARCHIVE_EXTENSION
+                                            )),
+                                        )
+                                    } else {
+                                        error!("{}", NO_LIBRARY_ERROR);
+                                        return;
+                                    };
+
+                                    if res.is_err() {
+                                        error!(
+                                            "Directory {:?} couldn't be archived or restored!",
+                                            dir
+                                        );
+                                    }
+                                } else {
+                                    warn!("No directory selected!");
+                                }
+                                return;
+                            }
+                            KeyCode::Char('a') => {
+                                let count = project.start_audit(None);
+                                if count > 0 {
+                                    info!(target: "audit", "Auditing {} moved directories", count);
+                                } else {
+                                    warn!(target: "audit", "No moved directories to audit!");
+                                }
+                                return;
+                            }
                             KeyCode::Right => {
-                                let selected_id = project.table_state.selected();
-                                if let Some(selected_id) = selected_id {
-                                    let entry = &project.entries[selected_id];
+                                if let Some(entry) = project.selected_entry() {
                                     match entry {
                                         crate::project::ProjectEntry::Directory(dir) => {
-                                            let res = dir.try_start_move_to(
-                                                project,
-                                                Path::new("F:\\Games").join(&dir.name),
-                                            );
-
-                                            if res.is_err() {
-                                                error!("Directory {:?} couldn't be moved!", dir);
+                                            match dir.stats() {
+                                                Some(Ok(stats)) if dir.can_be_moved() => {
+                                                    let popup = MoveDialogPopup::new(
+                                                        project,
+                                                        &state.steam_libraries,
+                                                        vec![dir.name.clone()],
+                                                        stats,
+                                                    );
+                                                    if popup.has_destinations() {
+                                                        state.open_popup(Box::new(popup)).unwrap();
+                                                    } else {
+                                                        error!("{}", NO_LIBRARY_ERROR);
+                                                    }
+                                                }
+                                                _ => {
+                                                    error!(
+                                                        "Directory {:?} couldn't be moved!",
+                                                        dir
+                                                    );
+                                                }
                                             }
                                         }
                                         crate::project::ProjectEntry::File(file) => {
@@ -371,9 +674,7 @@ fn handle_term_event(event: Event, state: &mut MoverrApp) {
                                 return;
                             }
                             KeyCode::Left => {
-                                let selected_id = project.table_state.selected();
-                                if let Some(selected_id) = selected_id {
-                                    let entry = &project.entries[selected_id];
+                                if let Some(entry) = project.selected_entry() {
                                     match entry {
                                         crate::project::ProjectEntry::Directory(dir) => {
                                             let res = dir.try_start_move_back(project);
@@ -468,14 +769,63 @@ fn handle_menu_event(event: MenuAction, state: &mut MoverrApp) {
                 .unwrap();
             state.menu.reset();
         }
+        MenuAction::Browse => {
+            let popup = FileBrowserPopup::new("Open Project", None, |state, path| {
+                if state.project_state.is_some() {
+                    state.close_project()?;
+                }
+                state.try_open_project(&path).map(|_| ())
+            });
+            state.open_popup(Box::new(popup)).unwrap();
+            state.menu.reset();
+        }
         MenuAction::CloseProj => {
             let res = state.close_project();
             if let Err(ref res) = res {
                 error!("Couldn't clone project: {}", res);
             }
         }
-        MenuAction::OpenRecent(file) => {
-            info!("Opening recent file: {}", file);
+        MenuAction::ExcludePatterns => {
+            if let Some(ref project) = state.project_state {
+                let patterns = project.settings.lock().unwrap().exclude.clone();
+                state
+                    .open_popup(Box::new(ExcludePatternsPopup::new(None, &patterns)))
+                    .unwrap();
+            } else {
+                warn!("No project opened!");
+            }
+            state.menu.reset();
+        }
+        MenuAction::Libraries => {
+            if let Some(ref project) = state.project_state {
+                let libraries = project.libraries();
+                let usage = libraries
+                    .iter()
+                    .map(|library| project.library_usage(library))
+                    .collect();
+                let default_library = project.default_library().map(|library| library.label);
+                state
+                    .open_popup(Box::new(LibrariesPopup::new(
+                        libraries,
+                        usage,
+                        default_library,
+                    )))
+                    .unwrap();
+            } else {
+                warn!("No project opened!");
+            }
+            state.menu.reset();
+        }
+        MenuAction::OpenRecent(directory)
+        | MenuAction::OpenSteamLibrary(directory)
+        | MenuAction::OpenLauncherLibrary(directory) => {
+            state.menu.reset();
+            if state.project_state.is_some() && state.close_project().is_err() {
+                return;
+            }
+            if let Err(err) = state.try_open_project(&directory) {
+                error!("Couldn't open {:?}: {}", directory, err);
+            }
         }
         MenuAction::Exit => {
             state.terminate();
@@ -497,6 +847,10 @@ pub fn run(mut terminal: DefaultTerminal) -> io::Result<()> {
             state.focus = FocusState::Project;
         }
 
+        if let Some(ref mut project_state) = state.project_state {
+            project_state.tick();
+        }
+
         terminal.draw(|frame| {
             draw_app(frame, &mut state);
         })?;
@@ -0,0 +1,257 @@
+use crate::file_size::num_ext::AsBytes;
+use crate::manifest::ManifestFile;
+use crate::path_ext::{ArchiveError, ProcessDirectoryProgress};
+use crate::sync::CancellationToken;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Component, Path};
+use std::sync::Mutex;
+
+/// Extension of the archives entries are packed into.
+pub const ARCHIVE_EXTENSION: &str = "tar.zst";
+
+/// Zstandard compression level. Game data is mostly compressed already, so higher levels cost a
+/// lot of time for little gain.
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Pack the directory at `root` into a new archive at `archive_path`.
+///
+/// Like the other functions here, this blocks, so it's meant to be run with [`smol::unblock`].
+pub fn pack(
+    root: &Path,
+    archive_path: &Path,
+    progress: Option<&Mutex<ProcessDirectoryProgress>>,
+    cancellation_token: Option<&CancellationToken>,
+) -> Result<(), ArchiveError> {
+    let file = File::create_new(archive_path).map_err(|e| match e.kind() {
+        io::ErrorKind::AlreadyExists => ArchiveError::DestinationExists,
+        kind => ArchiveError::Io(kind),
+    })?;
+    let encoder = zstd::Encoder::new(BufWriter::new(file), COMPRESSION_LEVEL)
+        .map_err(|e| ArchiveError::Io(e.kind()))?;
+    let mut builder = tar::Builder::new(encoder);
+    builder.follow_symlinks(false);
+
+    append_directory(&mut builder, root, "", progress, cancellation_token)?;
+
+    let file = builder
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .and_then(|writer| writer.into_inner().map_err(|e| e.into_error()))
+        .map_err(|e| ArchiveError::Io(e.kind()))?;
+    file.sync_all().map_err(|e| ArchiveError::Io(e.kind()))
+}
+
+fn append_directory<W: io::Write>(
+    builder: &mut tar::Builder<W>,
+    dir: &Path,
+    prefix: &str,
+    progress: Option<&Mutex<ProcessDirectoryProgress>>,
+    cancellation_token: Option<&CancellationToken>,
+) -> Result<(), ArchiveError> {
+    let mut children = fs::read_dir(dir)
+        .and_then(|children| children.collect::<io::Result<Vec<_>>>())
+        .map_err(|e| ArchiveError::Io(e.kind()))?;
+    children.sort_by_key(|child| child.file_name());
+
+    for child in children {
+        if let Some(cancellation_token) = cancellation_token {
+            if cancellation_token.is_cancelled() {
+                return Err(ArchiveError::Cancelled);
+            }
+        }
+
+        let relative = format!("{}{}", prefix, child.file_name().to_string_lossy());
+        let metadata = child
+            .path()
+            .symlink_metadata()
+            .map_err(|e| ArchiveError::Io(e.kind()))?;
+        if metadata.is_symlink() {
+            return Err(ArchiveError::SymlinkEncountered);
+        } else if metadata.is_dir() {
+            builder
+                .append_dir(&relative, child.path())
+                .map_err(|e| ArchiveError::Io(e.kind()))?;
+            append_directory(
+                builder,
+                &child.path(),
+                &format!("{}/", relative),
+                progress,
+                cancellation_token,
+            )?;
+        } else {
+            builder
+                .append_path_with_name(child.path(), &relative)
+                .map_err(|e| ArchiveError::Io(e.kind()))?;
+            if let Some(progress) = progress {
+                progress
+                    .lock()
+                    .unwrap()
+                    .process_file(metadata.len().bytes());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Hash every file in the archive at `archive_path`, keyed by its `/`-separated path, for
+/// comparing against the index written when it was packed.
+pub fn read_files(
+    archive_path: &Path,
+    progress: Option<&Mutex<ProcessDirectoryProgress>>,
+    cancellation_token: Option<&CancellationToken>,
+) -> Result<BTreeMap<String, ManifestFile>, ArchiveError> {
+    let mut archive = open(archive_path)?;
+    let mut files = BTreeMap::new();
+
+    for entry in archive.entries().map_err(|e| ArchiveError::Io(e.kind()))? {
+        if let Some(cancellation_token) = cancellation_token {
+            if cancellation_token.is_cancelled() {
+                return Err(ArchiveError::Cancelled);
+            }
+        }
+
+        let mut entry = entry.map_err(|e| ArchiveError::Io(e.kind()))?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let relative = relative_path(&entry.path().map_err(|e| ArchiveError::Io(e.kind()))?)?;
+        let mut hasher = blake3::Hasher::new();
+        let size = io::copy(&mut entry, &mut hasher).map_err(|e| ArchiveError::Io(e.kind()))?;
+        files.insert(
+            relative,
+            ManifestFile {
+                size: size.bytes(),
+                modified: None,
+                hash: Some(hasher.finalize().to_hex().to_string()),
+            },
+        );
+        if let Some(progress) = progress {
+            progress.lock().unwrap().process_file(size.bytes());
+        }
+    }
+
+    Ok(files)
+}
+
+/// Extract the archive at `archive_path` into the directory at `dest`, which mustn't exist yet.
+pub fn unpack(
+    archive_path: &Path,
+    dest: &Path,
+    progress: Option<&Mutex<ProcessDirectoryProgress>>,
+    cancellation_token: Option<&CancellationToken>,
+) -> Result<(), ArchiveError> {
+    let mut archive = open(archive_path)?;
+    fs::create_dir(dest).map_err(|e| match e.kind() {
+        io::ErrorKind::AlreadyExists => ArchiveError::DestinationExists,
+        kind => ArchiveError::Io(kind),
+    })?;
+
+    for entry in archive.entries().map_err(|e| ArchiveError::Io(e.kind()))? {
+        if let Some(cancellation_token) = cancellation_token {
+            if cancellation_token.is_cancelled() {
+                return Err(ArchiveError::Cancelled);
+            }
+        }
+
+        let mut entry = entry.map_err(|e| ArchiveError::Io(e.kind()))?;
+        if entry.header().entry_type().is_symlink() {
+            return Err(ArchiveError::SymlinkEncountered);
+        }
+        let size = entry.size();
+        let is_file = entry.header().entry_type().is_file();
+        // Refuses paths that would end up outside of `dest`
+        entry
+            .unpack_in(dest)
+            .map_err(|e| ArchiveError::Io(e.kind()))?;
+        if let Some(progress) = progress.filter(|_| is_file) {
+            progress.lock().unwrap().process_file(size.bytes());
+        }
+    }
+
+    Ok(())
+}
+
+fn open(archive_path: &Path) -> Result<tar::Archive<impl io::Read>, ArchiveError> {
+    let file = File::open(archive_path).map_err(|e| ArchiveError::Io(e.kind()))?;
+    let decoder = zstd::Decoder::new(file).map_err(|e| ArchiveError::Io(e.kind()))?;
+    Ok(tar::Archive::new(decoder))
+}
+
+/// Convert a path stored in an archive to the `/`-separated form used by manifests.
+fn relative_path(path: &Path) -> Result<String, ArchiveError> {
+    let mut parts = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy()),
+            Component::CurDir => {}
+            _ => return Err(ArchiveError::VerificationFailed),
+        }
+    }
+    Ok(parts.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::{AuditReport, MoveManifest};
+
+    fn hash_files(root: &Path) -> BTreeMap<String, ManifestFile> {
+        let mut manifest = MoveManifest::new(root, root);
+        smol::block_on(manifest.collect_files(root, true, None, None)).unwrap();
+        manifest.files
+    }
+
+    #[test]
+    fn test_pack_verify_unpack() {
+        let dir = std::env::temp_dir().join(format!("moverr-archive-{}", std::process::id()));
+        let (root, archive_path, unpacked) = (
+            dir.join("Game"),
+            dir.join("Game.tar.zst"),
+            dir.join("Unpacked"),
+        );
+        fs::create_dir_all(root.join("data/empty")).unwrap();
+        fs::write(root.join("game.exe"), "game").unwrap();
+        // Incompressible, so most of the archive is this file
+        let mut state = 1u32;
+        let noise: Vec<u8> = (0..64 * 1024)
+            .map(|_| {
+                state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+                (state >> 24) as u8
+            })
+            .collect();
+        fs::write(root.join("data/noise.bin"), &noise).unwrap();
+        let expected = hash_files(&root);
+
+        pack(&root, &archive_path, None, None).unwrap();
+        let files = read_files(&archive_path, None, None).unwrap();
+        assert!(AuditReport::compare(&expected, &files).is_clean());
+        assert!(matches!(
+            pack(&root, &archive_path, None, None),
+            Err(ArchiveError::DestinationExists)
+        ));
+
+        unpack(&archive_path, &unpacked, None, None).unwrap();
+        assert!(AuditReport::compare(&expected, &hash_files(&unpacked)).is_clean());
+        assert!(unpacked.join("data/empty").is_dir());
+
+        let mut bytes = fs::read(&archive_path).unwrap();
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 0xff;
+        fs::write(&archive_path, bytes).unwrap();
+        assert!(!read_files(&archive_path, None, None)
+            .is_ok_and(|files| AuditReport::compare(&expected, &files).is_clean()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(
+            relative_path(Path::new("./bin/game.exe")).unwrap(),
+            "bin/game.exe"
+        );
+        assert!(relative_path(Path::new("../escape")).is_err());
+    }
+}
@@ -0,0 +1,384 @@
+use crate::file_size::num_ext::{AsBytes, AsBytesMult};
+use crate::file_size::FileSize;
+use crate::path_ext::PathExt;
+use crate::sync::CancellationToken;
+use crate::volume_information::VolumeInformation;
+use futures_lite::StreamExt;
+use log::warn;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// File system families with naming restrictions that Moverr knows about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileSystemKind {
+    Fat,
+    ExFat,
+    Ntfs,
+    Other,
+}
+
+impl FileSystemKind {
+    /// Guess the kind from the file system name reported by the OS, e.g. `NTFS` on Windows or
+    /// `ntfs3` on Linux.
+    ///
+    /// `fuseblk` mounts whose real type is unknown are nearly always ntfs-3g or exfat-fuse, so
+    /// they're treated as NTFS to keep the Windows naming rules.
+    pub fn from_name(name: &str) -> Self {
+        match name.to_ascii_lowercase().as_str() {
+            "fat" | "fat12" | "fat16" | "fat32" | "vfat" | "msdos" => FileSystemKind::Fat,
+            "exfat" => FileSystemKind::ExFat,
+            "ntfs" | "ntfs3" | "fuseblk" => FileSystemKind::Ntfs,
+            _ => FileSystemKind::Other,
+        }
+    }
+
+    /// Whether the file system follows the Windows naming rules.
+    pub fn has_windows_names(self) -> bool {
+        !matches!(self, FileSystemKind::Other)
+    }
+}
+
+/// How the length of a path component is measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentLengthUnit {
+    Utf16,
+    Bytes,
+}
+
+/// Naming and size restrictions of a destination file system.
+#[derive(Debug, Clone)]
+pub struct FileSystemRules {
+    pub kind: FileSystemKind,
+    pub maximum_component_length: u32,
+    pub component_length_unit: ComponentLengthUnit,
+    pub case_insensitive: bool,
+    pub maximum_file_size: Option<FileSize>,
+}
+
+impl FileSystemRules {
+    /// Characters that can't be used in names on file systems with Windows naming rules.
+    pub const RESERVED_CHARACTERS: &'static [char] = &['<', '>', ':', '"', '\\', '|', '?', '*'];
+
+    /// Names that can't be used on file systems with Windows naming rules, with or without an
+    /// extension.
+    pub const RESERVED_NAMES: &'static [&'static str] = &[
+        "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+        "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+
+    pub fn for_kind(kind: FileSystemKind, maximum_component_length: u32) -> Self {
+        Self {
+            kind,
+            maximum_component_length,
+            component_length_unit: if kind.has_windows_names() {
+                ComponentLengthUnit::Utf16
+            } else {
+                ComponentLengthUnit::Bytes
+            },
+            case_insensitive: kind.has_windows_names(),
+            maximum_file_size: match kind {
+                FileSystemKind::Fat => Some(4.gb() - 1.bytes()),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn for_volume(volume: &VolumeInformation) -> Self {
+        Self::for_kind(
+            FileSystemKind::from_name(&volume.file_system_name),
+            volume.maximum_component_length,
+        )
+    }
+
+    fn component_length(&self, name: &str) -> usize {
+        match self.component_length_unit {
+            ComponentLengthUnit::Utf16 => name.encode_utf16().count(),
+            ComponentLengthUnit::Bytes => name.len(),
+        }
+    }
+
+    /// Check a single path component, returning the first problem found with it.
+    pub fn check_name(&self, name: &str) -> Option<NameProblem> {
+        let length = self.component_length(name);
+        if self.maximum_component_length > 0 && length > self.maximum_component_length as usize {
+            return Some(NameProblem::TooLong {
+                length,
+                maximum: self.maximum_component_length,
+            });
+        }
+
+        if !self.kind.has_windows_names() {
+            return None;
+        }
+
+        if let Some(c) = name
+            .chars()
+            .find(|c| c.is_ascii_control() || Self::RESERVED_CHARACTERS.contains(c))
+        {
+            return Some(NameProblem::ReservedCharacter(c));
+        }
+
+        let stem = name.split('.').next().unwrap_or(name).trim_end();
+        if Self::RESERVED_NAMES
+            .iter()
+            .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+        {
+            return Some(NameProblem::ReservedName);
+        }
+
+        if name.ends_with('.') || name.ends_with(' ') {
+            return Some(NameProblem::TrailingDotOrSpace);
+        }
+
+        None
+    }
+}
+
+/// A reason why a name can't be used on the destination file system.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameProblem {
+    TooLong {
+        length: usize,
+        maximum: u32,
+    },
+    ReservedCharacter(char),
+    ReservedName,
+    TrailingDotOrSpace,
+    /// Differs only in case from another name in the same directory.
+    CaseCollision {
+        other: String,
+    },
+    FileTooLarge {
+        size: FileSize,
+        maximum: FileSize,
+    },
+}
+
+impl Display for NameProblem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NameProblem::TooLong { length, maximum } => {
+                write!(f, "name is {} long, maximum is {}", length, maximum)
+            }
+            NameProblem::ReservedCharacter(c) => write!(f, "contains reserved character {:?}", c),
+            NameProblem::ReservedName => write!(f, "is a reserved name"),
+            NameProblem::TrailingDotOrSpace => write!(f, "ends with a dot or a space"),
+            NameProblem::CaseCollision { other } => {
+                write!(f, "differs only in case from {:?}", other)
+            }
+            NameProblem::FileTooLarge { size, maximum } => {
+                write!(f, "file is {}, maximum is {}", size, maximum)
+            }
+        }
+    }
+}
+
+/// A path that won't survive being moved to the destination file system.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompatibilityIssue {
+    /// Path relative to the parent of the checked directory, so it includes the directory's name.
+    pub path: PathBuf,
+    pub problem: NameProblem,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum CompatibilityError {
+    Io(io::ErrorKind),
+    Cancelled,
+}
+
+/// Check whether the directory at `source` can be moved to `dest`, based on the file system `dest`
+/// would end up on.
+///
+/// If the destination file system can't be determined, no checks are done.
+pub async fn check_move_compatibility(
+    source: &Path,
+    dest: &Path,
+    cancellation_token: Option<&CancellationToken>,
+) -> Result<Vec<CompatibilityIssue>, CompatibilityError> {
+    let volume = match dest.get_volume_information() {
+        Ok(volume) => volume,
+        Err(err) => {
+            warn!(
+                target: "compatibility",
+                "Couldn't get volume information for {}, skipping name checks. {:?}",
+                dest.display(),
+                err
+            );
+            return Ok(Vec::new());
+        }
+    };
+
+    let rules = FileSystemRules::for_volume(&volume);
+    check_directory_compatibility(source, &rules, cancellation_token).await
+}
+
+/// Check whether the directory at `source`, including its own name, can be stored on a file
+/// system with the given `rules`.
+pub async fn check_directory_compatibility(
+    source: &Path,
+    rules: &FileSystemRules,
+    cancellation_token: Option<&CancellationToken>,
+) -> Result<Vec<CompatibilityIssue>, CompatibilityError> {
+    async fn _check(
+        dir: &Path,
+        relative: &Path,
+        rules: &FileSystemRules,
+        issues: &mut Vec<CompatibilityIssue>,
+        cancellation_token: Option<&CancellationToken>,
+    ) -> Result<(), CompatibilityError> {
+        let mut children = async_fs::read_dir(dir)
+            .await
+            .map_err(|e| CompatibilityError::Io(e.kind()))?;
+
+        let mut folded_names: HashMap<String, String> = HashMap::new();
+
+        while let Some(child) = children
+            .try_next()
+            .await
+            .map_err(|e| CompatibilityError::Io(e.kind()))?
+        {
+            if let Some(cancellation_token) = cancellation_token {
+                if cancellation_token.is_cancelled() {
+                    return Err(CompatibilityError::Cancelled);
+                }
+            }
+
+            let name = child.file_name().to_string_lossy().into_owned();
+            let child_relative = relative.join(&name);
+
+            if let Some(problem) = rules.check_name(&name) {
+                issues.push(CompatibilityIssue {
+                    path: child_relative.clone(),
+                    problem,
+                });
+            }
+
+            if rules.case_insensitive {
+                if let Some(other) = folded_names.insert(name.to_lowercase(), name.clone()) {
+                    issues.push(CompatibilityIssue {
+                        path: child_relative.clone(),
+                        problem: NameProblem::CaseCollision { other },
+                    });
+                }
+            }
+
+            let metadata = async_fs::symlink_metadata(child.path())
+                .await
+                .map_err(|e| CompatibilityError::Io(e.kind()))?;
+            if metadata.is_dir() {
+                Box::pin(_check(
+                    &child.path(),
+                    &child_relative,
+                    rules,
+                    issues,
+                    cancellation_token,
+                ))
+                .await?;
+            } else if let Some(maximum) = rules.maximum_file_size {
+                let size = metadata.len().bytes();
+                if size > maximum {
+                    issues.push(CompatibilityIssue {
+                        path: child_relative,
+                        problem: NameProblem::FileTooLarge { size, maximum },
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    let mut issues = Vec::new();
+    let name = source
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    if let Some(problem) = rules.check_name(&name) {
+        issues.push(CompatibilityIssue {
+            path: PathBuf::from(&name),
+            problem,
+        });
+    }
+
+    _check(
+        source,
+        Path::new(&name),
+        rules,
+        &mut issues,
+        cancellation_token,
+    )
+    .await?;
+
+    Ok(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kind_from_name() {
+        assert_eq!(FileSystemKind::from_name("NTFS"), FileSystemKind::Ntfs);
+        assert_eq!(FileSystemKind::from_name("ntfs3"), FileSystemKind::Ntfs);
+        assert_eq!(FileSystemKind::from_name("FAT32"), FileSystemKind::Fat);
+        assert_eq!(FileSystemKind::from_name("vfat"), FileSystemKind::Fat);
+        assert_eq!(FileSystemKind::from_name("exFAT"), FileSystemKind::ExFat);
+        assert_eq!(FileSystemKind::from_name("fuseblk"), FileSystemKind::Ntfs);
+        assert_eq!(FileSystemKind::from_name("ext4"), FileSystemKind::Other);
+    }
+
+    #[test]
+    fn test_check_name_windows() {
+        let rules = FileSystemRules::for_kind(FileSystemKind::Ntfs, 255);
+        assert_eq!(rules.check_name("Portal 2"), None);
+        assert_eq!(
+            rules.check_name("what?.txt"),
+            Some(NameProblem::ReservedCharacter('?'))
+        );
+        assert_eq!(rules.check_name("con"), Some(NameProblem::ReservedName));
+        assert_eq!(
+            rules.check_name("COM1.log"),
+            Some(NameProblem::ReservedName)
+        );
+        assert_eq!(rules.check_name("CONSOLE"), None);
+        assert_eq!(
+            rules.check_name("name."),
+            Some(NameProblem::TrailingDotOrSpace)
+        );
+        assert_eq!(
+            rules.check_name(&"a".repeat(256)),
+            Some(NameProblem::TooLong {
+                length: 256,
+                maximum: 255
+            })
+        );
+        // Counted in UTF-16 units, not bytes
+        assert_eq!(rules.check_name(&"ą".repeat(255)), None);
+    }
+
+    #[test]
+    fn test_check_name_other() {
+        let rules = FileSystemRules::for_kind(FileSystemKind::Other, 255);
+        assert_eq!(rules.check_name("what?.txt"), None);
+        assert_eq!(rules.check_name("con"), None);
+        assert_eq!(
+            rules.check_name(&"ą".repeat(128)),
+            Some(NameProblem::TooLong {
+                length: 256,
+                maximum: 255
+            })
+        );
+    }
+
+    #[test]
+    fn test_fat_file_size() {
+        let rules = FileSystemRules::for_kind(FileSystemKind::Fat, 255);
+        assert_eq!(rules.maximum_file_size, Some(4.gb() - 1.bytes()));
+        let rules = FileSystemRules::for_kind(FileSystemKind::ExFat, 255);
+        assert_eq!(rules.maximum_file_size, None);
+    }
+}
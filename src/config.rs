@@ -0,0 +1,147 @@
+use crate::project_settings::DestinationLibrary;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+
+/// Name of the file in the config directory the application config is stored in.
+pub const CONFIG_FILE_NAME: &str = "config.json";
+
+/// How many recently opened projects are remembered.
+pub const RECENT_PROJECTS_LIMIT: usize = 10;
+
+/// Settings of the application itself, shared by all projects.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    /// Projects opened most recently, newest first.
+    pub recent_projects: Vec<PathBuf>,
+    /// Libraries offered to projects that don't have any of their own.
+    pub libraries: Vec<DestinationLibrary>,
+    pub preferences: Preferences,
+    /// Whether the config file couldn't be loaded, so saving would overwrite it with the
+    /// defaults.
+    #[serde(skip)]
+    load_failed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    /// Whether to hash every moved file and record the hashes in the manifest.
+    pub hash_files: bool,
+    /// How many directories on the same device get measured at once in projects that don't set
+    /// it themselves, or `None` for the
+    /// [default](crate::stats_scheduler::DEFAULT_CONCURRENCY_PER_DEVICE).
+    pub stats_concurrency: Option<NonZeroUsize>,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            hash_files: true,
+            stats_concurrency: None,
+        }
+    }
+}
+
+impl AppConfig {
+    /// Get the path of the config file, in the XDG config directory on Linux.
+    pub fn path() -> Option<PathBuf> {
+        Some(
+            dirs::config_dir()?
+                .join(env!("CARGO_PKG_NAME").to_lowercase())
+                .join(CONFIG_FILE_NAME),
+        )
+    }
+
+    /// Load the config, or the defaults if there's none yet.
+    pub fn load() -> Result<Self, String> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+        match fs::read(&path) {
+            Ok(json) => serde_json::from_slice(&json)
+                .map_err(|e| format!("Failed to parse {}: {}", path.display(), e)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(format!("Failed to read {}: {}", path.display(), err)),
+        }
+    }
+
+    /// Load the config, or the defaults if it can't be loaded. The defaults then aren't saved
+    /// over the broken config, so it can be fixed by hand.
+    pub fn load_or_default() -> Self {
+        Self::load().unwrap_or_else(|err| {
+            warn!("Ignoring config: {}", err);
+            Self {
+                load_failed: true,
+                ..Self::default()
+            }
+        })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let Some(path) = Self::path() else {
+            return Err("No config directory to save the config to".to_string());
+        };
+        if self.load_failed {
+            return Err(format!(
+                "{} couldn't be loaded, fix it and restart Moverr to save changes to it",
+                path.display()
+            ));
+        }
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|e| format!("Failed to serialize config: {}", e))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// Remember `directory` as the most recently opened project.
+    pub fn add_recent_project(&mut self, directory: &Path) {
+        self.recent_projects.retain(|recent| recent != directory);
+        self.recent_projects.insert(0, directory.to_path_buf());
+        self.recent_projects.truncate(RECENT_PROJECTS_LIMIT);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_recent_project() {
+        let mut config = AppConfig::default();
+        for index in 0..RECENT_PROJECTS_LIMIT + 2 {
+            config.add_recent_project(&PathBuf::from(format!("/games/{}", index)));
+        }
+        assert_eq!(config.recent_projects.len(), RECENT_PROJECTS_LIMIT);
+        assert_eq!(config.recent_projects[0], PathBuf::from("/games/11"));
+
+        // Opening a project again moves it to the front instead of adding it twice
+        config.add_recent_project(Path::new("/games/5"));
+        assert_eq!(config.recent_projects.len(), RECENT_PROJECTS_LIMIT);
+        assert_eq!(config.recent_projects[0], PathBuf::from("/games/5"));
+        assert_eq!(config.recent_projects[1], PathBuf::from("/games/11"));
+    }
+
+    #[test]
+    fn test_missing_fields() {
+        let config: AppConfig = serde_json::from_str(r#"{"recent_projects": ["/games"]}"#).unwrap();
+        assert_eq!(config.recent_projects, [PathBuf::from("/games")]);
+        assert!(config.preferences.hash_files);
+    }
+
+    #[test]
+    fn test_broken_config_not_saved() {
+        let config = AppConfig {
+            load_failed: true,
+            ..AppConfig::default()
+        };
+        assert!(config.save().is_err());
+    }
+}
@@ -0,0 +1,320 @@
+use crate::file_size::num_ext::AsBytes;
+use crate::file_size::FileSize;
+use crate::manifest::hash_file;
+use crate::stats_scheduler::{device_of, DeviceId};
+use crate::sync::CancellationToken;
+use futures_lite::StreamExt;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::{fs, io};
+
+/// Files with the same content.
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup {
+    /// Size of each of the files.
+    pub size: FileSize,
+    pub hash: String,
+    /// Paths of the files, each of them a separate copy on disk.
+    pub paths: Vec<PathBuf>,
+}
+
+impl DuplicateGroup {
+    /// Space that would be freed by keeping only one of the copies.
+    pub fn reclaimable(&self) -> FileSize {
+        self.size * self.paths.len().saturating_sub(1) as u64
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct FindDuplicatesProgress {
+    /// Files found so far.
+    pub file_count: u64,
+    /// Files that have to be hashed, as another file has the same size.
+    pub total_to_hash: u64,
+    pub hashed: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum FindDuplicatesError {
+    Io(io::ErrorKind),
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    HardLink,
+    /// Copy-on-write clone, so the copies can still be changed independently later. Only
+    /// supported on Linux, on file systems like Btrfs and XFS.
+    Reflink,
+}
+
+/// A file found while looking for duplicates.
+#[derive(Debug, Clone)]
+struct FoundFile {
+    path: PathBuf,
+    size: FileSize,
+    /// Device and inode, so that hard links to the same file aren't counted as copies.
+    file_id: Option<(u64, u64)>,
+}
+
+/// Look for files with the same content in the directories at `roots`. Symlinks aren't followed,
+/// and empty files are ignored.
+///
+/// Files are grouped by size first, and only the files that share their size with another one
+/// get hashed. The groups are returned with the most reclaimable space first.
+pub async fn find_duplicates(
+    roots: &[PathBuf],
+    progress: Option<&Mutex<FindDuplicatesProgress>>,
+    cancellation_token: Option<&CancellationToken>,
+) -> Result<Vec<DuplicateGroup>, FindDuplicatesError> {
+    let mut files = Vec::new();
+    for root in roots {
+        collect_files(root, &mut files, progress, cancellation_token).await?;
+    }
+
+    let candidates = group_by_size(files);
+    if let Some(progress) = progress {
+        progress.lock().unwrap().total_to_hash = candidates.iter().map(|g| g.len() as u64).sum();
+    }
+
+    let mut groups = Vec::new();
+    for candidates in candidates {
+        let mut by_hash: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+        let size = candidates[0].size;
+        for file in candidates {
+            if let Some(cancellation_token) = cancellation_token {
+                if cancellation_token.is_cancelled() {
+                    return Err(FindDuplicatesError::Cancelled);
+                }
+            }
+            let hash = hash_file(&file.path)
+                .await
+                .map_err(|e| FindDuplicatesError::Io(e.kind()))?;
+            by_hash.entry(hash).or_default().push(file.path);
+            if let Some(progress) = progress {
+                progress.lock().unwrap().hashed += 1;
+            }
+        }
+        groups.extend(
+            by_hash
+                .into_iter()
+                .filter(|(_, paths)| paths.len() > 1)
+                .map(|(hash, paths)| DuplicateGroup { size, hash, paths }),
+        );
+    }
+
+    groups.sort_by_key(|group| std::cmp::Reverse(group.reclaimable()));
+    Ok(groups)
+}
+
+async fn collect_files(
+    dir: &Path,
+    files: &mut Vec<FoundFile>,
+    progress: Option<&Mutex<FindDuplicatesProgress>>,
+    cancellation_token: Option<&CancellationToken>,
+) -> Result<(), FindDuplicatesError> {
+    let mut children = async_fs::read_dir(dir)
+        .await
+        .map_err(|e| FindDuplicatesError::Io(e.kind()))?;
+
+    while let Some(child) = children
+        .try_next()
+        .await
+        .map_err(|e| FindDuplicatesError::Io(e.kind()))?
+    {
+        if let Some(cancellation_token) = cancellation_token {
+            if cancellation_token.is_cancelled() {
+                return Err(FindDuplicatesError::Cancelled);
+            }
+        }
+
+        let metadata = async_fs::symlink_metadata(child.path())
+            .await
+            .map_err(|e| FindDuplicatesError::Io(e.kind()))?;
+        if metadata.is_dir() {
+            Box::pin(collect_files(
+                &child.path(),
+                files,
+                progress,
+                cancellation_token,
+            ))
+            .await?;
+        } else if metadata.is_file() && metadata.len() > 0 {
+            files.push(FoundFile {
+                path: child.path(),
+                size: metadata.len().bytes(),
+                file_id: file_id(&child.path(), &metadata),
+            });
+            if let Some(progress) = progress {
+                progress.lock().unwrap().file_count += 1;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Group the files that could be copies of each other, leaving out hard links to a file that's
+/// already in the group.
+fn group_by_size(files: Vec<FoundFile>) -> Vec<Vec<FoundFile>> {
+    let mut by_size: BTreeMap<FileSize, Vec<FoundFile>> = BTreeMap::new();
+    for file in files {
+        let group = by_size.entry(file.size).or_default();
+        let is_linked =
+            file.file_id.is_some() && group.iter().any(|other| other.file_id == file.file_id);
+        if !is_linked {
+            group.push(file);
+        }
+    }
+    by_size
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .collect()
+}
+
+#[cfg(unix)]
+fn file_id(_path: &Path, metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+/// Identify the file by the serial number of its volume and its index on it, which the metadata
+/// doesn't have on Windows.
+#[cfg(windows)]
+fn file_id(path: &Path, _metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::windows::io::AsRawHandle;
+    use windows::Win32::{
+        Foundation::HANDLE,
+        Storage::FileSystem::{GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION},
+    };
+
+    let file = std::fs::File::open(path).ok()?;
+    let mut info = BY_HANDLE_FILE_INFORMATION::default();
+    unsafe { GetFileInformationByHandle(HANDLE(file.as_raw_handle()), &mut info) }.ok()?;
+    Some((
+        info.dwVolumeSerialNumber as u64,
+        ((info.nFileIndexHigh as u64) << 32) | info.nFileIndexLow as u64,
+    ))
+}
+
+/// Replace the copies in `group` with links to the first one, as far as they're on the same
+/// device and still have the same content.
+///
+/// Returns the space freed, and the copies that couldn't be replaced together with why.
+pub async fn link_duplicates(
+    group: &DuplicateGroup,
+    kind: LinkKind,
+) -> (FileSize, Vec<(PathBuf, io::Error)>) {
+    let mut freed = FileSize::ZERO;
+    let mut failed = Vec::new();
+    let Some((original, copies)) = group.paths.split_first() else {
+        return (freed, failed);
+    };
+    let device = device_of(original);
+
+    for copy in copies {
+        match link_copy(original, copy, device, &group.hash, kind).await {
+            Ok(()) => freed += group.size,
+            Err(err) => failed.push((copy.clone(), err)),
+        }
+    }
+    (freed, failed)
+}
+
+async fn link_copy(
+    original: &Path,
+    copy: &Path,
+    device: Option<DeviceId>,
+    hash: &str,
+    kind: LinkKind,
+) -> io::Result<()> {
+    if device.is_none() || device_of(copy) != device {
+        return Err(io::Error::new(
+            io::ErrorKind::CrossesDevices,
+            "not on the same device as the kept copy",
+        ));
+    }
+    // Either file could've changed since they were hashed
+    if hash_file(original).await? != hash || hash_file(copy).await? != hash {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "changed since it was hashed",
+        ));
+    }
+
+    // Link next to the copy first, so the copy is only replaced once the link exists
+    let mut temp_name = OsString::from(".");
+    temp_name.push(copy.file_name().unwrap_or_default());
+    temp_name.push(".moverr-link");
+    let temp_path = copy.with_file_name(temp_name);
+    match kind {
+        LinkKind::HardLink => fs::hard_link(original, &temp_path)?,
+        LinkKind::Reflink => reflink(original, &temp_path)?,
+    }
+    fs::rename(&temp_path, copy).inspect_err(|_| {
+        let _ = fs::remove_file(&temp_path);
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn reflink(from: &Path, to: &Path) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let source = fs::File::open(from)?;
+    let dest = fs::File::create_new(to)?;
+    if unsafe { libc::ioctl(dest.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) } != 0 {
+        let err = io::Error::last_os_error();
+        drop(dest);
+        let _ = fs::remove_file(to);
+        return Err(err);
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reflink(_from: &Path, _to: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "reflinks aren't supported on this platform",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, size: u64, file_id: Option<(u64, u64)>) -> FoundFile {
+        FoundFile {
+            path: PathBuf::from(path),
+            size: size.bytes(),
+            file_id,
+        }
+    }
+
+    #[test]
+    fn test_group_by_size() {
+        let groups = group_by_size(vec![
+            file("a/setup.exe", 10, Some((1, 1))),
+            file("b/setup.exe", 10, Some((1, 2))),
+            // Hard link to a/setup.exe
+            file("c/setup.exe", 10, Some((1, 1))),
+            file("a/unique.bin", 20, Some((1, 3))),
+        ]);
+        assert_eq!(groups.len(), 1);
+        let paths: Vec<_> = groups[0].iter().map(|file| file.path.clone()).collect();
+        assert_eq!(paths, [PathBuf::from("a/setup.exe"), "b/setup.exe".into()]);
+    }
+
+    #[test]
+    fn test_reclaimable() {
+        let group = DuplicateGroup {
+            size: 10.bytes(),
+            hash: String::new(),
+            paths: vec!["a".into(), "b".into(), "c".into()],
+        };
+        assert_eq!(group.reclaimable(), 20.bytes());
+    }
+}
@@ -1,13 +1,16 @@
 use crate::file_size::units::FileSizeUnit;
 use num_format::{CustomFormat, Grouping, ToFormattedString};
+use serde::{Deserialize, Serialize};
 use std::fmt::{Debug, Display};
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
+use std::str::FromStr;
 
 pub mod num_ext;
 pub mod units;
 
 #[repr(transparent)]
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct FileSize(u64);
 
 impl FileSize {
@@ -171,6 +174,36 @@ impl Debug for FileSize {
     }
 }
 
+/// Parses sizes like `500 GiB`, `1.5T` or `4096`, the units being powers of 1024 and the bytes
+/// being the default.
+impl FromStr for FileSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let split = s
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(s.len());
+        let (value, unit) = s.split_at(split);
+        let value: f64 = value
+            .parse()
+            .map_err(|_| format!("Invalid size: {:?}", s))?;
+        let unit = match unit.trim() {
+            "" => FileSizeUnit::Byte,
+            unit => FileSizeUnit::from_acronym(unit)
+                .or_else(|| {
+                    let mut chars = unit.chars();
+                    chars
+                        .next()
+                        .filter(|_| chars.next().is_none())
+                        .and_then(|c| FileSizeUnit::from_char(c.to_ascii_uppercase()))
+                })
+                .ok_or_else(|| format!("Unknown size unit: {:?}", unit))?,
+        };
+        Ok(Self((value * unit.to_bytes() as f64) as u64))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -196,6 +229,15 @@ mod tests {
         assert_eq!(file_size.to_string_unit(), "1.5 GiB");
     }
 
+    #[test]
+    fn test_from_str() {
+        assert_eq!("4096".parse(), Ok(FileSize::from_bytes(4096)));
+        assert_eq!("1.5 KiB".parse(), Ok(FileSize::from_bytes(1536)));
+        assert_eq!("2g".parse(), Ok(FileSize::from_bytes(2 << 30)));
+        assert!("2 GB".parse::<FileSize>().is_err());
+        assert!("lots".parse::<FileSize>().is_err());
+    }
+
     #[test]
     fn test_debug() {
         let file_size = FileSize::from_bytes(1536);
@@ -0,0 +1,335 @@
+use crate::stats_scheduler::{device_of, DeviceId};
+use smol::{Executor, Task};
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+
+/// Identifies a job for as long as it's queued or running.
+pub type JobId = u64;
+
+struct QueuedJob {
+    id: JobId,
+    name: String,
+    label: String,
+    devices: Vec<Option<DeviceId>>,
+    future: Pin<Box<dyn Future<Output = ()> + Send>>,
+    /// Puts the entry back the way it was if the job is cancelled before it starts.
+    on_cancel: Box<dyn FnOnce() + Send>,
+}
+
+struct RunningJob {
+    id: JobId,
+    name: String,
+    label: String,
+    devices: Vec<Option<DeviceId>>,
+    task: Task<()>,
+}
+
+/// A job as shown in the list of jobs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobInfo {
+    pub id: JobId,
+    /// Name of the project entry the job works on.
+    pub name: String,
+    pub label: String,
+    pub running: bool,
+}
+
+/// Runs the moves, archives and restores of project entries one at a time per device, in the
+/// order they were queued in. Jobs whose source and destination are on different devices than
+/// the running ones start right away.
+///
+/// Copying several directories at once to or from a spinning disk makes it seek back and forth
+/// between them, which is much slower than copying them one after another.
+pub struct JobQueue {
+    executor: &'static Executor<'static>,
+    queue: Vec<QueuedJob>,
+    running: Vec<RunningJob>,
+    /// Whether queued jobs are held back. Running jobs carry on.
+    paused: bool,
+    next_id: JobId,
+}
+
+impl JobQueue {
+    pub fn new(executor: &'static Executor<'static>) -> Self {
+        Self {
+            executor,
+            queue: Vec::new(),
+            running: Vec::new(),
+            paused: false,
+            next_id: 0,
+        }
+    }
+
+    /// Queue `future` working on the entry `name` with the `devices` it reads from and writes
+    /// to, see [`devices_of`]. `on_cancel` is called if the job is cancelled before it starts.
+    pub fn enqueue(
+        &mut self,
+        name: String,
+        label: String,
+        devices: Vec<Option<DeviceId>>,
+        future: impl Future<Output = ()> + Send + 'static,
+        on_cancel: impl FnOnce() + Send + 'static,
+    ) -> JobId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.queue.push(QueuedJob {
+            id,
+            name,
+            label,
+            devices,
+            future: Box::pin(future),
+            on_cancel: Box::new(on_cancel),
+        });
+        id
+    }
+
+    /// Get the position in the queue of the job working on the entry `name`, if it's waiting.
+    pub fn queue_position(&self, name: &str) -> Option<usize> {
+        self.queue.iter().position(|job| job.name == name)
+    }
+
+    pub fn queued_count(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Get the running jobs, then the queued ones in the order they'll start in.
+    pub fn jobs(&self) -> Vec<JobInfo> {
+        let running = self.running.iter().map(|job| JobInfo {
+            id: job.id,
+            name: job.name.clone(),
+            label: job.label.clone(),
+            running: true,
+        });
+        let queued = self.queue.iter().map(|job| JobInfo {
+            id: job.id,
+            name: job.name.clone(),
+            label: job.label.clone(),
+            running: false,
+        });
+        running.chain(queued).collect()
+    }
+
+    /// Describe how many jobs are running and queued, for the title of the project table.
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.running.is_empty() {
+            parts.push(format!("{} running", self.running.len()));
+        }
+        if !self.queue.is_empty() {
+            parts.push(format!("{} queued", self.queue.len()));
+        }
+        if self.paused {
+            parts.push("queue paused".to_string());
+        }
+        if parts.is_empty() {
+            String::new()
+        } else {
+            format!("Jobs: {}", parts.join(", "))
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    /// Cancel the queued job `id`. Running jobs can't be cancelled, they'd leave a partial copy
+    /// behind.
+    ///
+    /// Returns whether the job was cancelled.
+    pub fn cancel(&mut self, id: JobId) -> bool {
+        let Some(index) = self.queue.iter().position(|job| job.id == id) else {
+            return false;
+        };
+        (self.queue.remove(index).on_cancel)();
+        true
+    }
+
+    /// Move the queued job `id` one place closer to the front of the queue.
+    ///
+    /// Returns whether the job was moved.
+    pub fn move_up(&mut self, id: JobId) -> bool {
+        match self.queue.iter().position(|job| job.id == id) {
+            Some(index) if index > 0 => {
+                self.queue.swap(index - 1, index);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Move the queued job `id` one place further back in the queue.
+    ///
+    /// Returns whether the job was moved.
+    pub fn move_down(&mut self, id: JobId) -> bool {
+        match self.queue.iter().position(|job| job.id == id) {
+            Some(index) if index + 1 < self.queue.len() => {
+                self.queue.swap(index, index + 1);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Forget the finished jobs and start the queued ones whose devices aren't in use, unless
+    /// the queue is paused.
+    pub fn pump(&mut self) {
+        self.running.retain(|job| !job.task.is_finished());
+        if self.paused {
+            return;
+        }
+
+        while let Some(index) = self.next() {
+            let job = self.queue.remove(index);
+            self.running.push(RunningJob {
+                id: job.id,
+                name: job.name,
+                label: job.label,
+                devices: job.devices,
+                task: self.executor.spawn(job.future),
+            });
+        }
+    }
+
+    /// Get the index of the queued job to start next, if any can be started. A job doesn't
+    /// overtake the ones queued before it on the same devices.
+    fn next(&self) -> Option<usize> {
+        let mut busy: Vec<Option<DeviceId>> = self
+            .running
+            .iter()
+            .flat_map(|job| job.devices.iter().copied())
+            .collect();
+        for (index, job) in self.queue.iter().enumerate() {
+            if !job.devices.iter().any(|device| busy.contains(device)) {
+                return Some(index);
+            }
+            busy.extend(job.devices.iter().copied());
+        }
+        None
+    }
+}
+
+impl Drop for JobQueue {
+    /// Let the running jobs finish when the project is closed, rather than stopping them halfway.
+    fn drop(&mut self) {
+        for job in self.running.drain(..) {
+            job.task.detach();
+        }
+    }
+}
+
+/// Get the devices a job working with `paths` uses. Paths that don't exist yet, like
+/// destinations, are on the device of their closest existing ancestor.
+pub fn devices_of(paths: &[&Path]) -> Vec<Option<DeviceId>> {
+    let mut devices: Vec<_> = paths
+        .iter()
+        .map(|path| path.ancestors().find_map(device_of))
+        .collect();
+    devices.sort();
+    devices.dedup();
+    devices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn enqueue(
+        queue: &mut JobQueue,
+        name: &str,
+        devices: &[DeviceId],
+        counter: &Arc<AtomicUsize>,
+    ) -> JobId {
+        let (started, cancelled) = (counter.clone(), counter.clone());
+        queue.enqueue(
+            name.to_string(),
+            format!("Move {}", name),
+            devices.iter().copied().map(Some).collect(),
+            async move {
+                started.fetch_add(1, Ordering::SeqCst);
+            },
+            move || {
+                cancelled.fetch_add(100, Ordering::SeqCst);
+            },
+        )
+    }
+
+    fn run_until_idle(executor: &Executor, queue: &mut JobQueue) {
+        while executor.try_tick() {}
+        queue.pump();
+    }
+
+    #[test]
+    fn test_one_job_per_device() {
+        static EXECUTOR: Executor = Executor::new();
+        let counter = Arc::new(AtomicUsize::new(0));
+        let mut queue = JobQueue::new(&EXECUTOR);
+        enqueue(&mut queue, "a", &[1, 2], &counter);
+        enqueue(&mut queue, "b", &[2, 3], &counter);
+        enqueue(&mut queue, "c", &[4], &counter);
+        enqueue(&mut queue, "d", &[3], &counter);
+
+        queue.pump();
+        // "b" shares a device with "a", and "d" can't overtake "b"
+        assert_eq!(queue.queue_position("a"), None);
+        assert_eq!(queue.queue_position("b"), Some(0));
+        assert_eq!(queue.queue_position("c"), None);
+        assert_eq!(queue.queue_position("d"), Some(1));
+
+        run_until_idle(&EXECUTOR, &mut queue);
+        assert_eq!(queue.queue_position("b"), None);
+        assert_eq!(queue.queue_position("d"), Some(0));
+        run_until_idle(&EXECUTOR, &mut queue);
+        run_until_idle(&EXECUTOR, &mut queue);
+        assert!(queue.jobs().is_empty());
+        assert_eq!(counter.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn test_reorder_cancel_and_pause() {
+        static EXECUTOR: Executor = Executor::new();
+        let counter = Arc::new(AtomicUsize::new(0));
+        let mut queue = JobQueue::new(&EXECUTOR);
+        queue.toggle_pause();
+        let a = enqueue(&mut queue, "a", &[1], &counter);
+        let b = enqueue(&mut queue, "b", &[1], &counter);
+        let c = enqueue(&mut queue, "c", &[1], &counter);
+
+        queue.pump();
+        assert_eq!(queue.queued_count(), 3);
+
+        assert!(queue.move_up(c));
+        assert!(queue.move_down(a));
+        assert!(!queue.move_up(c));
+        assert!(!queue.move_down(b));
+        let names: Vec<_> = queue.jobs().into_iter().map(|job| job.name).collect();
+        assert_eq!(names, ["c", "a", "b"]);
+
+        assert!(queue.cancel(b));
+        assert!(!queue.cancel(b));
+        assert_eq!(counter.load(Ordering::SeqCst), 100);
+
+        queue.toggle_pause();
+        queue.pump();
+        assert_eq!(queue.queue_position("a"), Some(0));
+        assert!(!queue.cancel(c));
+        run_until_idle(&EXECUTOR, &mut queue);
+        run_until_idle(&EXECUTOR, &mut queue);
+        assert_eq!(counter.load(Ordering::SeqCst), 102);
+    }
+
+    #[test]
+    fn test_devices_of() {
+        let dir = std::env::temp_dir();
+        let missing = dir.join("moverr-job-queue-test/not/there");
+        let devices = devices_of(&[&dir, &missing]);
+        assert_eq!(devices, [device_of(&dir)]);
+        assert!(devices[0].is_some());
+    }
+}
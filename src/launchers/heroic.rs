@@ -0,0 +1,238 @@
+use crate::launchers::{is_process_running, replace_file, LauncherGame, LauncherProvider};
+use serde_json::Value;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Heroic, installing GOG games itself and Epic games through Legendary.
+pub struct Heroic {
+    config_dir: PathBuf,
+}
+
+impl Heroic {
+    const NAME: &'static str = "Heroic";
+
+    /// Find Heroic's config directory, of the native or the Flatpak version.
+    pub fn detect() -> Option<Self> {
+        let mut candidates = Vec::new();
+        if let Some(config) = dirs::config_dir() {
+            candidates.push(config.join("heroic"));
+        }
+        if let Some(home) = dirs::home_dir() {
+            candidates.push(home.join(".var/app/com.heroicgameslauncher.hgl/config/heroic"));
+        }
+        candidates
+            .into_iter()
+            .find(|candidate| candidate.is_dir())
+            .map(|config_dir| Self { config_dir })
+    }
+
+    fn gog_installed(&self) -> PathBuf {
+        self.config_dir.join("gog_store/installed.json")
+    }
+
+    fn gog_library(&self) -> PathBuf {
+        self.config_dir.join("gog_store/library.json")
+    }
+
+    fn legendary_installed(&self) -> PathBuf {
+        self.config_dir
+            .join("legendaryConfig/legendary/installed.json")
+    }
+}
+
+impl LauncherProvider for Heroic {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn installed_games(&self) -> Result<Vec<LauncherGame>, String> {
+        let mut games = Vec::new();
+        if let Some(installed) = read_json(&self.gog_installed())? {
+            // Titles are only in the library, the installed games are listed by ID
+            let library = read_json(&self.gog_library()).unwrap_or_default();
+            games.extend(parse_gog(
+                &installed,
+                library.as_ref(),
+                &self.gog_installed(),
+            ));
+        }
+        if let Some(installed) = read_json(&self.legendary_installed())? {
+            games.extend(parse_legendary(&installed, &self.legendary_installed()));
+        }
+        Ok(games)
+    }
+
+    fn can_update_install_path(&self) -> bool {
+        true
+    }
+
+    /// Heroic keeps the installed games in memory and writes them back when it changes them.
+    fn is_running(&self) -> bool {
+        is_process_running(&["heroic"])
+    }
+
+    fn update_install_path(&self, game: &LauncherGame, new_path: &Path) -> Result<(), String> {
+        let mut installed = read_json(&game.config_file)?
+            .ok_or_else(|| format!("{} is gone", game.config_file.display()))?;
+        if !set_install_path(&mut installed, &game.id, new_path) {
+            return Err(format!(
+                "{} isn't in {} anymore",
+                game.title,
+                game.config_file.display()
+            ));
+        }
+        let json = serde_json::to_vec_pretty(&installed)
+            .map_err(|e| format!("Failed to serialize {}: {}", game.config_file.display(), e))?;
+        replace_file(&game.config_file, &json)
+    }
+}
+
+/// Read a JSON file, or `None` if there's none.
+fn read_json(path: &Path) -> Result<Option<Value>, String> {
+    match fs::read(path) {
+        Ok(json) => serde_json::from_slice(&json)
+            .map(Some)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(format!("Failed to read {}: {}", path.display(), err)),
+    }
+}
+
+/// Get the games from GOG's `installed.json`, with their titles from `library.json`.
+fn parse_gog(installed: &Value, library: Option<&Value>, config_file: &Path) -> Vec<LauncherGame> {
+    let title = |app_name: &str| {
+        library?
+            .get("games")?
+            .as_array()?
+            .iter()
+            .find(|game| game.get("app_name").and_then(Value::as_str) == Some(app_name))?
+            .get("title")?
+            .as_str()
+            .map(str::to_string)
+    };
+    installed
+        .get("installed")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|game| {
+            let id = game.get("appName")?.as_str()?;
+            let install_path = PathBuf::from(game.get("install_path")?.as_str()?);
+            Some(LauncherGame {
+                launcher: Heroic::NAME,
+                id: id.to_string(),
+                title: title(id).unwrap_or_else(|| file_name(&install_path)),
+                install_path,
+                config_file: config_file.to_path_buf(),
+            })
+        })
+        .collect()
+}
+
+/// Get the games from Legendary's `installed.json`, which has them by their app name.
+fn parse_legendary(installed: &Value, config_file: &Path) -> Vec<LauncherGame> {
+    installed
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(id, game)| {
+            let install_path = PathBuf::from(game.get("install_path")?.as_str()?);
+            Some(LauncherGame {
+                launcher: Heroic::NAME,
+                id: id.clone(),
+                title: game
+                    .get("title")
+                    .and_then(Value::as_str)
+                    .map_or_else(|| file_name(&install_path), str::to_string),
+                install_path,
+                config_file: config_file.to_path_buf(),
+            })
+        })
+        .collect()
+}
+
+/// Set the install path of the game `id` in either kind of `installed.json`.
+fn set_install_path(installed: &mut Value, id: &str, new_path: &Path) -> bool {
+    let game = match installed.get_mut("installed").and_then(Value::as_array_mut) {
+        Some(gog_games) => gog_games
+            .iter_mut()
+            .find(|game| game.get("appName").and_then(Value::as_str) == Some(id)),
+        None => installed.get_mut(id),
+    };
+    match game.and_then(Value::as_object_mut) {
+        Some(game) => {
+            game.insert(
+                "install_path".to_string(),
+                Value::String(new_path.to_string_lossy().into_owned()),
+            );
+            true
+        }
+        None => false,
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_gog() {
+        let installed = json!({"installed": [
+            {"appName": "1207658924", "install_path": "/games/Heroic/Witcher", "platform": "windows"},
+            {"appName": "42", "install_path": "/games/Heroic/Unknown"}
+        ]});
+        let library = json!({"games": [{"app_name": "1207658924", "title": "The Witcher"}]});
+        let games = parse_gog(&installed, Some(&library), Path::new("installed.json"));
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].title, "The Witcher");
+        assert_eq!(
+            games[0].install_path,
+            PathBuf::from("/games/Heroic/Witcher")
+        );
+        assert_eq!(games[1].title, "Unknown");
+    }
+
+    #[test]
+    fn test_parse_legendary() {
+        let installed = json!({
+            "Fortnite": {"app_name": "Fortnite", "title": "Fortnite", "install_path": "/games/Heroic/Fortnite"}
+        });
+        let games = parse_legendary(&installed, Path::new("installed.json"));
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].id, "Fortnite");
+        assert_eq!(
+            games[0].install_path,
+            PathBuf::from("/games/Heroic/Fortnite")
+        );
+    }
+
+    #[test]
+    fn test_set_install_path() {
+        let mut gog = json!({"installed": [{"appName": "1", "install_path": "/old"}]});
+        assert!(set_install_path(&mut gog, "1", Path::new("/new")));
+        assert_eq!(gog["installed"][0]["install_path"], "/new");
+        assert!(!set_install_path(&mut gog, "2", Path::new("/new")));
+
+        let mut legendary = json!({"Game": {"install_path": "/old", "title": "Game"}});
+        assert!(set_install_path(&mut legendary, "Game", Path::new("/new")));
+        assert_eq!(legendary["Game"]["install_path"], "/new");
+
+        // Heroic's own key order is kept when the file is written back
+        let mut legendary: Value =
+            serde_json::from_str(r#"{"Game": {"title": "Game", "install_path": "/old"}}"#).unwrap();
+        assert!(set_install_path(&mut legendary, "Game", Path::new("/new")));
+        assert_eq!(
+            serde_json::to_string(&legendary).unwrap(),
+            r#"{"Game":{"title":"Game","install_path":"/new"}}"#
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use crate::launchers::{LauncherGame, LauncherProvider};
+use flate2::read::GzDecoder;
+use log::warn;
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The itch app, which leaves a receipt in each game it installed. It keeps the install paths in
+/// its database, so it can't be told about moved games.
+pub struct Itch {
+    apps_dir: PathBuf,
+}
+
+impl Itch {
+    const NAME: &'static str = "itch";
+
+    /// Receipt of an install, relative to the game's directory.
+    const RECEIPT_PATH: &'static str = ".itch/receipt.json.gz";
+
+    /// Find the directory itch installs games in by default.
+    pub fn detect() -> Option<Self> {
+        let apps_dir = dirs::config_dir()?.join("itch/apps");
+        apps_dir.is_dir().then_some(Self { apps_dir })
+    }
+}
+
+impl LauncherProvider for Itch {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn installed_games(&self) -> Result<Vec<LauncherGame>, String> {
+        let entries = fs::read_dir(&self.apps_dir)
+            .map_err(|e| format!("Failed to read {}: {}", self.apps_dir.display(), e))?;
+        let mut games = Vec::new();
+        for install_path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+            let receipt_path = install_path.join(Self::RECEIPT_PATH);
+            match read_receipt(&receipt_path) {
+                Ok(Some(receipt)) => {
+                    games.extend(parse_receipt(&receipt, &install_path, &receipt_path))
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    warn!(target: "launchers", "Ignoring {}: {}", receipt_path.display(), err)
+                }
+            }
+        }
+        Ok(games)
+    }
+}
+
+/// Read a gzipped receipt, or `None` if the directory has none.
+fn read_receipt(path: &Path) -> Result<Option<Value>, String> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.to_string()),
+    };
+    serde_json::from_reader(GzDecoder::new(file))
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+fn parse_receipt(receipt: &Value, install_path: &Path, config_file: &Path) -> Option<LauncherGame> {
+    let game = receipt.get("game")?;
+    let id = match game.get("id")? {
+        Value::Number(id) => id.to_string(),
+        Value::String(id) => id.clone(),
+        _ => return None,
+    };
+    let title = match game.get("title").and_then(Value::as_str) {
+        Some(title) => title.to_string(),
+        None => install_path.file_name()?.to_string_lossy().into_owned(),
+    };
+    Some(LauncherGame {
+        launcher: Itch::NAME,
+        id,
+        title,
+        install_path: install_path.to_path_buf(),
+        config_file: config_file.to_path_buf(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_receipt() {
+        let receipt = json!({"game": {"id": 12345, "title": "Celeste Classic"}, "files": []});
+        let game = parse_receipt(
+            &receipt,
+            Path::new("/itch/apps/celeste-classic"),
+            Path::new("receipt.json.gz"),
+        )
+        .unwrap();
+        assert_eq!(game.id, "12345");
+        assert_eq!(game.title, "Celeste Classic");
+
+        let receipt = json!({"game": {"id": 1}});
+        let game = parse_receipt(&receipt, Path::new("/itch/apps/foo"), Path::new("r")).unwrap();
+        assert_eq!(game.title, "foo");
+        assert_eq!(
+            parse_receipt(&json!({}), Path::new("/foo"), Path::new("r")),
+            None
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use crate::launchers::{
+    is_process_running, replace_file, replace_path, LauncherGame, LauncherProvider,
+};
+use log::warn;
+use std::fs;
+use std::path::{Path, PathBuf};
+use yaml_rust2::YamlLoader;
+
+/// Lutris, which has a YAML config for each game it installed.
+pub struct Lutris {
+    games_dirs: Vec<PathBuf>,
+}
+
+impl Lutris {
+    const NAME: &'static str = "Lutris";
+
+    /// Find the directories Lutris keeps the game configs in, of the native or the Flatpak
+    /// version. Older versions kept them in the config directory, newer in the data directory.
+    pub fn detect() -> Option<Self> {
+        let mut candidates = Vec::new();
+        if let Some(config) = dirs::config_dir() {
+            candidates.push(config.join("lutris/games"));
+        }
+        if let Some(data) = dirs::data_dir() {
+            candidates.push(data.join("lutris/games"));
+        }
+        if let Some(home) = dirs::home_dir() {
+            let flatpak = home.join(".var/app/net.lutris.Lutris");
+            candidates.push(flatpak.join("config/lutris/games"));
+            candidates.push(flatpak.join("data/lutris/games"));
+        }
+        let games_dirs: Vec<_> = candidates.into_iter().filter(|dir| dir.is_dir()).collect();
+        (!games_dirs.is_empty()).then_some(Self { games_dirs })
+    }
+}
+
+impl LauncherProvider for Lutris {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn installed_games(&self) -> Result<Vec<LauncherGame>, String> {
+        let mut games = Vec::new();
+        for games_dir in &self.games_dirs {
+            let entries = fs::read_dir(games_dir)
+                .map_err(|e| format!("Failed to read {}: {}", games_dir.display(), e))?;
+            for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+                if path.extension().is_none_or(|extension| extension != "yml") {
+                    continue;
+                }
+                let game = fs::read_to_string(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|yaml| parse_game(&yaml, &path));
+                match game {
+                    Ok(Some(game)) => games.push(game),
+                    Ok(None) => {}
+                    Err(err) => {
+                        warn!(target: "launchers", "Ignoring {}: {}", path.display(), err)
+                    }
+                }
+            }
+        }
+        Ok(games)
+    }
+
+    fn can_update_install_path(&self) -> bool {
+        true
+    }
+
+    /// Lutris writes the game's config back when its settings are saved.
+    fn is_running(&self) -> bool {
+        is_process_running(&["lutris"])
+    }
+
+    /// Rewrite the paths in the game's config. Lutris also keeps the directory in its database,
+    /// but only to show it, the config is what it launches the game from.
+    fn update_install_path(&self, game: &LauncherGame, new_path: &Path) -> Result<(), String> {
+        let yaml = fs::read_to_string(&game.config_file)
+            .map_err(|e| format!("Failed to read {}: {}", game.config_file.display(), e))?;
+        replace_file(
+            &game.config_file,
+            replace_path(&yaml, &game.install_path, new_path).as_bytes(),
+        )
+    }
+}
+
+/// Get the game from its config, or `None` if the config doesn't say where it's installed.
+fn parse_game(yaml: &str, config_file: &Path) -> Result<Option<LauncherGame>, String> {
+    let documents = YamlLoader::load_from_str(yaml).map_err(|e| e.to_string())?;
+    let Some(config) = documents.first() else {
+        return Ok(None);
+    };
+    let game = &config["game"];
+    let install_path = match (
+        game["prefix"].as_str(),
+        game["working_dir"].as_str(),
+        game["exe"].as_str(),
+    ) {
+        (Some(prefix), _, _) => PathBuf::from(prefix),
+        (None, Some(working_dir), _) => PathBuf::from(working_dir),
+        (None, None, Some(exe)) => match Path::new(exe).parent() {
+            Some(parent) if parent.is_absolute() => parent.to_path_buf(),
+            _ => return Ok(None),
+        },
+        (None, None, None) => return Ok(None),
+    };
+
+    // The config is named after the game's slug and the time it was installed
+    let id = config_file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let title = match config["name"].as_str().or(config["game_name"].as_str()) {
+        Some(name) => name.to_string(),
+        None => slug_title(&id),
+    };
+    Ok(Some(LauncherGame {
+        launcher: Lutris::NAME,
+        id,
+        title,
+        install_path,
+        config_file: config_file.to_path_buf(),
+    }))
+}
+
+/// Make a title from a config name like `half-life-2-1700000000`.
+fn slug_title(id: &str) -> String {
+    let slug = match id.rsplit_once('-') {
+        Some((slug, timestamp)) if timestamp.chars().all(|c| c.is_ascii_digit()) => slug,
+        _ => id,
+    };
+    slug.split('-')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_game() {
+        let config_file = Path::new("/lutris/games/half-life-2-1700000000.yml");
+        let yaml = "game:\n  exe: /games/hl2/hl2.exe\n  prefix: /games/hl2\nsystem: {}\n";
+        let game = parse_game(yaml, config_file).unwrap().unwrap();
+        assert_eq!(game.id, "half-life-2-1700000000");
+        assert_eq!(game.title, "Half Life 2");
+        assert_eq!(game.install_path, PathBuf::from("/games/hl2"));
+
+        let yaml = "name: Quake\ngame:\n  exe: /games/quake/quake\n";
+        let game = parse_game(yaml, config_file).unwrap().unwrap();
+        assert_eq!(game.title, "Quake");
+        assert_eq!(game.install_path, PathBuf::from("/games/quake"));
+
+        assert_eq!(parse_game("game:\n  exe: quake\n", config_file), Ok(None));
+        assert_eq!(parse_game("", config_file), Ok(None));
+        assert!(parse_game("game: [", config_file).is_err());
+    }
+
+    #[test]
+    fn test_slug_title() {
+        assert_eq!(slug_title("half-life-2-1700000000"), "Half Life 2");
+        assert_eq!(slug_title("quake"), "Quake");
+    }
+}
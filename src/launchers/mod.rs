@@ -0,0 +1,243 @@
+//! Games installed by launchers other than Steam, found in the launchers' own config files.
+
+mod heroic;
+mod itch;
+mod lutris;
+
+use heroic::Heroic;
+use itch::Itch;
+use log::warn;
+use lutris::Lutris;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// A game installed by a launcher.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LauncherGame {
+    /// Name of the launcher that installed it.
+    pub launcher: &'static str,
+    /// ID of the game in the launcher.
+    pub id: String,
+    pub title: String,
+    pub install_path: PathBuf,
+    /// The file the launcher recorded the install path in.
+    pub config_file: PathBuf,
+}
+
+/// A launcher whose installed games can be found from its config files.
+pub trait LauncherProvider {
+    fn name(&self) -> &'static str;
+
+    /// Find the games the launcher has installed.
+    fn installed_games(&self) -> Result<Vec<LauncherGame>, String>;
+
+    /// Whether the launcher can be told where a game was moved to, so that it can be moved
+    /// without leaving a symlink behind.
+    fn can_update_install_path(&self) -> bool {
+        false
+    }
+
+    /// Whether the launcher is running, and might overwrite its config while a game is moved.
+    fn is_running(&self) -> bool {
+        false
+    }
+
+    /// Record in the launcher's config that `game` was moved to `new_path`.
+    fn update_install_path(&self, game: &LauncherGame, new_path: &Path) -> Result<(), String> {
+        let _ = new_path;
+        Err(format!(
+            "{} can't be told where {} was moved to",
+            self.name(),
+            game.title
+        ))
+    }
+}
+
+/// Get the providers of the launchers set up for the current user.
+pub fn providers() -> Vec<Box<dyn LauncherProvider>> {
+    let mut providers: Vec<Box<dyn LauncherProvider>> = Vec::new();
+    if let Some(heroic) = Heroic::detect() {
+        providers.push(Box::new(heroic));
+    }
+    if let Some(lutris) = Lutris::detect() {
+        providers.push(Box::new(lutris));
+    }
+    if let Some(itch) = Itch::detect() {
+        providers.push(Box::new(itch));
+    }
+    providers
+}
+
+/// Get the provider of the launcher called `name`.
+pub fn provider(name: &str) -> Option<Box<dyn LauncherProvider>> {
+    providers()
+        .into_iter()
+        .find(|provider| provider.name() == name)
+}
+
+/// Find the games of all launchers, skipping the launchers whose config can't be read.
+pub fn installed_games() -> Vec<LauncherGame> {
+    providers()
+        .iter()
+        .flat_map(|provider| {
+            provider.installed_games().unwrap_or_else(|err| {
+                warn!(target: "launchers", "Ignoring {}: {}", provider.name(), err);
+                Vec::new()
+            })
+        })
+        .collect()
+}
+
+/// Get the directories the launchers install games in, with the launcher installing there, to
+/// offer them as projects.
+pub fn install_roots(games: &[LauncherGame]) -> Vec<(&'static str, PathBuf)> {
+    let mut roots: Vec<_> = games
+        .iter()
+        .filter_map(|game| Some((game.launcher, game.install_path.parent()?.to_path_buf())))
+        .collect();
+    roots.sort();
+    roots.dedup();
+    roots
+}
+
+/// Update `game` in its launcher's config after it was moved to `new_path`.
+pub fn update_install_path(game: &LauncherGame, new_path: &Path) -> Result<(), String> {
+    let provider =
+        provider(game.launcher).ok_or_else(|| format!("{} isn't set up anymore", game.launcher))?;
+    // The move may have waited in the job queue long enough for the launcher to be started
+    if provider.is_running() {
+        return Err(format!(
+            "{} was started during the move, close it and move the game again",
+            game.launcher
+        ));
+    }
+    provider.update_install_path(game, new_path)
+}
+
+/// Replace the contents of `path` with `contents`, by writing them to a temporary file that's
+/// renamed over it, so a crash or a full disk never leaves the launcher's config truncated.
+fn replace_file(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let mut temp_name = path.as_os_str().to_owned();
+    temp_name.push(".moverr-tmp");
+    let temp_path = PathBuf::from(temp_name);
+
+    let res = File::create(&temp_path)
+        .and_then(|mut file| file.write_all(contents).and_then(|_| file.sync_all()))
+        .map_err(|e| format!("Failed to write {}: {}", temp_path.display(), e))
+        .and_then(|_| {
+            fs::rename(&temp_path, path)
+                .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+        });
+    if res.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    res
+}
+
+/// Check whether a process whose executable is called one of `names` is running, from the
+/// process names in `/proc` on Linux and the list of processes on Windows.
+fn is_process_running(names: &[&str]) -> bool {
+    #[cfg(unix)]
+    {
+        let Ok(processes) = fs::read_dir("/proc") else {
+            return false;
+        };
+        processes.filter_map(Result::ok).any(|process| {
+            fs::read_to_string(process.path().join("comm"))
+                .is_ok_and(|comm| names.contains(&comm.trim()))
+        })
+    }
+    #[cfg(windows)]
+    {
+        names.iter().any(|name| {
+            let output = std::process::Command::new("tasklist")
+                .args(["/FI", &format!("IMAGENAME eq {}.exe", name), "/FO", "CSV", "/NH"])
+                .output();
+            match output {
+                Ok(output) => String::from_utf8_lossy(&output.stdout)
+                    .to_lowercase()
+                    .contains(&format!("\"{}.exe\"", name)),
+                // Better refuse to move games than have the launcher undo the changes
+                Err(err) => {
+                    warn!(target: "launchers", "Failed to check whether {} is running: {}", name, err);
+                    true
+                }
+            }
+        })
+    }
+}
+
+/// Replace every occurrence of the path `old` in `text` with `new`, including where it's the
+/// start of a longer path, but not where it's only the start of a longer name.
+fn replace_path(text: &str, old: &Path, new: &Path) -> String {
+    let old = old.to_string_lossy();
+    let new = new.to_string_lossy();
+    // Characters that would make a match only the start of a longer name
+    let continues_name = |c: char| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ' ');
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(old.as_ref()) {
+        let end = start + old.len();
+        let at_boundary = !rest[end..].starts_with(continues_name);
+        result.push_str(&rest[..start]);
+        result.push_str(if at_boundary { &new } else { &old });
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_path() {
+        let text = "exe: /games/Foo/foo.exe\nprefix: \"/games/Foo\"\nother: /games/Foo 2/x\n";
+        assert_eq!(
+            replace_path(text, Path::new("/games/Foo"), Path::new("/mnt/Foo")),
+            "exe: /mnt/Foo/foo.exe\nprefix: \"/mnt/Foo\"\nother: /games/Foo 2/x\n"
+        );
+    }
+
+    #[test]
+    fn test_replace_file() {
+        let dir = std::env::temp_dir().join(format!("moverr-replace-file-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("installed.json");
+        fs::write(&path, "old").unwrap();
+
+        replace_file(&path, b"new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        // Nothing is left behind when the file can't be replaced
+        assert!(replace_file(&dir.join("missing/installed.json"), b"new").is_err());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_roots() {
+        let game = |launcher, path: &str| LauncherGame {
+            launcher,
+            id: String::new(),
+            title: String::new(),
+            install_path: PathBuf::from(path),
+            config_file: PathBuf::new(),
+        };
+        let games = [
+            game("Heroic", "/games/Heroic/A"),
+            game("Heroic", "/games/Heroic/B"),
+            game("Lutris", "/games/C"),
+        ];
+        assert_eq!(
+            install_roots(&games),
+            [
+                ("Heroic", PathBuf::from("/games/Heroic")),
+                ("Lutris", PathBuf::from("/games"))
+            ]
+        );
+    }
+}
@@ -1,14 +1,29 @@
 mod app;
+mod archive;
+mod compatibility;
+mod config;
+mod duplicates;
 mod file_size;
 mod fraction;
+mod job_queue;
+mod launchers;
+mod manifest;
+mod move_filter;
 mod path_ext;
 mod popups;
 mod progress;
 mod project;
+mod project_settings;
+mod size_tree;
+mod stats_cache;
+mod stats_scheduler;
+mod steam;
 mod sync;
+mod table_view;
 mod throbber;
 mod utils;
 mod volume_information;
+mod watcher;
 mod widgets;
 
 use crate::file_size::num_ext::AsBytes;
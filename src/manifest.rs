@@ -0,0 +1,383 @@
+use crate::file_size::num_ext::AsBytes;
+use crate::file_size::FileSize;
+use crate::path_ext::{allocated_size, DirectoryStats, ProcessDirectoryProgress};
+use crate::sync::CancellationToken;
+use futures_lite::{AsyncReadExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Extension appended to the name of a moved directory to get the name of its manifest file.
+pub const MANIFEST_EXTENSION: &str = "moverr.json";
+
+/// Current version of the manifest format.
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// Name of the hash algorithm used for [`ManifestFile::hash`].
+pub const HASH_ALGORITHM: &str = "blake3";
+
+/// A manifest written next to every moved directory.
+///
+/// It records where the directory came from and what it contained at the time of the move, so
+/// that the destination library is self-describing and can be audited, relinked or restored
+/// without the original machine.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MoveManifest {
+    pub version: u32,
+    pub moverr_version: String,
+    pub original_path: PathBuf,
+    pub destination_path: PathBuf,
+    /// Seconds since the Unix epoch when the move started.
+    pub started_at: u64,
+    /// Seconds since the Unix epoch when the manifest was written.
+    pub finished_at: u64,
+    pub stats: DirectoryStats,
+    /// Algorithm used for the file hashes, if any were calculated.
+    pub hash_algorithm: Option<String>,
+    /// Files in the moved directory, keyed by their `/`-separated path relative to its root.
+    pub files: BTreeMap<String, ManifestFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub size: FileSize,
+    /// Seconds since the Unix epoch of the last modification, if the platform provides it.
+    pub modified: Option<u64>,
+    pub hash: Option<String>,
+}
+
+/// Differences between a manifest and the directory it describes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditReport {
+    /// Files recorded in the manifest that no longer exist.
+    pub missing: Vec<String>,
+    /// Files that exist but aren't recorded in the manifest.
+    pub extra: Vec<String>,
+    /// Files whose size or hash doesn't match the manifest.
+    pub corrupted: Vec<String>,
+}
+
+impl AuditReport {
+    pub fn is_clean(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.corrupted.is_empty()
+    }
+
+    /// Compare the files found on disk against the files recorded in a manifest.
+    ///
+    /// Hashes are only compared if both sides have them.
+    pub fn compare(
+        expected: &BTreeMap<String, ManifestFile>,
+        actual: &BTreeMap<String, ManifestFile>,
+    ) -> Self {
+        let mut report = Self::default();
+
+        for (path, expected_file) in expected {
+            match actual.get(path) {
+                None => report.missing.push(path.clone()),
+                Some(actual_file) => {
+                    let size_matches = expected_file.size == actual_file.size;
+                    let hash_matches = match (&expected_file.hash, &actual_file.hash) {
+                        (Some(expected_hash), Some(actual_hash)) => expected_hash == actual_hash,
+                        _ => true,
+                    };
+                    if !size_matches || !hash_matches {
+                        report.corrupted.push(path.clone());
+                    }
+                }
+            }
+        }
+
+        report.extra = actual
+            .keys()
+            .filter(|path| !expected.contains_key(*path))
+            .cloned()
+            .collect();
+
+        report
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ManifestError {
+    Io(io::ErrorKind),
+    Parse,
+    Cancelled,
+}
+
+impl MoveManifest {
+    pub fn new(original_path: &Path, destination_path: &Path) -> Self {
+        let now = unix_timestamp(SystemTime::now()).unwrap_or_default();
+        Self {
+            version: MANIFEST_VERSION,
+            moverr_version: env!("CARGO_PKG_VERSION").to_string(),
+            original_path: original_path.to_path_buf(),
+            destination_path: destination_path.to_path_buf(),
+            started_at: now,
+            finished_at: now,
+            stats: DirectoryStats::default(),
+            hash_algorithm: None,
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Get the path of the manifest belonging to the moved directory at `directory`.
+    ///
+    /// The manifest is stored next to the directory, not inside it, so that it's not part of the
+    /// moved data itself.
+    pub fn path_for(directory: &Path) -> PathBuf {
+        let mut file_name = directory
+            .file_name()
+            .map(OsString::from)
+            .unwrap_or_default();
+        file_name.push(".");
+        file_name.push(MANIFEST_EXTENSION);
+        directory.with_file_name(file_name)
+    }
+
+    /// Walk `root` and record all its files and stats in the manifest, optionally hashing their
+    /// contents.
+    pub async fn collect_files(
+        &mut self,
+        root: &Path,
+        hash: bool,
+        progress: Option<Arc<Mutex<ProcessDirectoryProgress>>>,
+        cancellation_token: Option<Arc<CancellationToken>>,
+    ) -> Result<(), ManifestError> {
+        let mut stats = DirectoryStats::default();
+        self.files =
+            collect_manifest_files(root, hash, Some(&mut stats), progress, cancellation_token)
+                .await?;
+        self.stats = stats;
+        self.hash_algorithm = hash.then(|| HASH_ALGORITHM.to_string());
+        Ok(())
+    }
+
+    pub async fn write(&mut self, path: &Path) -> Result<(), ManifestError> {
+        self.finished_at = unix_timestamp(SystemTime::now()).unwrap_or(self.started_at);
+        let json = serde_json::to_vec_pretty(self).map_err(|_| ManifestError::Parse)?;
+        async_fs::write(path, json)
+            .await
+            .map_err(|e| ManifestError::Io(e.kind()))
+    }
+
+    pub async fn read(path: &Path) -> Result<Self, ManifestError> {
+        let json = async_fs::read(path)
+            .await
+            .map_err(|e| ManifestError::Io(e.kind()))?;
+        serde_json::from_slice(&json).map_err(|_| ManifestError::Parse)
+    }
+
+    /// Re-walk the moved directory at `root` and compare it against this manifest.
+    ///
+    /// Files are re-hashed only if the manifest has hashes to compare against.
+    pub async fn audit(
+        &self,
+        root: &Path,
+        progress: Option<Arc<Mutex<ProcessDirectoryProgress>>>,
+        cancellation_token: Option<Arc<CancellationToken>>,
+    ) -> Result<AuditReport, ManifestError> {
+        let hash = self.hash_algorithm.as_deref() == Some(HASH_ALGORITHM);
+        let actual = collect_manifest_files(root, hash, None, progress, cancellation_token).await?;
+        Ok(AuditReport::compare(&self.files, &actual))
+    }
+}
+
+/// Walk `root` recursively and describe every file in it, keyed by its relative path.
+///
+/// Symlinks are recorded as files without a hash, since the moved directories aren't expected to
+/// contain any. If `stats` is given, it's filled in along the way.
+pub async fn collect_manifest_files(
+    root: &Path,
+    hash: bool,
+    stats: Option<&mut DirectoryStats>,
+    progress: Option<Arc<Mutex<ProcessDirectoryProgress>>>,
+    cancellation_token: Option<Arc<CancellationToken>>,
+) -> Result<BTreeMap<String, ManifestFile>, ManifestError> {
+    async fn _collect(
+        dir: &Path,
+        prefix: &str,
+        hash: bool,
+        files: &mut BTreeMap<String, ManifestFile>,
+        stats: &mut DirectoryStats,
+        progress: &Option<Arc<Mutex<ProcessDirectoryProgress>>>,
+        cancellation_token: &Option<Arc<CancellationToken>>,
+    ) -> Result<(), ManifestError> {
+        let mut children = async_fs::read_dir(dir)
+            .await
+            .map_err(|e| ManifestError::Io(e.kind()))?;
+
+        while let Some(child) = children
+            .try_next()
+            .await
+            .map_err(|e| ManifestError::Io(e.kind()))?
+        {
+            if let Some(cancellation_token) = cancellation_token {
+                if cancellation_token.is_cancelled() {
+                    return Err(ManifestError::Cancelled);
+                }
+            }
+
+            let child_path = child.path();
+            let relative = format!("{}{}", prefix, child.file_name().to_string_lossy());
+
+            let metadata = async_fs::symlink_metadata(&child_path)
+                .await
+                .map_err(|e| ManifestError::Io(e.kind()))?;
+            if metadata.is_dir() {
+                stats.subfolder_count += 1;
+                Box::pin(_collect(
+                    &child_path,
+                    &format!("{}/", relative),
+                    hash,
+                    files,
+                    stats,
+                    progress,
+                    cancellation_token,
+                ))
+                .await?;
+            } else {
+                if metadata.is_symlink() {
+                    stats.symlink_count += 1;
+                } else {
+                    stats.add_file(
+                        &relative,
+                        metadata.len().bytes(),
+                        allocated_size(&child_path, &metadata),
+                    );
+                }
+
+                let file_hash = if hash && !metadata.is_symlink() {
+                    Some(
+                        hash_file(&child_path)
+                            .await
+                            .map_err(|e| ManifestError::Io(e.kind()))?,
+                    )
+                } else {
+                    None
+                };
+
+                if let Some(progress) = progress.as_ref() {
+                    progress
+                        .lock()
+                        .unwrap()
+                        .process_file(metadata.len().bytes());
+                }
+
+                files.insert(
+                    relative,
+                    ManifestFile {
+                        size: metadata.len().bytes(),
+                        modified: metadata.modified().ok().and_then(unix_timestamp),
+                        hash: file_hash,
+                    },
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    let mut files = BTreeMap::new();
+    let mut local_stats = DirectoryStats::default();
+    _collect(
+        root,
+        "",
+        hash,
+        &mut files,
+        stats.unwrap_or(&mut local_stats),
+        &progress,
+        &cancellation_token,
+    )
+    .await?;
+    Ok(files)
+}
+
+/// Hash the contents of the file at `path`, returning the hex-encoded digest.
+pub async fn hash_file(path: &Path) -> io::Result<String> {
+    const BUFFER_SIZE: usize = 1024 * 1024;
+
+    let mut file = async_fs::File::open(path).await?;
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+fn unix_timestamp(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_for() {
+        assert_eq!(
+            MoveManifest::path_for(Path::new("/games/Portal 2")),
+            Path::new("/games/Portal 2.moverr.json")
+        );
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut manifest = MoveManifest::new(Path::new("/from/Game"), Path::new("/to/Game"));
+        manifest.files.insert(
+            "data/file.bin".to_string(),
+            ManifestFile {
+                size: 42.bytes(),
+                modified: Some(1),
+                hash: Some("abc".to_string()),
+            },
+        );
+
+        let json = serde_json::to_string(&manifest).unwrap();
+        let parsed: MoveManifest = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.original_path, manifest.original_path);
+        assert_eq!(parsed.files, manifest.files);
+    }
+
+    #[test]
+    fn test_audit_compare() {
+        fn file(size: u64, hash: Option<&str>) -> ManifestFile {
+            ManifestFile {
+                size: size.bytes(),
+                modified: None,
+                hash: hash.map(str::to_string),
+            }
+        }
+
+        let expected = BTreeMap::from([
+            ("same".to_string(), file(1, Some("a"))),
+            ("resized".to_string(), file(1, None)),
+            ("rehashed".to_string(), file(1, Some("a"))),
+            ("gone".to_string(), file(1, None)),
+        ]);
+        let actual = BTreeMap::from([
+            ("same".to_string(), file(1, Some("a"))),
+            ("resized".to_string(), file(2, None)),
+            ("rehashed".to_string(), file(1, Some("b"))),
+            ("new".to_string(), file(1, None)),
+        ]);
+
+        let report = AuditReport::compare(&expected, &actual);
+        assert!(!report.is_clean());
+        assert_eq!(report.missing, ["gone"]);
+        assert_eq!(report.extra, ["new"]);
+        assert_eq!(report.corrupted, ["rehashed", "resized"]);
+
+        assert!(AuditReport::compare(&expected, &expected).is_clean());
+    }
+}
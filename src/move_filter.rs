@@ -0,0 +1,178 @@
+use globset::{GlobBuilder, GlobMatcher};
+use std::sync::Arc;
+
+/// A single compiled include/exclude rule.
+#[derive(Debug, Clone)]
+struct FilterRule {
+    matcher: GlobMatcher,
+    /// Whether the rule re-includes matched paths instead of excluding them.
+    include: bool,
+    /// Whether the rule only matches directories.
+    directory_only: bool,
+}
+
+/// A set of gitignore-like rules deciding which paths inside a directory get moved.
+///
+/// Each pattern excludes the paths it matches, unless it starts with `!`, in which case it
+/// re-includes them. The last matching pattern wins. Patterns without a `/` match names at any
+/// depth, patterns ending with `/` only match directories, and an excluded directory is excluded
+/// together with everything inside it.
+///
+/// The filter keeps track of the directory it's applied to, so walks can [`descend`] into
+/// subdirectories and keep matching paths relative to the moved directory's root.
+///
+/// [`descend`]: MoveFilter::descend
+#[derive(Debug, Clone, Default)]
+pub struct MoveFilter {
+    rules: Arc<[FilterRule]>,
+    /// The patterns the rules were compiled from.
+    patterns: Arc<[String]>,
+    prefix: String,
+}
+
+impl MoveFilter {
+    /// Compile the given patterns. Empty patterns and patterns starting with `#` are skipped.
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Result<Self, String> {
+        let patterns: Vec<String> = patterns
+            .iter()
+            .map(|pattern| pattern.as_ref().trim())
+            .filter(|pattern| !pattern.is_empty() && !pattern.starts_with('#'))
+            .map(str::to_string)
+            .collect();
+        let rules = patterns
+            .iter()
+            .map(|pattern| {
+                let pattern = pattern.as_str();
+                let (include, pattern) = match pattern.strip_prefix('!') {
+                    Some(pattern) => (true, pattern),
+                    None => (false, pattern),
+                };
+                let (directory_only, pattern) = match pattern.strip_suffix('/') {
+                    Some(pattern) => (true, pattern),
+                    None => (false, pattern),
+                };
+                let glob = match pattern.strip_prefix('/') {
+                    Some(anchored) => anchored.to_string(),
+                    None if pattern.contains('/') => pattern.to_string(),
+                    None => format!("**/{}", pattern),
+                };
+                let matcher = GlobBuilder::new(&glob)
+                    .literal_separator(true)
+                    .build()
+                    .map_err(|e| format!("Invalid pattern {:?}: {}", pattern, e))?
+                    .compile_matcher();
+                Ok(FilterRule {
+                    matcher,
+                    include,
+                    directory_only,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Self {
+            rules: rules.into(),
+            patterns: patterns.into(),
+            prefix: String::new(),
+        })
+    }
+
+    /// Whether the filter can exclude anything at all.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The patterns the filter was built from, without the empty ones and comments.
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// The `/`-separated path of the current directory relative to the root the filter is applied
+    /// to, empty for the root itself.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Get the filter for the subdirectory `name` of the current directory.
+    pub fn descend(&self, name: &str) -> Self {
+        Self {
+            rules: self.rules.clone(),
+            patterns: self.patterns.clone(),
+            prefix: self.relative_path(name),
+        }
+    }
+
+    /// Get the filter for the subdirectory at the `/`-separated `path` relative to the current
+    /// directory.
+    pub fn descend_path(&self, path: &str) -> Self {
+        path.split('/')
+            .fold(self.clone(), |filter, name| filter.descend(name))
+    }
+
+    /// Get the `/`-separated path of `name` relative to the root the filter is applied to.
+    pub fn relative_path(&self, name: &str) -> String {
+        if self.prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", self.prefix, name)
+        }
+    }
+
+    /// Check whether the entry `name` in the current directory is excluded from moving.
+    pub fn is_excluded(&self, name: &str, is_dir: bool) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+
+        let path = self.relative_path(name);
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| (is_dir || !rule.directory_only) && rule.matcher.is_match(&path))
+            .is_some_and(|rule| !rule.include)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty() {
+        let filter = MoveFilter::default();
+        assert!(filter.is_empty());
+        assert!(!filter.is_excluded("anything", true));
+    }
+
+    #[test]
+    fn test_unanchored() {
+        let filter = MoveFilter::new(&["*.log", "shadercache/"]).unwrap();
+        assert!(filter.is_excluded("crash.log", false));
+        assert!(filter.descend("logs").is_excluded("crash.log", false));
+        assert!(filter.is_excluded("shadercache", true));
+        assert!(!filter.is_excluded("shadercache", false));
+        assert!(!filter.is_excluded("game.exe", false));
+    }
+
+    #[test]
+    fn test_anchored() {
+        let filter = MoveFilter::new(&["/saves", "data/cache"]).unwrap();
+        assert!(filter.is_excluded("saves", true));
+        assert!(!filter.descend("data").is_excluded("saves", true));
+        assert!(filter.descend("data").is_excluded("cache", true));
+        assert!(!filter.is_excluded("cache", true));
+        assert!(filter.descend_path("data").is_excluded("cache", true));
+        assert!(!filter.descend_path("data/cache").is_excluded("saves", true));
+    }
+
+    #[test]
+    fn test_negation() {
+        let filter = MoveFilter::new(&["*.log", "!keep.log", "# comment", ""]).unwrap();
+        assert!(filter.is_excluded("crash.log", false));
+        assert!(!filter.is_excluded("keep.log", false));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(MoveFilter::new(&["[unclosed"]).is_err());
+    }
+}
@@ -1,17 +1,21 @@
+use crate::archive;
 use crate::file_size::num_ext::AsBytes;
 use crate::file_size::FileSize;
 use crate::fraction::{Fraction, FromRatio};
+use crate::manifest::{AuditReport, ManifestError, MoveManifest};
+use crate::move_filter::MoveFilter;
 use crate::sync::CancellationToken;
-use crate::volume_information::VolumeInformation;
+use crate::volume_information::{VolumeInformation, VolumeSpace};
 use futures_lite::StreamExt;
-use log::error;
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
 use smol::fs;
+use std::collections::BTreeMap;
+use std::ops::AddAssign;
+use std::path::Component;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
-use std::{borrow::Cow, io, os::windows::ffi::OsStrExt, path::Path};
-use windows::{
-    core::PCWSTR,
-    Win32::{Foundation::MAX_PATH, Storage::FileSystem::GetVolumeInformationW},
-};
+use std::{borrow::Cow, io, path::Path};
 
 pub trait PathExt {
     /// Find the nearest existing ancestor path.
@@ -20,26 +24,34 @@ pub trait PathExt {
     fn find_nearest_anchor(self: &Self) -> Option<Cow<Path>>;
     /// Find the real volume root path.
     fn find_volume_root(self: &Self) -> Option<Cow<Path>>;
-    fn get_volume_information(&self) -> Result<VolumeInformation, Option<windows::core::Error>>;
+    /// Get information about the volume the path is on.
+    fn get_volume_information(&self) -> Result<VolumeInformation, Option<io::Error>>;
+    /// Get the free space and block size of the volume the path is on.
+    fn get_volume_space(&self) -> Result<VolumeSpace, Option<io::Error>>;
     async fn calc_directory_stats(
         &self,
+        filter: &MoveFilter,
         cancellation_token: Option<&CancellationToken>,
     ) -> Result<DirectoryStats, DirectoryStatsError>;
     async fn copy_directory(
         &self,
         dest: &Path,
+        filter: &MoveFilter,
         progress: Option<Arc<Mutex<ProcessDirectoryProgress>>>,
         cancellation_token: Option<Arc<CancellationToken>>,
     ) -> Result<(), CopyDirectoryError>;
     async fn verify_copy(
         &self,
         dest: &Path,
+        filter: &MoveFilter,
+        verification: VerificationLevel,
         progress: Option<Arc<Mutex<ProcessDirectoryProgress>>>,
         cancellation_token: Option<Arc<CancellationToken>>,
     ) -> Result<(), VerifyDirectoryError>;
     async fn move_and_symlink(
         &self,
         dest: &Path,
+        options: &MoveOptions,
         progress: Option<Arc<Mutex<MoveAndSymlinkProgress>>>,
         cancellation_token: Option<Arc<CancellationToken>>,
     ) -> Result<(), MoveAndSymlinkError>;
@@ -49,6 +61,21 @@ pub trait PathExt {
         progress: Option<Arc<Mutex<MoveBackProgress>>>,
         cancellation_token: Option<Arc<CancellationToken>>,
     ) -> Result<(), MoveBackError>;
+    /// Pack the directory into a new archive at `archive_path` and write its index next to it
+    /// once the archive is verified. The directory is left in place, for the caller to remove
+    /// once the archive is recorded.
+    async fn archive_to(
+        &self,
+        archive_path: &Path,
+        progress: Option<Arc<Mutex<ArchiveProgress>>>,
+        cancellation_token: Option<Arc<CancellationToken>>,
+    ) -> Result<(), ArchiveError>;
+    async fn restore_from_archive(
+        &self,
+        archive_path: &Path,
+        progress: Option<Arc<Mutex<RestoreProgress>>>,
+        cancellation_token: Option<Arc<CancellationToken>>,
+    ) -> Result<(), ArchiveError>;
 }
 
 impl PathExt for Path {
@@ -102,53 +129,39 @@ impl PathExt for Path {
             let metadata = anchor.symlink_metadata().ok()?;
             if metadata.file_type().is_symlink() {
                 let target = anchor.read_link().ok()?;
-                anchor = Cow::Owned(target);
+                anchor = Cow::Owned(resolve_link_target(&anchor, &target));
             } else {
                 return Some(anchor);
             }
         }
     }
 
-    fn get_volume_information(
-        self: &Self,
-    ) -> Result<VolumeInformation, Option<windows::core::Error>> {
-        let path_utf16: Vec<u16> = self
-            .find_volume_root()
+    fn get_volume_information(self: &Self) -> Result<VolumeInformation, Option<io::Error>> {
+        #[cfg(windows)]
+        let path = self.find_volume_root().ok_or(None)?;
+        // Mount points aren't anchors on Unix, so the mount table is looked up for the path instead
+        #[cfg(unix)]
+        let path = self
+            .find_nearest_existing_ancestor()
             .ok_or(None)?
-            .as_ref()
-            .as_os_str()
-            .encode_wide()
-            .collect();
-        const BUFFER_SIZE: usize = MAX_PATH as usize + 1;
-
-        let mut volume_name_utf16 = [0u16; BUFFER_SIZE];
-        let mut serial_number = 0u32;
-        let mut max_component_length = 0u32;
-        let mut flags = 0u32;
-        let mut fs_type_utf16 = [0u16; BUFFER_SIZE];
-
-        unsafe {
-            GetVolumeInformationW(
-                PCWSTR(path_utf16.as_ptr()),
-                Some(&mut volume_name_utf16),
-                Some(&mut serial_number),
-                Some(&mut max_component_length),
-                Some(&mut flags),
-                Some(&mut fs_type_utf16),
-            )
-        }?;
-
-        Ok(VolumeInformation {
-            volume_name: String::from_utf16_lossy(&volume_name_utf16),
-            volume_serial_number: serial_number,
-            maximum_component_length: max_component_length,
-            file_system_flags: flags,
-            file_system_name: String::from_utf16_lossy(&fs_type_utf16),
-        })
+            .canonicalize()
+            .map_err(Some)?;
+
+        VolumeInformation::query(path.as_ref())
+    }
+
+    fn get_volume_space(&self) -> Result<VolumeSpace, Option<io::Error>> {
+        #[cfg(windows)]
+        let path = self.find_volume_root().ok_or(None)?;
+        #[cfg(unix)]
+        let path = Cow::Borrowed(self.find_nearest_existing_ancestor().ok_or(None)?);
+
+        VolumeSpace::query(&path).map_err(Some)
     }
 
     async fn calc_directory_stats(
         &self,
+        filter: &MoveFilter,
         cancellation_token: Option<&CancellationToken>,
     ) -> Result<DirectoryStats, DirectoryStatsError> {
         let mut stats = DirectoryStats::default();
@@ -171,19 +184,28 @@ impl PathExt for Path {
             let metadata = async_fs::symlink_metadata(child.path())
                 .await
                 .map_err(|e| DirectoryStatsError::Io(e.kind()))?;
+            let name = child.file_name().to_string_lossy().into_owned();
+            if filter.is_excluded(&name, metadata.is_dir()) {
+                continue;
+            }
+
             if metadata.is_symlink() {
                 stats.symlink_count += 1;
             } else if metadata.is_dir() {
                 stats.subfolder_count += 1;
-                let child_stats =
-                    Box::pin(child.path().calc_directory_stats(cancellation_token)).await?;
-                stats.subfolder_count += child_stats.subfolder_count;
-                stats.file_count += child_stats.file_count;
-                stats.symlink_count += child_stats.symlink_count;
-                stats.size += child_stats.size;
+                let child_stats = Box::pin(
+                    child
+                        .path()
+                        .calc_directory_stats(&filter.descend(&name), cancellation_token),
+                )
+                .await?;
+                stats.add_nested(&name, &child_stats);
             } else {
-                stats.file_count += 1;
-                stats.size += metadata.len().bytes();
+                stats.add_file(
+                    &name,
+                    metadata.len().bytes(),
+                    allocated_size(&child.path(), &metadata),
+                );
             }
         }
 
@@ -193,6 +215,7 @@ impl PathExt for Path {
     async fn copy_directory(
         &self,
         dest: &Path,
+        filter: &MoveFilter,
         progress: Option<Arc<Mutex<ProcessDirectoryProgress>>>,
         cancellation_token: Option<Arc<CancellationToken>>,
     ) -> Result<(), CopyDirectoryError> {
@@ -214,6 +237,7 @@ impl PathExt for Path {
         async fn _copy_directory(
             source: &Path,
             dest: &Path,
+            filter: &MoveFilter,
             progress: &Option<Arc<Mutex<ProcessDirectoryProgress>>>,
             cancellation_token: Option<Arc<CancellationToken>>,
         ) -> Result<(), CopyDirectoryError> {
@@ -240,6 +264,11 @@ impl PathExt for Path {
                 let metadata = async_fs::symlink_metadata(&child_path)
                     .await
                     .map_err(|e| CopyDirectoryError::Io(e.kind()))?;
+                let name = child.file_name().to_string_lossy().into_owned();
+                if filter.is_excluded(&name, metadata.is_dir()) {
+                    continue;
+                }
+
                 if metadata.is_symlink() {
                     return Err(CopyDirectoryError::SymlinkEncountered);
                 } else if metadata.is_dir() {
@@ -249,6 +278,7 @@ impl PathExt for Path {
                     Box::pin(_copy_directory(
                         &child_path,
                         &child_dest,
+                        &filter.descend(&name),
                         &progress.clone(),
                         cancellation_token.clone(),
                     ))
@@ -270,7 +300,7 @@ impl PathExt for Path {
             Ok(())
         }
 
-        let result = _copy_directory(self, dest, &progress, cancellation_token).await;
+        let result = _copy_directory(self, dest, filter, &progress, cancellation_token).await;
 
         if result.is_err() {
             // Error/cancellation occurred, clean up the destination directory
@@ -293,6 +323,8 @@ impl PathExt for Path {
     async fn verify_copy(
         &self,
         dest: &Path,
+        filter: &MoveFilter,
+        verification: VerificationLevel,
         progress: Option<Arc<Mutex<ProcessDirectoryProgress>>>,
         cancellation_token: Option<Arc<CancellationToken>>,
     ) -> Result<(), VerifyDirectoryError> {
@@ -317,6 +349,11 @@ impl PathExt for Path {
             let source_metadata = async_fs::symlink_metadata(&child_path)
                 .await
                 .map_err(|e| VerifyDirectoryError::Io(e.kind()))?;
+            let name = child.file_name().to_string_lossy().into_owned();
+            if filter.is_excluded(&name, source_metadata.is_dir()) {
+                continue;
+            }
+
             let dest_metadata = async_fs::symlink_metadata(&child_dest)
                 .await
                 .map_err(|e| VerifyDirectoryError::Io(e.kind()))?;
@@ -331,6 +368,8 @@ impl PathExt for Path {
                 }
                 Box::pin(child_path.verify_copy(
                     &child_dest,
+                    &filter.descend(&name),
+                    verification,
                     progress.clone(),
                     cancellation_token.clone(),
                 ))
@@ -342,6 +381,13 @@ impl PathExt for Path {
                 if source_metadata.len() != dest_metadata.len() {
                     return Err(VerifyDirectoryError::InvalidData);
                 }
+                if verification == VerificationLevel::Contents
+                    && !files_equal(&child_path, &child_dest)
+                        .await
+                        .map_err(|e| VerifyDirectoryError::Io(e.kind()))?
+                {
+                    return Err(VerifyDirectoryError::InvalidData);
+                }
 
                 if let Some(progress) = progress.as_ref() {
                     progress
@@ -358,9 +404,12 @@ impl PathExt for Path {
     async fn move_and_symlink(
         &self,
         dest: &Path,
+        options: &MoveOptions,
         progress: Option<Arc<Mutex<MoveAndSymlinkProgress>>>,
         cancellation_token: Option<Arc<CancellationToken>>,
     ) -> Result<(), MoveAndSymlinkError> {
+        let mut manifest = MoveManifest::new(self, dest);
+
         let inner_progress = if let Some(progress) = progress.as_ref() {
             let mut progress = progress.lock().unwrap();
             progress.stage = MoveAndSymlinkStage::Copying;
@@ -370,7 +419,12 @@ impl PathExt for Path {
         };
 
         let copy_res = self
-            .copy_directory(dest, inner_progress.clone(), cancellation_token.clone())
+            .copy_directory(
+                dest,
+                &options.filter,
+                inner_progress.clone(),
+                cancellation_token.clone(),
+            )
             .await;
 
         if let Err(err) = copy_res {
@@ -392,7 +446,13 @@ impl PathExt for Path {
         }
 
         let verify_res = self
-            .verify_copy(dest, inner_progress.clone(), cancellation_token.clone())
+            .verify_copy(
+                dest,
+                &options.filter,
+                options.verification,
+                inner_progress.clone(),
+                cancellation_token.clone(),
+            )
             .await;
 
         if let Err(err) = verify_res {
@@ -404,18 +464,71 @@ impl PathExt for Path {
         }
 
         if let Some(progress) = progress.as_ref() {
-            progress.lock().unwrap().stage = MoveAndSymlinkStage::Symlinking;
+            inner_progress.as_ref().unwrap().lock().unwrap().zero();
+            progress.lock().unwrap().stage = MoveAndSymlinkStage::WritingManifest;
         }
 
-        let remove_res = async_fs::remove_dir_all(self).await;
+        // Hashing the destination rather than the source, so the manifest describes what
+        // actually landed on the destination drive
+        let manifest_res = manifest
+            .collect_files(
+                dest,
+                options.hash_files,
+                inner_progress.clone(),
+                cancellation_token.clone(),
+            )
+            .await;
+        let manifest_res = match manifest_res {
+            Ok(_) => manifest.write(&MoveManifest::path_for(dest)).await,
+            Err(err) => Err(err),
+        };
 
-        if let Err(err) = remove_res {
-            return Err(MoveAndSymlinkError::Io(err.kind()));
+        if let Err(err) = manifest_res {
+            // The original is still intact, so the copy goes away rather than blocking the next
+            // attempt
+            if let Err(err) = async_fs::remove_dir_all(dest).await {
+                error!(target: "move", "Failed to remove the copy {:?}. {:?}", dest, err);
+            }
+            let manifest_path = MoveManifest::path_for(dest);
+            if let Err(err) = async_fs::remove_file(&manifest_path).await {
+                if err.kind() != io::ErrorKind::NotFound {
+                    warn!(target: "move", "Failed to remove manifest {:?}. {:?}", manifest_path, err);
+                }
+            }
+
+            return match err {
+                ManifestError::Cancelled => Err(MoveAndSymlinkError::Cancelled),
+                ManifestError::Parse => Err(MoveAndSymlinkError::ManifestFailed),
+                ManifestError::Io(e) => Err(MoveAndSymlinkError::Io(e)),
+            };
+        }
+
+        // Whatever tracks the directory is told about the copy before the original is removed,
+        // which is up to the caller
+        if options.strategy == MoveStrategy::Relocate {
+            return Ok(());
         }
 
-        let symlink_res = async_fs::windows::symlink_dir(dest, self).await;
+        if let Some(progress) = progress.as_ref() {
+            progress.lock().unwrap().stage = MoveAndSymlinkStage::Symlinking;
+        }
+
+        let has_excluded_res = contains_excluded(self, &options.filter).await;
+
+        let link_res = match has_excluded_res {
+            // Excluded content stays in place, so only the moved parts can be linked out
+            Ok(true) => link_out(self, dest, &options.filter, options.link_type).await,
+            Ok(false) => match link_target(dest, self, options.link_type) {
+                Ok(target) => match async_fs::remove_dir_all(self).await {
+                    Ok(_) => symlink(&target, self, true).await,
+                    Err(err) => Err(err),
+                },
+                Err(err) => Err(err),
+            },
+            Err(err) => Err(err),
+        };
 
-        if let Err(err) = symlink_res {
+        if let Err(err) = link_res {
             return Err(MoveAndSymlinkError::Io(err.kind()));
         }
 
@@ -440,10 +553,28 @@ impl PathExt for Path {
             None
         };
 
-        let remove_symlink_res = async_fs::remove_dir(self).await;
+        // If the directory itself isn't a symlink, it was only partially moved and the symlinks to
+        // the moved content are somewhere inside of it
+        let is_partial = match async_fs::symlink_metadata(self).await {
+            Ok(metadata) => !metadata.is_symlink(),
+            Err(err) => return Err(MoveBackError::Io(err.kind())),
+        };
+        let links = if is_partial {
+            collect_links_to(self, dest)
+                .await
+                .map_err(|e| MoveBackError::Io(e.kind()))?
+        } else {
+            vec![PathBuf::new()]
+        };
+        let link_path = |relative: &Path| join_relative(self, relative);
+        let target_path = |relative: &Path| join_relative(dest, relative);
 
-        if let Err(err) = remove_symlink_res {
-            return Err(MoveBackError::Io(err.kind()));
+        for relative in &links {
+            let remove_symlink_res = remove_symlink(&link_path(relative)).await;
+
+            if let Err(err) = remove_symlink_res {
+                return Err(MoveBackError::Io(err.kind()));
+            }
         }
 
         if let Some(progress) = progress.as_ref() {
@@ -451,19 +582,33 @@ impl PathExt for Path {
             progress.lock().unwrap().stage = MoveBackStage::Copying;
         }
 
-        let copy_res = dest
-            .copy_directory(self, inner_progress.clone(), cancellation_token.clone())
-            .await;
-
-        if let Err(err) = copy_res {
-            return match err {
-                CopyDirectoryError::DestinationExists => {
-                    panic!("This should never happen. Destination should be the symlink.")
-                }
-                CopyDirectoryError::SymlinkEncountered => Err(MoveBackError::SymlinkEncountered),
-                CopyDirectoryError::Cancelled => Err(MoveBackError::Cancelled),
-                CopyDirectoryError::Io(e) => Err(MoveBackError::Io(e)),
+        for relative in &links {
+            let target = target_path(relative);
+            let copy_res = if target.is_dir() {
+                target
+                    .copy_directory(
+                        &link_path(relative),
+                        &MoveFilter::default(),
+                        inner_progress.clone(),
+                        cancellation_token.clone(),
+                    )
+                    .await
+            } else {
+                copy_file(&target, &link_path(relative), &inner_progress).await
             };
+
+            if let Err(err) = copy_res {
+                return match err {
+                    CopyDirectoryError::DestinationExists => {
+                        panic!("This should never happen. Destination should be the symlink.")
+                    }
+                    CopyDirectoryError::SymlinkEncountered => {
+                        Err(MoveBackError::SymlinkEncountered)
+                    }
+                    CopyDirectoryError::Cancelled => Err(MoveBackError::Cancelled),
+                    CopyDirectoryError::Io(e) => Err(MoveBackError::Io(e)),
+                };
+            }
         }
 
         if let Some(progress) = progress.as_ref() {
@@ -471,16 +616,29 @@ impl PathExt for Path {
             progress.lock().unwrap().stage = MoveBackStage::Verifying;
         }
 
-        let verify_res = self
-            .verify_copy(dest, inner_progress.clone(), cancellation_token.clone())
-            .await;
-
-        if let Err(err) = verify_res {
-            return match err {
-                VerifyDirectoryError::Cancelled => Err(MoveBackError::Cancelled),
-                VerifyDirectoryError::InvalidData => Err(MoveBackError::VerificationFailed),
-                VerifyDirectoryError::Io(e) => Err(MoveBackError::Io(e)),
+        for relative in &links {
+            let target = target_path(relative);
+            let verify_res = if target.is_dir() {
+                link_path(relative)
+                    .verify_copy(
+                        &target,
+                        &MoveFilter::default(),
+                        VerificationLevel::Sizes,
+                        inner_progress.clone(),
+                        cancellation_token.clone(),
+                    )
+                    .await
+            } else {
+                verify_file(&link_path(relative), &target, &inner_progress).await
             };
+
+            if let Err(err) = verify_res {
+                return match err {
+                    VerifyDirectoryError::Cancelled => Err(MoveBackError::Cancelled),
+                    VerifyDirectoryError::InvalidData => Err(MoveBackError::VerificationFailed),
+                    VerifyDirectoryError::Io(e) => Err(MoveBackError::Io(e)),
+                };
+            }
         }
 
         let remove_res = async_fs::remove_dir_all(dest).await;
@@ -489,12 +647,488 @@ impl PathExt for Path {
             return Err(MoveBackError::Io(err.kind()));
         }
 
+        // The manifest only describes the moved copy, so it goes away together with it
+        let manifest_path = MoveManifest::path_for(dest);
+        if let Err(err) = async_fs::remove_file(&manifest_path).await {
+            if err.kind() != io::ErrorKind::NotFound {
+                warn!(target: "move_back", "Failed to remove manifest {:?}. {:?}", manifest_path, err);
+            }
+        }
+
         if let Some(progress) = progress.as_ref() {
             progress.lock().unwrap().stage = MoveBackStage::Finished;
         }
 
         Ok(())
     }
+
+    async fn archive_to(
+        &self,
+        archive_path: &Path,
+        progress: Option<Arc<Mutex<ArchiveProgress>>>,
+        cancellation_token: Option<Arc<CancellationToken>>,
+    ) -> Result<(), ArchiveError> {
+        let mut manifest = MoveManifest::new(self, archive_path);
+
+        let inner_progress = if let Some(progress) = progress.as_ref() {
+            let mut progress = progress.lock().unwrap();
+            progress.stage = ArchiveStage::Indexing;
+            Some(progress.progress.clone())
+        } else {
+            None
+        };
+
+        if archive_path.exists() {
+            return Err(ArchiveError::DestinationExists);
+        }
+        if let Some(parent) = archive_path.parent() {
+            async_fs::create_dir_all(parent)
+                .await
+                .map_err(|e| ArchiveError::Io(e.kind()))?;
+        }
+
+        // The index is taken from the original, so the archive can be checked against it both
+        // right after packing and when restoring
+        manifest
+            .collect_files(
+                self,
+                true,
+                inner_progress.clone(),
+                cancellation_token.clone(),
+            )
+            .await
+            .map_err(ArchiveError::from)?;
+        if manifest.stats.symlink_count > 0 {
+            return Err(ArchiveError::SymlinkEncountered);
+        }
+
+        if let Some(progress) = progress.as_ref() {
+            inner_progress.as_ref().unwrap().lock().unwrap().zero();
+            progress.lock().unwrap().stage = ArchiveStage::Packing;
+        }
+
+        let pack_res = smol::unblock({
+            let root = self.to_path_buf();
+            let archive_path = archive_path.to_path_buf();
+            let inner_progress = inner_progress.clone();
+            let cancellation_token = cancellation_token.clone();
+            move || {
+                archive::pack(
+                    &root,
+                    &archive_path,
+                    inner_progress.as_deref(),
+                    cancellation_token.as_deref(),
+                )
+            }
+        })
+        .await;
+
+        if let Err(err) = pack_res {
+            // Nothing was packed if the archive already existed, so it isn't ours to remove
+            if !matches!(err, ArchiveError::DestinationExists) {
+                remove_partial_archive(archive_path).await;
+            }
+            return Err(err);
+        }
+
+        if let Some(progress) = progress.as_ref() {
+            inner_progress.as_ref().unwrap().lock().unwrap().zero();
+            progress.lock().unwrap().stage = ArchiveStage::Verifying;
+        }
+
+        let verify_res = smol::unblock({
+            let archive_path = archive_path.to_path_buf();
+            let inner_progress = inner_progress.clone();
+            let cancellation_token = cancellation_token.clone();
+            move || {
+                archive::read_files(
+                    &archive_path,
+                    inner_progress.as_deref(),
+                    cancellation_token.as_deref(),
+                )
+            }
+        })
+        .await
+        .and_then(|files| {
+            if AuditReport::compare(&manifest.files, &files).is_clean() {
+                Ok(())
+            } else {
+                Err(ArchiveError::VerificationFailed)
+            }
+        });
+        let verify_res = match verify_res {
+            Ok(_) => manifest
+                .write(&MoveManifest::path_for(archive_path))
+                .await
+                .map_err(ArchiveError::from),
+            Err(err) => Err(err),
+        };
+
+        if let Err(err) = verify_res {
+            remove_partial_archive(archive_path).await;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    async fn restore_from_archive(
+        &self,
+        archive_path: &Path,
+        progress: Option<Arc<Mutex<RestoreProgress>>>,
+        cancellation_token: Option<Arc<CancellationToken>>,
+    ) -> Result<(), ArchiveError> {
+        let inner_progress = if let Some(progress) = progress.as_ref() {
+            let mut progress = progress.lock().unwrap();
+            progress.stage = RestoreStage::Extracting;
+            Some(progress.progress.clone())
+        } else {
+            None
+        };
+
+        let index_path = MoveManifest::path_for(archive_path);
+        let manifest = MoveManifest::read(&index_path)
+            .await
+            .map_err(ArchiveError::from)?;
+
+        let unpack_res = smol::unblock({
+            let dest = self.to_path_buf();
+            let archive_path = archive_path.to_path_buf();
+            let inner_progress = inner_progress.clone();
+            let cancellation_token = cancellation_token.clone();
+            move || {
+                archive::unpack(
+                    &archive_path,
+                    &dest,
+                    inner_progress.as_deref(),
+                    cancellation_token.as_deref(),
+                )
+            }
+        })
+        .await;
+
+        if let Err(err) = unpack_res {
+            if !matches!(err, ArchiveError::DestinationExists) {
+                let _ = async_fs::remove_dir_all(self).await;
+            }
+            return Err(err);
+        }
+
+        if let Some(progress) = progress.as_ref() {
+            inner_progress.as_ref().unwrap().lock().unwrap().zero();
+            progress.lock().unwrap().stage = RestoreStage::Verifying;
+        }
+
+        let audit_res = manifest
+            .audit(self, inner_progress.clone(), cancellation_token.clone())
+            .await
+            .map_err(ArchiveError::from)
+            .and_then(|report| {
+                if report.is_clean() {
+                    Ok(())
+                } else {
+                    Err(ArchiveError::VerificationFailed)
+                }
+            });
+
+        if let Err(err) = audit_res {
+            // The archive is still intact, so a bad extraction is thrown away rather than kept
+            let _ = async_fs::remove_dir_all(self).await;
+            return Err(err);
+        }
+
+        for path in [archive_path, &index_path] {
+            if let Err(err) = async_fs::remove_file(path).await {
+                warn!(target: "archive", "Failed to remove {:?}. {:?}", path, err);
+            }
+        }
+
+        if let Some(progress) = progress.as_ref() {
+            progress.lock().unwrap().stage = RestoreStage::Finished;
+        }
+
+        Ok(())
+    }
+}
+
+/// Remove an archive that couldn't be completed, so it isn't mistaken for a valid one.
+async fn remove_partial_archive(archive_path: &Path) {
+    if let Err(err) = async_fs::remove_file(archive_path).await {
+        if err.kind() != io::ErrorKind::NotFound {
+            warn!(target: "archive", "Failed to remove partial archive {:?}. {:?}", archive_path, err);
+        }
+    }
+}
+
+/// Join `relative` to `base`, without adding a trailing separator if `relative` is empty.
+fn join_relative(base: &Path, relative: &Path) -> PathBuf {
+    if relative.as_os_str().is_empty() {
+        base.to_path_buf()
+    } else {
+        base.join(relative)
+    }
+}
+
+/// Resolve lexically what the symlink at `link` pointing to `target` points to, as an absolute
+/// path if `link` is one.
+pub fn resolve_link_target(link: &Path, target: &Path) -> PathBuf {
+    if target.is_absolute() {
+        return target.to_path_buf();
+    }
+    normalize_lexically(&link.parent().unwrap_or(Path::new("")).join(target))
+}
+
+/// Get what a symlink at `link` has to point to so it leads to `target`.
+fn link_target(target: &Path, link: &Path, link_type: LinkType) -> io::Result<PathBuf> {
+    match link_type {
+        LinkType::Absolute => Ok(target.to_path_buf()),
+        LinkType::Relative => relative_link_target(target, link),
+    }
+}
+
+/// Get the path of `target` relative to the directory the symlink at `link` is in.
+///
+/// Both have to be absolute and, on Windows, on the same drive.
+fn relative_link_target(target: &Path, link: &Path) -> io::Result<PathBuf> {
+    let is_absolute = link.is_absolute() && target.is_absolute();
+    let base = normalize_lexically(link.parent().unwrap_or(Path::new("")));
+    let target = normalize_lexically(target);
+    let base: Vec<_> = base.components().collect();
+    let target: Vec<_> = target.components().collect();
+    if !is_absolute || base.first() != target.first() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "relative links need both paths to be absolute and on the same drive",
+        ));
+    }
+
+    let common = base
+        .iter()
+        .zip(&target)
+        .take_while(|(base, target)| base == target)
+        .count();
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+    relative.extend(&target[common..]);
+    Ok(relative)
+}
+
+/// Resolve the `.` and `..` components of `path` without touching the file system.
+fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Compare the contents of the files at `a` and `b`.
+async fn files_equal(a: &Path, b: &Path) -> io::Result<bool> {
+    use futures_lite::AsyncReadExt;
+
+    const CHUNK_SIZE: usize = 1 << 16;
+    let mut a = async_fs::File::open(a).await?;
+    let mut b = async_fs::File::open(b).await?;
+    let mut a_buf = vec![0; CHUNK_SIZE];
+    let mut b_buf = vec![0; CHUNK_SIZE];
+    loop {
+        let read = a.read(&mut a_buf).await?;
+        if read == 0 {
+            // Both have the same length, so `b` is at its end too
+            return Ok(true);
+        }
+        b.read_exact(&mut b_buf[..read]).await?;
+        if a_buf[..read] != b_buf[..read] {
+            return Ok(false);
+        }
+    }
+}
+
+/// Create a symlink at `link` pointing to `target`.
+#[cfg(windows)]
+async fn symlink(target: &Path, link: &Path, is_dir: bool) -> io::Result<()> {
+    if is_dir {
+        async_fs::windows::symlink_dir(target, link).await
+    } else {
+        async_fs::windows::symlink_file(target, link).await
+    }
+}
+
+/// Create a symlink at `link` pointing to `target`.
+#[cfg(unix)]
+async fn symlink(target: &Path, link: &Path, _is_dir: bool) -> io::Result<()> {
+    async_fs::unix::symlink(target, link).await
+}
+
+/// Remove the symlink at `link` without touching its target.
+#[cfg(windows)]
+async fn remove_symlink(link: &Path) -> io::Result<()> {
+    // Directory symlinks are removed like directories on Windows
+    if link.is_dir() {
+        async_fs::remove_dir(link).await
+    } else {
+        async_fs::remove_file(link).await
+    }
+}
+
+/// Remove the symlink at `link` without touching its target.
+#[cfg(unix)]
+async fn remove_symlink(link: &Path) -> io::Result<()> {
+    async_fs::remove_file(link).await
+}
+
+/// Check whether the filter excludes anything inside the directory at `path`.
+async fn contains_excluded(path: &Path, filter: &MoveFilter) -> io::Result<bool> {
+    if filter.is_empty() {
+        return Ok(false);
+    }
+
+    let mut children = async_fs::read_dir(path).await?;
+    while let Some(child) = children.try_next().await? {
+        let name = child.file_name().to_string_lossy().into_owned();
+        let is_dir = async_fs::symlink_metadata(child.path()).await?.is_dir();
+        if filter.is_excluded(&name, is_dir) {
+            return Ok(true);
+        }
+        if is_dir && Box::pin(contains_excluded(&child.path(), &filter.descend(&name))).await? {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Replace everything inside `source` that isn't excluded by `filter` with symlinks to its copy in
+/// `dest`.
+///
+/// Directories without any excluded content are linked as a whole, the others are kept as real
+/// directories and linked out recursively.
+async fn link_out(
+    source: &Path,
+    dest: &Path,
+    filter: &MoveFilter,
+    link_type: LinkType,
+) -> io::Result<()> {
+    // Read up front, as the children are renamed and replaced while linking them out
+    let children: Vec<_> = async_fs::read_dir(source).await?.try_collect().await?;
+    for child in children {
+        let name = child.file_name().to_string_lossy().into_owned();
+        let child_path = child.path();
+        let child_dest = dest.join(child.file_name());
+        let is_dir = async_fs::symlink_metadata(&child_path).await?.is_dir();
+        if filter.is_excluded(&name, is_dir) {
+            continue;
+        }
+
+        if is_dir {
+            let child_filter = filter.descend(&name);
+            if contains_excluded(&child_path, &child_filter).await? {
+                Box::pin(link_out(&child_path, &child_dest, &child_filter, link_type)).await?;
+                continue;
+            }
+        }
+        let target = link_target(&child_dest, &child_path, link_type)?;
+        // The child is only removed once the symlink is in its place, so if linking fails
+        // partway, each child is either still there or linked to its copy
+        let unlinked_path = source.join(format!(".{}.moverr-unlinked", name));
+        async_fs::rename(&child_path, &unlinked_path).await?;
+        if let Err(err) = symlink(&target, &child_path, is_dir).await {
+            if let Err(err) = async_fs::rename(&unlinked_path, &child_path).await {
+                error!(target: "move", "Failed to put back {:?}. {:?}", child_path, err);
+            }
+            return Err(err);
+        }
+        let remove_res = if is_dir {
+            async_fs::remove_dir_all(&unlinked_path).await
+        } else {
+            async_fs::remove_file(&unlinked_path).await
+        };
+        if let Err(err) = remove_res {
+            warn!(target: "move", "Failed to remove {:?}. {:?}", unlinked_path, err);
+        }
+    }
+
+    Ok(())
+}
+
+/// Find all symlinks inside the directory at `path` that point into `target`, relative to `path`.
+pub async fn collect_links_to(path: &Path, target: &Path) -> io::Result<Vec<PathBuf>> {
+    async fn _collect(
+        dir: &Path,
+        relative: &Path,
+        target: &Path,
+        links: &mut Vec<PathBuf>,
+    ) -> io::Result<()> {
+        let mut children = async_fs::read_dir(dir).await?;
+        while let Some(child) = children.try_next().await? {
+            let child_path = child.path();
+            let child_relative = relative.join(child.file_name());
+            let metadata = async_fs::symlink_metadata(&child_path).await?;
+            if metadata.is_symlink() {
+                let link_target = async_fs::read_link(&child_path).await?;
+                if resolve_link_target(&child_path, &link_target).starts_with(target) {
+                    links.push(child_relative);
+                }
+            } else if metadata.is_dir() {
+                Box::pin(_collect(&child_path, &child_relative, target, links)).await?;
+            }
+        }
+        Ok(())
+    }
+
+    let mut links = Vec::new();
+    _collect(path, Path::new(""), target, &mut links).await?;
+    Ok(links)
+}
+
+async fn copy_file(
+    source: &Path,
+    dest: &Path,
+    progress: &Option<Arc<Mutex<ProcessDirectoryProgress>>>,
+) -> Result<(), CopyDirectoryError> {
+    let size = async_fs::copy(source, dest)
+        .await
+        .map_err(|e| CopyDirectoryError::Io(e.kind()))?;
+    if let Some(progress) = progress.as_ref() {
+        progress.lock().unwrap().process_file(size.bytes());
+    }
+    Ok(())
+}
+
+async fn verify_file(
+    source: &Path,
+    dest: &Path,
+    progress: &Option<Arc<Mutex<ProcessDirectoryProgress>>>,
+) -> Result<(), VerifyDirectoryError> {
+    let source_metadata = async_fs::symlink_metadata(source)
+        .await
+        .map_err(|e| VerifyDirectoryError::Io(e.kind()))?;
+    let dest_metadata = async_fs::symlink_metadata(dest)
+        .await
+        .map_err(|e| VerifyDirectoryError::Io(e.kind()))?;
+    if !dest_metadata.is_file() || source_metadata.len() != dest_metadata.len() {
+        return Err(VerifyDirectoryError::InvalidData);
+    }
+    if let Some(progress) = progress.as_ref() {
+        progress
+            .lock()
+            .unwrap()
+            .process_file(source_metadata.len().bytes());
+    }
+    Ok(())
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -503,12 +1137,143 @@ pub enum DirectoryStatsError {
     Cancelled,
 }
 
-#[derive(Debug, Default, Clone)]
+/// How many of the largest files [`DirectoryStats`] keeps track of.
+pub const LARGEST_FILES_COUNT: usize = 10;
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct DirectoryStats {
-    pub subfolder_count: u32,
-    pub file_count: u32,
-    pub symlink_count: u32,
+    pub subfolder_count: u64,
+    pub file_count: u64,
+    pub symlink_count: u64,
+    /// Apparent size, the sum of the lengths of the files.
     pub size: FileSize,
+    /// Space the files take up on disk, which is less than their apparent size for compressed
+    /// and sparse files, and more for files that don't fill their last block.
+    ///
+    /// Missing from manifests written before it was measured.
+    #[serde(default)]
+    pub allocated_size: FileSize,
+    /// Files by their lowercase extension, with an empty one for files without any.
+    #[serde(default)]
+    pub extensions: BTreeMap<String, ExtensionStats>,
+    /// `/`-separated paths of the largest files relative to the directory, largest first.
+    #[serde(default)]
+    pub largest_files: Vec<(String, FileSize)>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ExtensionStats {
+    pub file_count: u64,
+    pub size: FileSize,
+}
+
+impl DirectoryStats {
+    /// Count the file at the `/`-separated `relative` path inside the directory.
+    pub fn add_file(&mut self, relative: &str, size: FileSize, allocated_size: FileSize) {
+        self.file_count += 1;
+        self.size += size;
+        self.allocated_size += allocated_size;
+
+        let name = relative.rsplit('/').next().unwrap_or(relative);
+        let extension = Path::new(name)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let extension = self.extensions.entry(extension).or_default();
+        extension.file_count += 1;
+        extension.size += size;
+
+        self.add_largest_files([(relative.to_string(), size)]);
+    }
+
+    /// Add the stats of the directory at the `/`-separated `relative` path inside this one, not
+    /// counting that directory itself.
+    pub fn add_nested(&mut self, relative: &str, other: &DirectoryStats) {
+        self.subfolder_count += other.subfolder_count;
+        self.file_count += other.file_count;
+        self.symlink_count += other.symlink_count;
+        self.size += other.size;
+        self.allocated_size += other.allocated_size;
+
+        for (extension, other) in &other.extensions {
+            let extension = self.extensions.entry(extension.clone()).or_default();
+            extension.file_count += other.file_count;
+            extension.size += other.size;
+        }
+
+        self.add_largest_files(other.largest_files.iter().map(|(path, size)| {
+            let path = if relative.is_empty() {
+                path.clone()
+            } else {
+                format!("{}/{}", relative, path)
+            };
+            (path, *size)
+        }));
+    }
+
+    fn add_largest_files(&mut self, files: impl IntoIterator<Item = (String, FileSize)>) {
+        for (path, size) in files {
+            // Ties keep the file that was counted first
+            let index = self
+                .largest_files
+                .partition_point(|(_, other)| *other >= size);
+            if index < LARGEST_FILES_COUNT {
+                self.largest_files.insert(index, (path, size));
+                self.largest_files.truncate(LARGEST_FILES_COUNT);
+            }
+        }
+    }
+
+    /// Estimate the space the directory would take up on a file system with blocks of
+    /// `block_size` bytes.
+    ///
+    /// Only the totals are known, so every file is assumed to waste half a block on average, and
+    /// every directory a whole one. Compression at the destination isn't taken into account.
+    pub fn estimated_allocated_size(&self, block_size: u64) -> FileSize {
+        self.size
+            + (self.file_count * block_size / 2).bytes()
+            + (self.subfolder_count * block_size).bytes()
+    }
+}
+
+/// Adds the stats of another directory as if its content was directly inside this one.
+impl AddAssign<&DirectoryStats> for DirectoryStats {
+    fn add_assign(&mut self, rhs: &DirectoryStats) {
+        self.add_nested("", rhs);
+    }
+}
+
+/// Get the space the file at `path` with the given `metadata` takes up on disk.
+#[cfg(unix)]
+pub fn allocated_size(_path: &Path, metadata: &std::fs::Metadata) -> FileSize {
+    use std::os::unix::fs::MetadataExt;
+    // Always counted in 512-byte units, whatever the block size of the file system
+    (metadata.blocks() * 512).bytes()
+}
+
+/// Get the space the file at `path` with the given `metadata` takes up on disk.
+///
+/// This is the compressed size for compressed and sparse files, and otherwise the apparent size,
+/// as Windows doesn't tell how much of the last cluster is wasted.
+#[cfg(windows)]
+pub fn allocated_size(path: &Path, metadata: &std::fs::Metadata) -> FileSize {
+    use std::os::windows::ffi::OsStrExt;
+    use windows::{
+        core::PCWSTR,
+        Win32::Storage::FileSystem::{GetCompressedFileSizeW, INVALID_FILE_SIZE},
+    };
+
+    let path_utf16: Vec<u16> = path
+        .as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+    let mut high = 0u32;
+    let low = unsafe { GetCompressedFileSizeW(PCWSTR(path_utf16.as_ptr()), Some(&mut high)) };
+    if low == INVALID_FILE_SIZE && io::Error::last_os_error().raw_os_error() != Some(0) {
+        return metadata.len().bytes();
+    }
+    (((high as u64) << 32) | low as u64).bytes()
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -537,8 +1302,8 @@ pub enum ProcessDirectoryState {
 #[derive(Debug, Default, Clone)]
 pub struct ProcessDirectoryProgress {
     pub state: ProcessDirectoryState,
-    pub total_files: u32,
-    pub processed_files: u32,
+    pub total_files: u64,
+    pub processed_files: u64,
     pub total_size: FileSize,
     pub processed_size: FileSize,
 }
@@ -550,7 +1315,7 @@ impl From<&DirectoryStats> for ProcessDirectoryProgress {
 }
 
 impl ProcessDirectoryProgress {
-    pub fn new(total_files: u32, total_size: FileSize) -> Self {
+    pub fn new(total_files: u64, total_size: FileSize) -> Self {
         Self {
             state: ProcessDirectoryState::InProgress,
             total_files,
@@ -565,7 +1330,9 @@ impl ProcessDirectoryProgress {
     }
 
     pub fn copied_size_frac(&self) -> Fraction {
-        Fraction::from_ratio(self.processed_size, self.total_size).unwrap()
+        // Nothing to process counts as nothing processed yet
+        Fraction::from_ratio(self.processed_size, self.total_size)
+            .unwrap_or_else(|_| Fraction::try_from(0.0).unwrap())
     }
 
     pub fn zero(&mut self) {
@@ -580,12 +1347,73 @@ impl ProcessDirectoryProgress {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct MoveOptions {
+    /// Whether to hash every moved file and record the hashes in the manifest.
+    pub hash_files: bool,
+    pub verification: VerificationLevel,
+    pub link_type: LinkType,
+    pub strategy: MoveStrategy,
+    /// Which parts of the directory to move. Excluded content stays in place under a real
+    /// directory, and only the rest is linked out.
+    pub filter: MoveFilter,
+}
+
+impl Default for MoveOptions {
+    fn default() -> Self {
+        Self {
+            hash_files: true,
+            verification: VerificationLevel::default(),
+            link_type: LinkType::default(),
+            strategy: MoveStrategy::default(),
+            filter: MoveFilter::default(),
+        }
+    }
+}
+
+/// How thoroughly copies are checked against their source.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VerificationLevel {
+    /// Only compare the sizes of the files.
+    #[default]
+    Sizes,
+    /// Compare the contents of the files byte by byte, reading everything twice.
+    Contents,
+}
+
+/// How the symlinks left in place of moved content point to it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LinkType {
+    #[default]
+    Absolute,
+    /// Relative to the symlink, so the links keep working if the project and the destination
+    /// are moved together. Only possible on the same drive on Windows.
+    Relative,
+}
+
+/// What's left in place of a moved directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MoveStrategy {
+    /// A symlink to where it was moved, so nothing notices it's gone.
+    #[default]
+    Symlink,
+    /// Nothing, for directories whose location is tracked elsewhere, e.g. by Steam. Excluded
+    /// content doesn't apply, as the whole directory has to move. The original is left in place
+    /// once copied and the manifest written, to be removed after the new location is recorded.
+    Relocate,
+}
+
 #[derive(Debug, Clone, Copy, Default)]
 pub enum MoveAndSymlinkStage {
+    /// Checking whether all names can be stored on the destination file system.
+    CheckingNames,
     #[default]
     Copying,
     Verifying,
+    WritingManifest,
     Symlinking,
+    /// Removing the original of a relocated directory.
+    RemovingOriginal,
     Finished,
 }
 
@@ -595,6 +1423,7 @@ pub enum MoveAndSymlinkError {
     DestinationExists,
     SymlinkEncountered,
     VerificationFailed,
+    ManifestFailed,
     Cancelled,
 }
 
@@ -611,7 +1440,7 @@ impl From<&DirectoryStats> for MoveAndSymlinkProgress {
 }
 
 impl MoveAndSymlinkProgress {
-    pub fn new(total_files: u32, total_size: FileSize) -> Self {
+    pub fn new(total_files: u64, total_size: FileSize) -> Self {
         Self {
             stage: MoveAndSymlinkStage::Copying,
             progress: Arc::new(Mutex::new(ProcessDirectoryProgress::new(
@@ -652,7 +1481,7 @@ impl From<&DirectoryStats> for MoveBackProgress {
 }
 
 impl MoveBackProgress {
-    pub fn new(total_files: u32, total_size: FileSize) -> Self {
+    pub fn new(total_files: u64, total_size: FileSize) -> Self {
         Self {
             stage: MoveBackStage::RemovingSymlink,
             progress: Arc::new(Mutex::new(ProcessDirectoryProgress::new(
@@ -662,3 +1491,119 @@ impl MoveBackProgress {
         }
     }
 }
+
+#[derive(Debug, Clone, Copy, Default)]
+pub enum ArchiveStage {
+    /// Hashing the original, so the archive can be verified against it.
+    #[default]
+    Indexing,
+    Packing,
+    Verifying,
+    RemovingOriginal,
+    Finished,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub enum RestoreStage {
+    #[default]
+    Extracting,
+    Verifying,
+    Finished,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ArchiveError {
+    Io(io::ErrorKind),
+    DestinationExists,
+    SymlinkEncountered,
+    VerificationFailed,
+    ManifestFailed,
+    Cancelled,
+}
+
+impl From<ManifestError> for ArchiveError {
+    fn from(value: ManifestError) -> Self {
+        match value {
+            ManifestError::Io(e) => ArchiveError::Io(e),
+            ManifestError::Parse => ArchiveError::ManifestFailed,
+            ManifestError::Cancelled => ArchiveError::Cancelled,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ArchiveProgress {
+    pub stage: ArchiveStage,
+    pub progress: Arc<Mutex<ProcessDirectoryProgress>>,
+}
+
+impl From<&DirectoryStats> for ArchiveProgress {
+    fn from(value: &DirectoryStats) -> Self {
+        Self::new(value.file_count, value.size)
+    }
+}
+
+impl ArchiveProgress {
+    pub fn new(total_files: u64, total_size: FileSize) -> Self {
+        Self {
+            stage: ArchiveStage::Indexing,
+            progress: Arc::new(Mutex::new(ProcessDirectoryProgress::new(
+                total_files,
+                total_size,
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct RestoreProgress {
+    pub stage: RestoreStage,
+    pub progress: Arc<Mutex<ProcessDirectoryProgress>>,
+}
+
+impl From<&DirectoryStats> for RestoreProgress {
+    fn from(value: &DirectoryStats) -> Self {
+        Self::new(value.file_count, value.size)
+    }
+}
+
+impl RestoreProgress {
+    pub fn new(total_files: u64, total_size: FileSize) -> Self {
+        Self {
+            stage: RestoreStage::Extracting,
+            progress: Arc::new(Mutex::new(ProcessDirectoryProgress::new(
+                total_files,
+                total_size,
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_link_target() {
+        let target = relative_link_target(
+            Path::new("/mnt/hdd/Games/Game"),
+            Path::new("/home/user/Games/Game"),
+        )
+        .unwrap();
+        assert_eq!(target, Path::new("../../../mnt/hdd/Games/Game"));
+        assert_eq!(
+            resolve_link_target(Path::new("/home/user/Games/Game"), &target),
+            Path::new("/mnt/hdd/Games/Game")
+        );
+        assert!(relative_link_target(Path::new("Games/Game"), Path::new("/Game")).is_err());
+    }
+
+    #[test]
+    fn test_normalize_lexically() {
+        assert_eq!(
+            normalize_lexically(Path::new("/a/./b/../c")),
+            Path::new("/a/c")
+        );
+        assert_eq!(normalize_lexically(Path::new("../a")), Path::new("../a"));
+    }
+}
@@ -0,0 +1,238 @@
+use crate::app::MoverrApp;
+use crate::file_size::FileSize;
+use crate::popups::{Popup, PopupFn};
+use crate::project::{ProjectDirectoryEntry, ProjectState};
+use crate::utils::{impl_as_any_mut, AsAny, AsAnyMut};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use log::{error, info, warn};
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::prelude::StatefulWidget;
+use ratatui::prelude::Widget;
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, BorderType, Clear, List, ListItem, ListState, Padding};
+
+/// An operation run on all marked directories at once. Moving them has its own dialog, see
+/// [`MoveDialogPopup::for_marked`](crate::popups::MoveDialogPopup::for_marked).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchOperation {
+    MoveBack,
+    Rescan,
+    /// Audit the moved directories against their manifests.
+    Verify,
+}
+
+impl BatchOperation {
+    pub fn label(self) -> &'static str {
+        match self {
+            BatchOperation::MoveBack => "Move back",
+            BatchOperation::Rescan => "Rescan",
+            BatchOperation::Verify => "Verify",
+        }
+    }
+
+    fn applies_to(self, dir: &ProjectDirectoryEntry) -> bool {
+        match self {
+            BatchOperation::MoveBack => dir.can_be_moved_back(),
+            BatchOperation::Rescan => !dir.is_busy(),
+            BatchOperation::Verify => dir.is_moved() && !dir.is_being_audited(),
+        }
+    }
+}
+
+/// Popup confirming an operation on the marked directories, with how much they add up to.
+pub struct BatchPopup {
+    operation: BatchOperation,
+    /// The marked directories the operation applies to, with their sizes if known.
+    entries: Vec<(String, Option<FileSize>)>,
+    /// How many of the marked directories the operation doesn't apply to.
+    skipped: usize,
+    list_state: ListState,
+}
+
+impl BatchPopup {
+    /// Create the popup for running `operation` on the directories marked in `project`. Returns
+    /// `None` if it doesn't apply to any of them.
+    pub fn new(project: &ProjectState, operation: BatchOperation) -> Option<Self> {
+        let (applicable, skipped): (Vec<_>, Vec<_>) = project
+            .marked_directories()
+            .partition(|dir| operation.applies_to(dir));
+        if applicable.is_empty() {
+            return None;
+        }
+        Some(Self {
+            operation,
+            entries: applicable
+                .into_iter()
+                .map(|dir| (dir.name.clone(), dir.size()))
+                .collect(),
+            skipped: skipped.len(),
+            list_state: ListState::default(),
+        })
+    }
+
+    /// Describe the total size of the entries, and how many were left out.
+    fn summary(&self) -> String {
+        let total = self
+            .entries
+            .iter()
+            .filter_map(|(_, size)| *size)
+            .fold(FileSize::ZERO, |total, size| total + size);
+        let unknown = self
+            .entries
+            .iter()
+            .filter(|(_, size)| size.is_none())
+            .count();
+        let mut summary = format!("{} in {} directories", total, self.entries.len());
+        if unknown > 0 {
+            summary.push_str(&format!(", {} of unknown size", unknown));
+        }
+        if self.skipped > 0 {
+            summary.push_str(&format!(", {} marked ones left out", self.skipped));
+        }
+        summary
+    }
+
+    fn confirm(state: &mut MoverrApp) {
+        // Popup shouldn't have changed
+        let popup = state.try_get_popup_mut::<BatchPopup>().unwrap();
+        let operation = popup.operation;
+        let names: Vec<_> = popup.entries.iter().map(|(name, _)| name.clone()).collect();
+        state.close_popup();
+
+        let Some(project) = state.project_state.as_mut() else {
+            return;
+        };
+        match operation {
+            BatchOperation::MoveBack => {
+                for name in &names {
+                    let started = project
+                        .find_directory(name)
+                        .is_some_and(|dir| dir.try_start_move_back(project).is_ok());
+                    if !started {
+                        error!("Directory {:?} couldn't be moved back!", name);
+                    }
+                }
+            }
+            BatchOperation::Rescan => {
+                for name in &names {
+                    info!(target: "project", "Rescanning {}", name);
+                    project.rescan(name);
+                }
+            }
+            BatchOperation::Verify => {
+                let count = project.start_audit(Some(&names));
+                if count > 0 {
+                    info!(target: "audit", "Auditing {} moved directories", count);
+                } else {
+                    warn!(target: "audit", "No moved directories to audit!");
+                }
+            }
+        }
+        project.clear_marks();
+    }
+}
+
+impl Popup for BatchPopup {
+    fn render(&mut self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        Clear.render(area, buf);
+
+        let block = Block::bordered()
+            .border_type(BorderType::Thick)
+            .padding(Padding::horizontal(1))
+            .title(format!(
+                "{} {} marked directories",
+                self.operation.label(),
+                self.entries.len()
+            ))
+            .title_bottom(Line::from("[Enter] Confirm [Esc] Cancel").right_aligned());
+        let inner_area = block.inner(area);
+        block.render(area, buf);
+
+        let [summary_area, list_area] =
+            Layout::vertical([Constraint::Length(2), Constraint::Fill(1)]).areas(inner_area);
+
+        buf.set_line(
+            summary_area.x,
+            summary_area.y,
+            &Line::from(self.summary()).bold(),
+            summary_area.width,
+        );
+
+        let items: Vec<_> = self
+            .entries
+            .iter()
+            .map(|(name, size)| {
+                ListItem::new(format!(
+                    "{:>11} {}",
+                    size.map_or_else(|| "?".to_string(), |size| size.to_string()),
+                    name
+                ))
+            })
+            .collect();
+        let list = List::new(items)
+            .highlight_symbol("> ")
+            .highlight_style(Style::default().reversed());
+        StatefulWidget::render(list, list_area, buf, &mut self.list_state);
+    }
+
+    fn handle_key_event(&mut self, key_event: KeyEvent) -> Option<&'static PopupFn> {
+        if key_event.kind != KeyEventKind::Press {
+            return None;
+        }
+
+        match key_event.code {
+            KeyCode::Esc => Some(&|state: &mut MoverrApp| {
+                state.close_popup();
+            }),
+            KeyCode::Enter => Some(&Self::confirm),
+            KeyCode::Up => {
+                self.list_state.select_previous();
+                None
+            }
+            KeyCode::Down => {
+                self.list_state.select_next();
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+impl_as_any_mut!(BatchPopup);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_size::num_ext::AsBytes;
+
+    #[test]
+    fn test_summary() {
+        let mut popup = BatchPopup {
+            operation: BatchOperation::Rescan,
+            entries: vec![
+                ("a".to_string(), Some(1000.bytes())),
+                ("b".to_string(), Some(24.bytes())),
+            ],
+            skipped: 0,
+            list_state: ListState::default(),
+        };
+        assert_eq!(
+            popup.summary(),
+            format!("{} in 2 directories", 1024.bytes())
+        );
+        popup.entries.push(("c".to_string(), None));
+        popup.skipped = 2;
+        assert_eq!(
+            popup.summary(),
+            format!(
+                "{} in 3 directories, 1 of unknown size, 2 marked ones left out",
+                1024.bytes()
+            )
+        );
+    }
+}
@@ -0,0 +1,280 @@
+use crate::app::MoverrApp;
+use crate::duplicates::{
+    find_duplicates, link_duplicates, DuplicateGroup, FindDuplicatesError, FindDuplicatesProgress,
+    LinkKind,
+};
+use crate::file_size::FileSize;
+use crate::popups::{Popup, PopupFn};
+use crate::utils::{impl_as_any_mut, AsAny, AsAnyMut};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use log::{info, warn};
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::prelude::StatefulWidget;
+use ratatui::prelude::Widget;
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, BorderType, Clear, List, ListItem, ListState, Padding};
+use smol::{spawn, Task};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+type Groups = Arc<Mutex<Option<Result<Vec<DuplicateGroup>, FindDuplicatesError>>>>;
+
+/// Popup listing the files that are stored more than once across the project, and replacing the
+/// copies with links on request.
+pub struct DuplicatesPopup {
+    project_directory: PathBuf,
+    groups: Groups,
+    progress: Arc<Mutex<FindDuplicatesProgress>>,
+    /// Dropping the task stops the scan when the popup is closed.
+    _scan: Task<()>,
+    /// Whether the copies of a group are being replaced with links. That isn't stopped when the
+    /// popup is closed, so no copy is left half replaced.
+    linking: Arc<AtomicBool>,
+    /// Outcome of the last replacement.
+    message: Arc<Mutex<Option<String>>>,
+    list_state: ListState,
+}
+
+impl DuplicatesPopup {
+    pub fn new(project_directory: PathBuf, locations: Vec<PathBuf>) -> Self {
+        let groups: Groups = Arc::new(Mutex::new(None));
+        let progress = Arc::new(Mutex::new(FindDuplicatesProgress::default()));
+
+        let scan = spawn({
+            let groups = groups.clone();
+            let progress = progress.clone();
+            async move {
+                let result = find_duplicates(&locations, Some(&progress), None).await;
+                if let Ok(ref found) = result {
+                    let reclaimable = found
+                        .iter()
+                        .fold(FileSize::ZERO, |total, group| total + group.reclaimable());
+                    info!(
+                        target: "project",
+                        "Found {} groups of duplicates, {} reclaimable",
+                        found.len(),
+                        reclaimable
+                    );
+                }
+                *groups.lock().unwrap() = Some(result);
+            }
+        });
+
+        Self {
+            project_directory,
+            groups,
+            progress,
+            _scan: scan,
+            linking: Arc::new(AtomicBool::new(false)),
+            message: Arc::new(Mutex::new(None)),
+            list_state: ListState::default().with_selected(Some(0)),
+        }
+    }
+
+    /// Show `path` relative to the project directory, if it's inside it.
+    fn display_path(&self, path: &Path) -> String {
+        path.strip_prefix(&self.project_directory)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    }
+
+    fn selected_group(&self) -> Option<DuplicateGroup> {
+        let groups = self.groups.lock().unwrap();
+        let Some(Ok(groups)) = groups.as_ref() else {
+            return None;
+        };
+        groups.get(self.list_state.selected()?).cloned()
+    }
+
+    fn link_selected(&mut self, kind: LinkKind) {
+        let Some(group) = self.selected_group() else {
+            return;
+        };
+        if self.linking.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        *self.message.lock().unwrap() = Some("Linking…".to_string());
+        let groups = self.groups.clone();
+        let message = self.message.clone();
+        let linking = self.linking.clone();
+        spawn(async move {
+            let (freed, failed) = link_duplicates(&group, kind).await;
+            for (path, err) in &failed {
+                warn!(target: "project", "Failed to link {}: {}", path.display(), err);
+            }
+            info!(target: "project", "Linked duplicates of {}, freeing {}", group.hash, freed);
+
+            // Only the copies that weren't replaced are still duplicates
+            if let Some(Ok(groups)) = groups.lock().unwrap().as_mut() {
+                if let Some(index) = groups.iter().position(|other| other.hash == group.hash) {
+                    groups[index].paths.retain(|path| {
+                        *path == group.paths[0] || failed.iter().any(|(failed, _)| failed == path)
+                    });
+                    if groups[index].paths.len() < 2 {
+                        groups.remove(index);
+                    }
+                }
+            }
+            *message.lock().unwrap() = Some(if failed.is_empty() {
+                format!("Freed {}", freed)
+            } else {
+                format!(
+                    "Freed {}, {} copies couldn't be linked",
+                    freed,
+                    failed.len()
+                )
+            });
+            linking.store(false, Ordering::SeqCst);
+        })
+        .detach();
+    }
+}
+
+impl Popup for DuplicatesPopup {
+    fn render(&mut self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        Clear.render(area, buf);
+
+        let block = Block::bordered()
+            .border_type(BorderType::Thick)
+            .padding(Padding::horizontal(1))
+            .title("Duplicate files")
+            .title_bottom(
+                Line::from("[H] Hard link copies [L] Reflink copies [Esc] Close").right_aligned(),
+            );
+        let inner_area = block.inner(area);
+        block.render(area, buf);
+
+        let [list_area, paths_area, hint_area] = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(6),
+            Constraint::Length(1),
+        ])
+        .areas(inner_area);
+
+        let groups = self.groups.lock().unwrap();
+        let (items, hint): (Vec<ListItem>, Line) = match groups.as_ref() {
+            None => {
+                let progress = self.progress.lock().unwrap();
+                let hint = if progress.total_to_hash == 0 {
+                    format!("Looking for files… {} so far", progress.file_count)
+                } else {
+                    format!(
+                        "Comparing files… {}/{}",
+                        progress.hashed, progress.total_to_hash
+                    )
+                };
+                (Vec::new(), Line::from(hint).gray())
+            }
+            Some(Err(FindDuplicatesError::Io(kind))) => (
+                Vec::new(),
+                Line::from(format!("Failed to look for duplicates: {}", kind)).red(),
+            ),
+            Some(Err(FindDuplicatesError::Cancelled)) => (Vec::new(), Line::default()),
+            Some(Ok(groups)) => {
+                let items = groups
+                    .iter()
+                    .map(|group| {
+                        ListItem::new(format!(
+                            "{:>11} reclaimable  {} × {:>11}  {}",
+                            group.reclaimable().to_string(),
+                            group.paths.len(),
+                            group.size.to_string(),
+                            self.display_path(&group.paths[0]),
+                        ))
+                    })
+                    .collect();
+                let hint = match self.message.lock().unwrap().as_ref() {
+                    Some(message) => Line::from(message.clone()),
+                    None if groups.is_empty() => Line::from("No duplicates found").gray(),
+                    None => {
+                        let reclaimable = groups
+                            .iter()
+                            .fold(FileSize::ZERO, |total, group| total + group.reclaimable());
+                        Line::from(format!(
+                            "{} groups, {} reclaimable",
+                            groups.len(),
+                            reclaimable
+                        ))
+                    }
+                };
+                (items, hint)
+            }
+        };
+        drop(groups);
+
+        buf.set_line(
+            hint_area.x,
+            hint_area.y,
+            &hint.right_aligned(),
+            hint_area.width,
+        );
+
+        let list = List::new(items)
+            .highlight_symbol("> ")
+            .highlight_style(Style::default().reversed());
+        StatefulWidget::render(list, list_area, buf, &mut self.list_state);
+
+        if let Some(group) = self.selected_group() {
+            let paths = group.paths.iter().enumerate().map(|(index, path)| {
+                let path = self.display_path(path);
+                if index == 0 {
+                    ListItem::new(format!("{} (kept)", path)).green()
+                } else {
+                    ListItem::new(path)
+                }
+            });
+            Widget::render(
+                List::new(paths).block(Block::new().title("Copies").bold()),
+                paths_area,
+                buf,
+            );
+        }
+    }
+
+    fn handle_key_event(&mut self, key_event: KeyEvent) -> Option<&'static PopupFn> {
+        if key_event.kind != KeyEventKind::Press {
+            return None;
+        }
+
+        match key_event.code {
+            KeyCode::Esc => Some(&|state: &mut MoverrApp| {
+                state.close_popup();
+            }),
+            KeyCode::Up => {
+                self.list_state.select_previous();
+                None
+            }
+            KeyCode::Down => {
+                self.list_state.select_next();
+                None
+            }
+            KeyCode::Home => {
+                self.list_state.select_first();
+                None
+            }
+            KeyCode::End => {
+                self.list_state.select_last();
+                None
+            }
+            KeyCode::Char('h') => {
+                self.link_selected(LinkKind::HardLink);
+                None
+            }
+            KeyCode::Char('l') => {
+                self.link_selected(LinkKind::Reflink);
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+impl_as_any_mut!(DuplicatesPopup);
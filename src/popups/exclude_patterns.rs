@@ -0,0 +1,169 @@
+use crate::app::MoverrApp;
+use crate::move_filter::MoveFilter;
+use crate::popups::{Popup, PopupFn};
+use crate::utils::{impl_as_any_mut, AsAny, AsAnyMut};
+use crate::widgets::{TextInput, TextInputState};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use log::{error, info};
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::prelude::StatefulWidget;
+use ratatui::prelude::Widget;
+use ratatui::style::Stylize;
+use ratatui::text::Line;
+use ratatui::widgets::{Block, BorderType, Clear, Padding};
+
+/// Popup for editing the exclude patterns of a single entry, or of the whole project.
+pub struct ExcludePatternsPopup {
+    /// Name of the entry the patterns belong to, or `None` for the project-wide patterns.
+    pub entry_name: Option<String>,
+    pub patterns_input_state: TextInputState,
+    pub last_error: Option<String>,
+}
+
+impl ExcludePatternsPopup {
+    /// Separator between the patterns in the text input.
+    pub const SEPARATOR: char = ';';
+
+    pub fn new(entry_name: Option<String>, patterns: &[String]) -> Self {
+        let mut patterns_input_state = TextInputState::default();
+        patterns_input_state.set_input(&patterns.join(&format!("{} ", Self::SEPARATOR)));
+        Self {
+            entry_name,
+            patterns_input_state,
+            last_error: None,
+        }
+    }
+
+    fn patterns(&self) -> Vec<String> {
+        self.patterns_input_state
+            .input_as_string()
+            .split(Self::SEPARATOR)
+            .map(str::trim)
+            .filter(|pattern| !pattern.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn apply(state: &mut MoverrApp) {
+        // Popup shouldn't have changed
+        let popup = state.try_get_popup_mut::<ExcludePatternsPopup>().unwrap();
+        let patterns = popup.patterns();
+        if let Err(err) = MoveFilter::new(&patterns) {
+            popup.last_error = Some(err);
+            return;
+        }
+        let entry_name = popup.entry_name.clone();
+
+        let Some(project) = state.project_state.as_mut() else {
+            state.close_popup();
+            return;
+        };
+
+        let save_res = {
+            let mut settings = project.settings.lock().unwrap();
+            match entry_name {
+                Some(ref name) => settings.update_entry(name, |entry| entry.exclude = patterns),
+                None => settings.exclude = patterns,
+            }
+            settings.save(&project.directory)
+        };
+
+        match save_res {
+            Ok(_) => {
+                info!(
+                    target: "project",
+                    "Updated exclude patterns of {}",
+                    entry_name.as_deref().unwrap_or("the project")
+                );
+                project.restart_calc(entry_name.as_deref());
+                state.close_popup();
+            }
+            Err(err) => {
+                error!(target: "project", "{}", err);
+                let popup = state.try_get_popup_mut::<ExcludePatternsPopup>().unwrap();
+                popup.last_error = Some(err);
+            }
+        }
+    }
+}
+
+impl Popup for ExcludePatternsPopup {
+    fn render(&mut self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        Clear.render(area, buf);
+
+        let block = Block::bordered()
+            .border_type(BorderType::Thick)
+            .padding(Padding::horizontal(1))
+            .title(match self.entry_name {
+                Some(ref name) => format!("Exclude patterns: {}", name),
+                None => "Project exclude patterns".to_string(),
+            })
+            .title_bottom(Line::from("[Enter] Save [Esc] Cancel").right_aligned());
+        let inner_area = block.inner(area);
+        block.render(area, buf);
+
+        let [label_area, input_area, hint_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .flex(Flex::Center)
+        .areas(inner_area);
+
+        buf.set_line(
+            label_area.x,
+            label_area.y,
+            &Line::from("Patterns"),
+            label_area.width,
+        );
+        TextInput::default().render(input_area, buf, &mut self.patterns_input_state);
+        if let Some(last_error) = &self.last_error {
+            buf.set_line(
+                hint_area.x,
+                hint_area.y,
+                &Line::from(last_error.as_ref()).red().right_aligned(),
+                hint_area.width,
+            );
+        } else {
+            buf.set_line(
+                hint_area.x,
+                hint_area.y,
+                &Line::from("e.g. shadercache/; *.log; !keep.log")
+                    .gray()
+                    .right_aligned(),
+                hint_area.width,
+            );
+        }
+    }
+
+    fn handle_key_event(&mut self, key_event: KeyEvent) -> Option<&'static PopupFn> {
+        match key_event {
+            KeyEvent {
+                code: KeyCode::Esc,
+                kind: KeyEventKind::Press,
+                ..
+            } => Some(&|state: &mut MoverrApp| {
+                state.close_popup();
+            }),
+            KeyEvent {
+                code: KeyCode::Enter,
+                kind: KeyEventKind::Press,
+                ..
+            } => Some(&Self::apply),
+            _ => {
+                self.patterns_input_state.handle_key_event(key_event);
+                None
+            }
+        }
+    }
+
+    fn height_hint(&self) -> Option<Constraint> {
+        Some(Constraint::Length(5))
+    }
+}
+
+impl_as_any_mut!(ExcludePatternsPopup);
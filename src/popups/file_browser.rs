@@ -0,0 +1,492 @@
+use crate::app::MoverrApp;
+use crate::popups::{Popup, PopupFn};
+use crate::utils::{impl_as_any_mut, AsAny, AsAnyMut};
+use crate::volume_information::mounted_volumes;
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::prelude::StatefulWidget;
+use ratatui::prelude::Widget;
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, BorderType, Clear, List, ListItem, ListState, Padding};
+use std::collections::HashSet;
+use std::fs::read_dir;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// How long after the last key press typing continues the name being jumped to instead of
+/// starting a new one.
+const TYPE_TO_JUMP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Called with the directory picked in a [`FileBrowserPopup`]. The popup is closed if it returns
+/// `Ok`, otherwise the error is shown in it.
+type PickFn = dyn Fn(&mut MoverrApp, PathBuf) -> Result<(), String>;
+
+/// A directory that's visible in the tree, because all its ancestors are expanded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TreeRow {
+    path: PathBuf,
+    depth: usize,
+    expanded: bool,
+}
+
+impl TreeRow {
+    fn new(path: PathBuf, depth: usize) -> Self {
+        Self {
+            path,
+            depth,
+            expanded: false,
+        }
+    }
+
+    /// Get the name shown for the row, which is the whole path for the roots.
+    fn name(&self) -> String {
+        match self.path.file_name() {
+            Some(name) if self.depth > 0 => name.to_string_lossy().into_owned(),
+            _ => self.path.display().to_string(),
+        }
+    }
+}
+
+/// Tree of directories with the mounted volumes at its root, read as it gets expanded.
+#[derive(Debug, Default)]
+struct DirectoryTree {
+    rows: Vec<TreeRow>,
+    roots: Vec<PathBuf>,
+    show_hidden: bool,
+}
+
+impl DirectoryTree {
+    fn new(roots: Vec<PathBuf>) -> Self {
+        Self {
+            rows: roots
+                .iter()
+                .cloned()
+                .map(|root| TreeRow::new(root, 0))
+                .collect(),
+            roots,
+            show_hidden: false,
+        }
+    }
+
+    fn expand(&mut self, index: usize) -> io::Result<()> {
+        if self.rows[index].expanded {
+            return Ok(());
+        }
+        let children = list_directories(&self.rows[index].path, self.show_hidden)?;
+        self.insert_children(index, children);
+        Ok(())
+    }
+
+    fn insert_children(&mut self, index: usize, children: Vec<PathBuf>) {
+        let depth = self.rows[index].depth + 1;
+        self.rows[index].expanded = true;
+        self.rows.splice(
+            index + 1..index + 1,
+            children.into_iter().map(|child| TreeRow::new(child, depth)),
+        );
+    }
+
+    fn collapse(&mut self, index: usize) {
+        let depth = self.rows[index].depth;
+        let end = self.rows[index + 1..]
+            .iter()
+            .position(|row| row.depth <= depth)
+            .map_or(self.rows.len(), |offset| index + 1 + offset);
+        self.rows.drain(index + 1..end);
+        self.rows[index].expanded = false;
+    }
+
+    /// Get the row of the directory containing the one at `index`.
+    fn parent(&self, index: usize) -> Option<usize> {
+        let depth = self.rows[index].depth;
+        self.rows[..index].iter().rposition(|row| row.depth < depth)
+    }
+
+    /// Expand the ancestors of `path` as far as they can be read, and get the row of the deepest
+    /// one that's shown.
+    fn reveal(&mut self, path: &Path) -> Option<usize> {
+        let mut index = self
+            .rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| path.starts_with(&row.path))
+            .max_by_key(|(_, row)| row.path.components().count())?
+            .0;
+        while self.rows[index].path != path {
+            if self.expand(index).is_err() {
+                break;
+            }
+            let depth = self.rows[index].depth;
+            let Some(child) = self.rows[index + 1..]
+                .iter()
+                .take_while(|row| row.depth > depth)
+                .position(|row| path.starts_with(&row.path))
+            else {
+                break;
+            };
+            index += 1 + child;
+        }
+        Some(index)
+    }
+
+    /// Show or hide hidden directories, keeping the expanded directories expanded.
+    fn set_show_hidden(&mut self, show_hidden: bool) {
+        let expanded: HashSet<_> = self
+            .rows
+            .iter()
+            .filter(|row| row.expanded)
+            .map(|row| row.path.clone())
+            .collect();
+        self.show_hidden = show_hidden;
+        self.rows = Self::new(self.roots.clone()).rows;
+
+        let mut index = 0;
+        while index < self.rows.len() {
+            if expanded.contains(&self.rows[index].path) {
+                // Directories that can't be read anymore just stay collapsed
+                let _ = self.expand(index);
+            }
+            index += 1;
+        }
+    }
+
+    fn position(&self, path: &Path) -> Option<usize> {
+        self.rows.iter().position(|row| row.path == path)
+    }
+}
+
+/// List the directories in `path`, sorted by name.
+fn list_directories(path: &Path, show_hidden: bool) -> io::Result<Vec<PathBuf>> {
+    let mut directories: Vec<_> = read_dir(path)?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+        .filter(|entry| show_hidden || !entry.file_name().to_string_lossy().starts_with('.'))
+        .map(|entry| entry.path())
+        .collect();
+    directories.sort_by_cached_key(|path| path.file_name().map(|name| name.to_ascii_lowercase()));
+    Ok(directories)
+}
+
+/// Find the first row at or after `start` whose name starts with `prefix`, ignoring case and
+/// wrapping around to the top.
+fn find_match(names: &[String], start: usize, prefix: &str) -> Option<usize> {
+    let prefix = prefix.to_lowercase();
+    (0..names.len())
+        .map(|offset| (start + offset) % names.len())
+        .find(|&index| names[index].to_lowercase().starts_with(&prefix))
+}
+
+/// Popup for picking a directory from a tree of the mounted volumes.
+pub struct FileBrowserPopup {
+    title: String,
+    tree: DirectoryTree,
+    list_state: ListState,
+    /// Start of the name being typed to jump to a row.
+    typed: String,
+    last_typed: Instant,
+    on_pick: Rc<PickFn>,
+    pub last_error: Option<String>,
+}
+
+impl FileBrowserPopup {
+    /// Create a browser with `start` (or the home directory) selected, calling `on_pick` with
+    /// the directory picked.
+    pub fn new(
+        title: impl Into<String>,
+        start: Option<&Path>,
+        on_pick: impl Fn(&mut MoverrApp, PathBuf) -> Result<(), String> + 'static,
+    ) -> Self {
+        let home = dirs::home_dir();
+        let mut roots = mounted_volumes();
+        if let Some(home) = &home {
+            if !roots.contains(home) {
+                roots.insert(0, home.clone());
+            }
+        }
+
+        let mut tree = DirectoryTree::new(roots);
+        let selected = start
+            .or(home.as_deref())
+            .and_then(|start| tree.reveal(start))
+            .unwrap_or(0);
+
+        Self {
+            title: title.into(),
+            tree,
+            list_state: ListState::default().with_selected(Some(selected)),
+            typed: String::new(),
+            last_typed: Instant::now(),
+            on_pick: Rc::new(on_pick),
+            last_error: None,
+        }
+    }
+
+    fn selected(&self) -> Option<usize> {
+        self.list_state
+            .selected()
+            .filter(|&index| index < self.tree.rows.len())
+    }
+
+    fn expand_selected(&mut self) {
+        let Some(index) = self.selected() else {
+            return;
+        };
+        if self.tree.rows[index].expanded {
+            // Step into the directory if it's open already
+            if self
+                .tree
+                .rows
+                .get(index + 1)
+                .is_some_and(|row| row.depth > self.tree.rows[index].depth)
+            {
+                self.list_state.select(Some(index + 1));
+            }
+            return;
+        }
+        if let Err(err) = self.tree.expand(index) {
+            self.last_error = Some(format!(
+                "Couldn't read {}: {}",
+                self.tree.rows[index].name(),
+                err
+            ));
+        }
+    }
+
+    fn collapse_selected(&mut self) {
+        let Some(index) = self.selected() else {
+            return;
+        };
+        if self.tree.rows[index].expanded {
+            self.tree.collapse(index);
+        } else if let Some(parent) = self.tree.parent(index) {
+            self.list_state.select(Some(parent));
+        }
+    }
+
+    fn toggle_hidden(&mut self) {
+        let selected = self
+            .selected()
+            .map(|index| self.tree.rows[index].path.clone());
+        self.tree.set_show_hidden(!self.tree.show_hidden);
+        // The selected directory is gone if it was hidden, select its closest visible ancestor
+        let index = selected.and_then(|selected| {
+            selected
+                .ancestors()
+                .find_map(|ancestor| self.tree.position(ancestor))
+        });
+        self.list_state.select(Some(index.unwrap_or(0)));
+    }
+
+    fn type_to_jump(&mut self, c: char) {
+        if self.last_typed.elapsed() > TYPE_TO_JUMP_TIMEOUT {
+            self.typed.clear();
+        }
+        self.last_typed = Instant::now();
+        self.typed.push(c);
+
+        let names: Vec<_> = self.tree.rows.iter().map(TreeRow::name).collect();
+        // A new name starts searching below the selected row, so typing the same letter cycles
+        let start = self.selected().map_or(0, |index| {
+            if self.typed.chars().count() == 1 {
+                index + 1
+            } else {
+                index
+            }
+        });
+        if let Some(index) = find_match(&names, start, &self.typed) {
+            self.list_state.select(Some(index));
+        }
+    }
+
+    fn pick(state: &mut MoverrApp) {
+        // Popup shouldn't have changed
+        let popup = state.try_get_popup_mut::<FileBrowserPopup>().unwrap();
+        let Some(index) = popup.selected() else {
+            return;
+        };
+        let path = popup.tree.rows[index].path.clone();
+        let on_pick = popup.on_pick.clone();
+
+        match on_pick(state, path) {
+            Ok(_) => state.close_popup(),
+            Err(err) => {
+                if let Some(popup) = state.try_get_popup_mut::<FileBrowserPopup>() {
+                    popup.last_error = Some(err);
+                }
+            }
+        }
+    }
+}
+
+impl Popup for FileBrowserPopup {
+    fn render(&mut self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        Clear.render(area, buf);
+
+        let block = Block::bordered()
+            .border_type(BorderType::Thick)
+            .padding(Padding::horizontal(1))
+            .title(self.title.as_str())
+            .title_bottom(
+                Line::from(format!(
+                    "[Tab] {} hidden [Enter] Pick [Esc] Cancel",
+                    if self.tree.show_hidden {
+                        "Hide"
+                    } else {
+                        "Show"
+                    }
+                ))
+                .right_aligned(),
+            );
+        let inner_area = block.inner(area);
+        block.render(area, buf);
+
+        let [list_area, hint_area] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(inner_area);
+
+        let items: Vec<_> = self
+            .tree
+            .rows
+            .iter()
+            .map(|row| {
+                ListItem::new(format!(
+                    "{}{} {}",
+                    "  ".repeat(row.depth),
+                    if row.expanded { "▾" } else { "▸" },
+                    row.name()
+                ))
+            })
+            .collect();
+        let list = List::new(items)
+            .highlight_symbol("> ")
+            .highlight_style(Style::default().reversed());
+        StatefulWidget::render(list, list_area, buf, &mut self.list_state);
+
+        let hint = if let Some(last_error) = &self.last_error {
+            Line::from(last_error.as_str()).red()
+        } else if !self.typed.is_empty() && self.last_typed.elapsed() <= TYPE_TO_JUMP_TIMEOUT {
+            Line::from(format!("Jump to: {}", self.typed)).gray()
+        } else if let Some(index) = self.selected() {
+            Line::from(self.tree.rows[index].path.display().to_string()).gray()
+        } else {
+            Line::default()
+        };
+        buf.set_line(
+            hint_area.x,
+            hint_area.y,
+            &hint.right_aligned(),
+            hint_area.width,
+        );
+    }
+
+    fn handle_key_event(&mut self, key_event: KeyEvent) -> Option<&'static PopupFn> {
+        if !matches!(key_event.kind, KeyEventKind::Press | KeyEventKind::Repeat) {
+            return None;
+        }
+        if key_event.code != KeyCode::Enter {
+            self.last_error = None;
+        }
+
+        match key_event.code {
+            KeyCode::Esc => Some(&|state: &mut MoverrApp| {
+                state.close_popup();
+            }),
+            KeyCode::Enter => Some(&Self::pick),
+            KeyCode::Up => {
+                self.list_state.select_previous();
+                None
+            }
+            KeyCode::Down => {
+                self.list_state.select_next();
+                None
+            }
+            KeyCode::Home => {
+                self.list_state.select_first();
+                None
+            }
+            KeyCode::End => {
+                self.list_state.select_last();
+                None
+            }
+            KeyCode::Right => {
+                self.expand_selected();
+                None
+            }
+            KeyCode::Left => {
+                self.collapse_selected();
+                None
+            }
+            KeyCode::Tab => {
+                self.toggle_hidden();
+                None
+            }
+            KeyCode::Char(c)
+                if key_event.modifiers == KeyModifiers::NONE
+                    || key_event.modifiers == KeyModifiers::SHIFT =>
+            {
+                self.type_to_jump(c);
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+impl_as_any_mut!(FileBrowserPopup);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(tree: &DirectoryTree) -> Vec<(&str, usize)> {
+        tree.rows
+            .iter()
+            .map(|row| (row.path.to_str().unwrap(), row.depth))
+            .collect()
+    }
+
+    #[test]
+    fn test_expand_collapse() {
+        let mut tree = DirectoryTree::new(vec![PathBuf::from("/"), PathBuf::from("/mnt/games")]);
+        tree.insert_children(0, vec![PathBuf::from("/home"), PathBuf::from("/srv")]);
+        tree.insert_children(1, vec![PathBuf::from("/home/user")]);
+        assert_eq!(
+            paths(&tree),
+            [
+                ("/", 0),
+                ("/home", 1),
+                ("/home/user", 2),
+                ("/srv", 1),
+                ("/mnt/games", 0)
+            ]
+        );
+        assert_eq!(tree.parent(2), Some(1));
+        assert_eq!(tree.parent(3), Some(0));
+        assert_eq!(tree.parent(4), None);
+
+        tree.collapse(1);
+        assert_eq!(
+            paths(&tree),
+            [("/", 0), ("/home", 1), ("/srv", 1), ("/mnt/games", 0)]
+        );
+        tree.collapse(0);
+        assert_eq!(paths(&tree), [("/", 0), ("/mnt/games", 0)]);
+        assert!(!tree.rows[0].expanded);
+    }
+
+    #[test]
+    fn test_find_match() {
+        let names = ["Games", "Music", "games2", "Videos"].map(String::from);
+        assert_eq!(find_match(&names, 0, "g"), Some(0));
+        assert_eq!(find_match(&names, 1, "g"), Some(2));
+        assert_eq!(find_match(&names, 3, "g"), Some(0));
+        assert_eq!(find_match(&names, 0, "GAMES2"), Some(2));
+        assert_eq!(find_match(&names, 0, "x"), None);
+    }
+}
@@ -0,0 +1,131 @@
+use crate::app::MoverrApp;
+use crate::job_queue::{JobInfo, JobQueue};
+use crate::popups::{Popup, PopupFn};
+use crate::utils::{impl_as_any_mut, AsAny, AsAnyMut};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use log::warn;
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::prelude::StatefulWidget;
+use ratatui::prelude::Widget;
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, BorderType, Clear, List, ListItem, ListState, Padding};
+use std::sync::{Arc, Mutex};
+
+/// Popup listing the running and queued jobs of the project, for reordering, cancelling and
+/// pausing them.
+pub struct JobsPopup {
+    job_queue: Arc<Mutex<JobQueue>>,
+    list_state: ListState,
+}
+
+impl JobsPopup {
+    pub fn new(job_queue: Arc<Mutex<JobQueue>>) -> Self {
+        Self {
+            job_queue,
+            list_state: ListState::default().with_selected(Some(0)),
+        }
+    }
+
+    fn selected_job(&self) -> Option<JobInfo> {
+        let jobs = self.job_queue.lock().unwrap().jobs();
+        jobs.into_iter().nth(self.list_state.selected()?)
+    }
+}
+
+impl Popup for JobsPopup {
+    fn render(&mut self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        Clear.render(area, buf);
+
+        let job_queue = self.job_queue.lock().unwrap();
+        let block = Block::bordered()
+            .border_type(BorderType::Thick)
+            .padding(Padding::horizontal(1))
+            .title(if job_queue.is_paused() {
+                "Jobs (queue paused)"
+            } else {
+                "Jobs"
+            })
+            .title_bottom(
+                Line::from("[+/-] Earlier/Later [C] Cancel [P] Pause/Resume queue [Esc] Close")
+                    .right_aligned(),
+            );
+        let inner_area = block.inner(area);
+        block.render(area, buf);
+
+        let jobs = job_queue.jobs();
+        drop(job_queue);
+        if jobs.is_empty() {
+            buf.set_line(
+                inner_area.x,
+                inner_area.y,
+                &Line::from("No jobs").italic(),
+                inner_area.width,
+            );
+            return;
+        }
+
+        let mut queued = 0;
+        let items: Vec<_> = jobs
+            .iter()
+            .map(|job| {
+                if job.running {
+                    ListItem::new(format!("RUNNING    {}: {}", job.name, job.label)).blue()
+                } else {
+                    queued += 1;
+                    ListItem::new(format!("QUEUED #{:<3} {}: {}", queued, job.name, job.label))
+                }
+            })
+            .collect();
+        let list = List::new(items)
+            .highlight_symbol("> ")
+            .highlight_style(Style::default().reversed());
+        StatefulWidget::render(list, inner_area, buf, &mut self.list_state);
+    }
+
+    fn handle_key_event(&mut self, key_event: KeyEvent) -> Option<&'static PopupFn> {
+        if key_event.kind != KeyEventKind::Press {
+            return None;
+        }
+
+        match key_event.code {
+            KeyCode::Esc => {
+                return Some(&|state: &mut MoverrApp| {
+                    state.close_popup();
+                })
+            }
+            KeyCode::Up => self.list_state.select_previous(),
+            KeyCode::Down => self.list_state.select_next(),
+            KeyCode::Char('+') => {
+                if let Some(job) = self.selected_job() {
+                    if self.job_queue.lock().unwrap().move_up(job.id) {
+                        self.list_state.select_previous();
+                    }
+                }
+            }
+            KeyCode::Char('-') => {
+                if let Some(job) = self.selected_job() {
+                    if self.job_queue.lock().unwrap().move_down(job.id) {
+                        self.list_state.select_next();
+                    }
+                }
+            }
+            KeyCode::Char('c') | KeyCode::Delete => {
+                if let Some(job) = self.selected_job() {
+                    if !self.job_queue.lock().unwrap().cancel(job.id) {
+                        warn!("{} is already running and can't be cancelled!", job.name);
+                    }
+                }
+            }
+            KeyCode::Char('p') => self.job_queue.lock().unwrap().toggle_pause(),
+            _ => {}
+        }
+        None
+    }
+}
+
+impl_as_any_mut!(JobsPopup);
@@ -0,0 +1,320 @@
+use crate::app::MoverrApp;
+use crate::file_size::FileSize;
+use crate::popups::{Popup, PopupFn};
+use crate::project_settings::DestinationLibrary;
+use crate::utils::{impl_as_any_mut, AsAny, AsAnyMut};
+use crate::widgets::{TextInput, TextInputState};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use log::{error, info};
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::prelude::StatefulWidget;
+use ratatui::prelude::Widget;
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, BorderType, Clear, List, ListItem, ListState, Padding};
+use std::path::PathBuf;
+
+/// Labels of the inputs of a library being added.
+const NEW_LIBRARY_FIELDS: [&str; 3] = ["Label", "Path", "Quota"];
+
+/// Inputs of a library being added.
+#[derive(Default)]
+struct NewLibrary {
+    inputs: [TextInputState; 3],
+    focused: usize,
+}
+
+/// Popup for editing the destination libraries of the project and picking the default one.
+pub struct LibrariesPopup {
+    libraries: Vec<DestinationLibrary>,
+    /// How much of each library the project's entries took up when the popup was opened, or
+    /// `None` for libraries added since.
+    usage: Vec<Option<FileSize>>,
+    default_library: Option<String>,
+    list_state: ListState,
+    new_library: Option<NewLibrary>,
+    pub last_error: Option<String>,
+}
+
+impl LibrariesPopup {
+    pub fn new(
+        libraries: Vec<DestinationLibrary>,
+        usage: Vec<FileSize>,
+        default_library: Option<String>,
+    ) -> Self {
+        Self {
+            usage: usage.into_iter().map(Some).collect(),
+            default_library: default_library
+                .or_else(|| libraries.first().map(|library| library.label.clone())),
+            libraries,
+            list_state: ListState::default().with_selected(Some(0)),
+            new_library: None,
+            last_error: None,
+        }
+    }
+
+    fn is_default(&self, library: &DestinationLibrary) -> bool {
+        self.default_library.as_ref() == Some(&library.label)
+    }
+
+    fn add_new_library(&mut self) {
+        let Some(new_library) = &self.new_library else {
+            return;
+        };
+        let [label, path, quota] = new_library
+            .inputs
+            .each_ref()
+            .map(|input| input.input_as_string().trim().to_string());
+
+        if label.is_empty() {
+            self.last_error = Some("The label can't be empty".to_string());
+            return;
+        }
+        if self.libraries.iter().any(|library| library.label == label) {
+            self.last_error = Some(format!("There's already a library called {}", label));
+            return;
+        }
+        let path = PathBuf::from(path);
+        if !path.is_absolute() {
+            self.last_error = Some("The path has to be absolute".to_string());
+            return;
+        }
+        let quota = if quota.is_empty() {
+            None
+        } else {
+            match quota.parse::<FileSize>() {
+                Ok(quota) => Some(quota),
+                Err(err) => {
+                    self.last_error = Some(err);
+                    return;
+                }
+            }
+        };
+
+        if self.libraries.is_empty() {
+            self.default_library = Some(label.clone());
+        }
+        self.libraries
+            .push(DestinationLibrary { label, path, quota });
+        self.usage.push(None);
+        self.list_state.select(Some(self.libraries.len() - 1));
+        self.new_library = None;
+        self.last_error = None;
+    }
+
+    fn remove_selected(&mut self) {
+        let Some(index) = self.list_state.selected() else {
+            return;
+        };
+        if index >= self.libraries.len() {
+            return;
+        }
+        let removed = self.libraries.remove(index);
+        self.usage.remove(index);
+        if self.is_default(&removed) {
+            self.default_library = self.libraries.first().map(|library| library.label.clone());
+        }
+    }
+
+    fn set_selected_as_default(&mut self) {
+        if let Some(library) = self
+            .list_state
+            .selected()
+            .and_then(|index| self.libraries.get(index))
+        {
+            self.default_library = Some(library.label.clone());
+        }
+    }
+
+    fn apply(state: &mut MoverrApp) {
+        // Popup shouldn't have changed
+        let popup = state.try_get_popup_mut::<LibrariesPopup>().unwrap();
+        let libraries = popup.libraries.clone();
+        let default_library = popup.default_library.clone();
+
+        let Some(project) = state.project_state.as_mut() else {
+            state.close_popup();
+            return;
+        };
+
+        let save_res = {
+            let mut settings = project.settings.lock().unwrap();
+            settings.libraries = libraries;
+            settings.default_library = default_library;
+            settings.save(&project.directory)
+        };
+
+        match save_res {
+            Ok(_) => {
+                info!(target: "project", "Updated destination libraries");
+                state.close_popup();
+            }
+            Err(err) => {
+                error!(target: "project", "{}", err);
+                let popup = state.try_get_popup_mut::<LibrariesPopup>().unwrap();
+                popup.last_error = Some(err);
+            }
+        }
+    }
+}
+
+impl Popup for LibrariesPopup {
+    fn render(&mut self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        Clear.render(area, buf);
+
+        let block = Block::bordered()
+            .border_type(BorderType::Thick)
+            .padding(Padding::horizontal(1))
+            .title("Destination libraries")
+            .title_bottom(
+                Line::from(if self.new_library.is_some() {
+                    "[Tab] Next field [Enter] Add [Esc] Back"
+                } else {
+                    "[A] Add [Del] Remove [D] Default [Enter] Save [Esc] Cancel"
+                })
+                .right_aligned(),
+            );
+        let inner_area = block.inner(area);
+        block.render(area, buf);
+
+        let form_height = if self.new_library.is_some() { 6 } else { 0 };
+        let [list_area, form_area, hint_area] = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(form_height),
+            Constraint::Length(1),
+        ])
+        .areas(inner_area);
+
+        let items: Vec<_> = self
+            .libraries
+            .iter()
+            .zip(&self.usage)
+            .map(|(library, usage)| {
+                let usage = match (usage, library.quota) {
+                    (Some(usage), Some(quota)) => format!("{} of {} used", usage, quota),
+                    (Some(usage), None) => format!("{} used", usage),
+                    (None, Some(quota)) => format!("{} quota", quota),
+                    (None, None) => String::new(),
+                };
+                ListItem::new(vec![
+                    Line::from(format!(
+                        "{} {}",
+                        if self.is_default(library) { "★" } else { " " },
+                        library.label
+                    ))
+                    .bold(),
+                    Line::from(format!("  {}  {}", library.path.display(), usage)),
+                ])
+            })
+            .collect();
+        let list = List::new(items)
+            .highlight_symbol("> ")
+            .highlight_style(Style::default().reversed());
+        StatefulWidget::render(list, list_area, buf, &mut self.list_state);
+
+        if let Some(new_library) = &mut self.new_library {
+            let rows = Layout::vertical([Constraint::Length(2); 3]).split(form_area);
+            for (index, (input, row)) in new_library.inputs.iter_mut().zip(rows.iter()).enumerate()
+            {
+                let [label_area, input_area] =
+                    Layout::vertical([Constraint::Length(1), Constraint::Length(1)]).areas(*row);
+                let label = Line::from(NEW_LIBRARY_FIELDS[index]);
+                buf.set_line(
+                    label_area.x,
+                    label_area.y,
+                    &if index == new_library.focused {
+                        label.bold()
+                    } else {
+                        label
+                    },
+                    label_area.width,
+                );
+                TextInput::default().render(input_area, buf, input);
+            }
+        }
+
+        let hint = match (&self.last_error, &self.new_library) {
+            (Some(last_error), _) => Line::from(last_error.as_ref()).red(),
+            (None, Some(_)) => Line::from("Quota is optional, e.g. 500 GiB").gray(),
+            (None, None) if self.libraries.is_empty() => {
+                Line::from("No libraries yet, add one with [A]").gray()
+            }
+            (None, None) => Line::default(),
+        };
+        buf.set_line(
+            hint_area.x,
+            hint_area.y,
+            &hint.right_aligned(),
+            hint_area.width,
+        );
+    }
+
+    fn handle_key_event(&mut self, key_event: KeyEvent) -> Option<&'static PopupFn> {
+        if let Some(new_library) = &mut self.new_library {
+            if key_event.kind == KeyEventKind::Press {
+                match key_event.code {
+                    KeyCode::Esc => {
+                        self.new_library = None;
+                        self.last_error = None;
+                        return None;
+                    }
+                    KeyCode::Enter => {
+                        self.add_new_library();
+                        return None;
+                    }
+                    KeyCode::Tab | KeyCode::Down => {
+                        new_library.focused = (new_library.focused + 1) % NEW_LIBRARY_FIELDS.len();
+                        return None;
+                    }
+                    KeyCode::BackTab | KeyCode::Up => {
+                        new_library.focused = (new_library.focused + NEW_LIBRARY_FIELDS.len() - 1)
+                            % NEW_LIBRARY_FIELDS.len();
+                        return None;
+                    }
+                    _ => {}
+                }
+            }
+            new_library.inputs[new_library.focused].handle_key_event(key_event);
+            return None;
+        }
+
+        if key_event.kind != KeyEventKind::Press {
+            return None;
+        }
+
+        match key_event.code {
+            KeyCode::Esc => Some(&|state: &mut MoverrApp| {
+                state.close_popup();
+            }),
+            KeyCode::Enter => Some(&Self::apply),
+            KeyCode::Up => {
+                self.list_state.select_previous();
+                None
+            }
+            KeyCode::Down => {
+                self.list_state.select_next();
+                None
+            }
+            KeyCode::Char('a') => {
+                self.new_library = Some(NewLibrary::default());
+                self.last_error = None;
+                None
+            }
+            KeyCode::Delete => {
+                self.remove_selected();
+                None
+            }
+            KeyCode::Char('d') => {
+                self.set_selected_as_default();
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+impl_as_any_mut!(LibrariesPopup);
@@ -1,11 +1,29 @@
+mod batch;
+mod duplicates;
+mod exclude_patterns;
+mod file_browser;
+mod jobs;
+mod libraries;
+mod move_dialog;
 mod open_project;
+mod size_breakdown;
+mod subdirectories;
 
 use crate::app::MoverrApp;
 use crate::utils::{AsAny, AsAnyMut};
+pub use batch::{BatchOperation, BatchPopup};
 use crossterm::event::KeyEvent;
+pub use duplicates::DuplicatesPopup;
+pub use exclude_patterns::ExcludePatternsPopup;
+pub use file_browser::FileBrowserPopup;
+pub use jobs::JobsPopup;
+pub use libraries::LibrariesPopup;
+pub use move_dialog::MoveDialogPopup;
 pub use open_project::OpenProjectPopup;
 use ratatui::buffer::Buffer;
 use ratatui::layout::{Constraint, Rect};
+pub use size_breakdown::SizeBreakdownPopup;
+pub use subdirectories::SubdirectoriesPopup;
 
 type PopupFn = dyn Fn(&mut MoverrApp);
 
@@ -0,0 +1,456 @@
+use crate::app::MoverrApp;
+use crate::file_size::FileSize;
+use crate::launchers;
+use crate::path_ext::{
+    DirectoryStats, LinkType, MoveOptions, MoveStrategy, PathExt, VerificationLevel,
+};
+use crate::popups::{FileBrowserPopup, Popup, PopupFn};
+use crate::project::ProjectState;
+use crate::project_settings::DestinationLibrary;
+use crate::steam::SteamLibrary;
+use crate::utils::{impl_as_any_mut, AsAny, AsAnyMut};
+use crate::volume_information::VolumeSpace;
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use log::{error, warn};
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::prelude::StatefulWidget;
+use ratatui::prelude::Widget;
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, BorderType, Clear, List, ListItem, ListState, Padding};
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// A library the entry can be moved to, with what's known about the room left in it.
+#[derive(Debug, Clone)]
+pub struct Destination {
+    pub library: DestinationLibrary,
+    /// Free space of the volume the library is on, if it could be queried.
+    pub space: Option<VolumeSpace>,
+    /// What's left of the library's quota, if it has one.
+    pub remaining_quota: Option<FileSize>,
+    pub strategy: MoveStrategy,
+}
+
+/// Popup for picking where to move an entry, or several marked ones, and how, showing whether
+/// they fit first.
+pub struct MoveDialogPopup {
+    entry_names: Vec<String>,
+    /// The stats of all entries together.
+    stats: DirectoryStats,
+    destinations: Vec<Destination>,
+    options: MoveOptions,
+    /// The launchers that installed the entries, if they can all be told where the entries were
+    /// moved to instead of leaving a symlink.
+    launcher: Option<String>,
+    list_state: ListState,
+    pub last_error: Option<String>,
+}
+
+impl MoveDialogPopup {
+    /// Create the dialog for moving the entries `entry_names` of `project`, which together have
+    /// `stats`. Games in a Steam library can also be moved to the other `steam_libraries`, and
+    /// games of other launchers can be moved without a symlink.
+    pub fn new(
+        project: &ProjectState,
+        steam_libraries: &[SteamLibrary],
+        entry_names: Vec<String>,
+        stats: DirectoryStats,
+    ) -> Self {
+        let default_library = project.default_library();
+        let mut destinations: Vec<_> = project
+            .libraries()
+            .into_iter()
+            .map(|library| Destination {
+                space: library.path.get_volume_space().ok(),
+                remaining_quota: project.remaining_quota(&library.path),
+                library,
+                strategy: MoveStrategy::Symlink,
+            })
+            .collect();
+        if entry_names
+            .iter()
+            .all(|name| project.steam_apps.contains_key(name))
+        {
+            destinations.extend(
+                steam_libraries
+                    .iter()
+                    .filter(|library| library.common() != project.directory)
+                    .map(|library| Destination {
+                        library: DestinationLibrary {
+                            label: format!("Steam: {}", library.display_name()),
+                            path: library.common(),
+                            quota: None,
+                        },
+                        space: library.path.get_volume_space().ok(),
+                        remaining_quota: None,
+                        strategy: MoveStrategy::Relocate,
+                    }),
+            );
+        }
+        let selected = default_library
+            .and_then(|default_library| {
+                destinations
+                    .iter()
+                    .position(|destination| destination.library.label == default_library.label)
+            })
+            .unwrap_or(0);
+        let launcher_names: Option<BTreeSet<_>> = entry_names
+            .iter()
+            .map(|name| {
+                project
+                    .launcher_games
+                    .get(name)
+                    .and_then(|game| launchers::provider(game.launcher))
+                    .filter(|provider| provider.can_update_install_path())
+                    .map(|provider| provider.name())
+            })
+            .collect();
+        let launcher = launcher_names
+            .map(|names| names.into_iter().collect::<Vec<_>>().join(", "))
+            .filter(|names| !names.is_empty());
+
+        Self {
+            entry_names,
+            stats,
+            destinations,
+            options: project.move_options(),
+            launcher,
+            list_state: ListState::default().with_selected(Some(selected)),
+            last_error: None,
+        }
+    }
+
+    /// Create the dialog for moving the directories marked in `project`, leaving out the ones
+    /// that can't be moved. Returns `None` if none of them can.
+    pub fn for_marked(project: &ProjectState, steam_libraries: &[SteamLibrary]) -> Option<Self> {
+        let mut entry_names = Vec::new();
+        let mut stats = DirectoryStats::default();
+        for dir in project.marked_directories() {
+            match dir.stats() {
+                Some(Ok(dir_stats)) if dir.can_be_moved() => {
+                    stats += &dir_stats;
+                    entry_names.push(dir.name.clone());
+                }
+                _ => warn!("Leaving out {}, it can't be moved right now", dir.name),
+            }
+        }
+        (!entry_names.is_empty()).then(|| Self::new(project, steam_libraries, entry_names, stats))
+    }
+
+    pub fn has_destinations(&self) -> bool {
+        !self.destinations.is_empty()
+    }
+
+    /// Describe the entries being moved.
+    fn entries_label(&self) -> String {
+        match self.entry_names.as_slice() {
+            [name] => name.clone(),
+            names => format!("{} entries", names.len()),
+        }
+    }
+
+    /// Describe the room left at `destination` before and after the move.
+    fn space_line(&self, destination: &Destination) -> Line<'static> {
+        let mut line = match destination.space {
+            Some(space) => {
+                let needed = self.stats.estimated_allocated_size(space.block_size);
+                if needed > space.available {
+                    return Line::from(format!(
+                        "  Not enough space: {} free, about {} needed",
+                        space.available, needed
+                    ))
+                    .red();
+                }
+                format!(
+                    "  {} free, {} after the move",
+                    space.available,
+                    FileSize::from_bytes(space.available.as_bytes() - needed.as_bytes())
+                )
+            }
+            None => "  Free space unknown".to_string(),
+        };
+        if let Some(remaining_quota) = destination.remaining_quota {
+            if self.stats.size > remaining_quota {
+                return Line::from(format!(
+                    "  Over the quota: only {} of it are left",
+                    remaining_quota
+                ))
+                .red();
+            }
+            line.push_str(&format!(", {} of the quota left", remaining_quota));
+        }
+        Line::from(line).gray()
+    }
+
+    fn confirm(state: &mut MoverrApp) {
+        // Popup shouldn't have changed
+        let popup = state.try_get_popup_mut::<MoveDialogPopup>().unwrap();
+        let Some(destination) = popup
+            .list_state
+            .selected()
+            .and_then(|index| popup.destinations.get(index))
+        else {
+            return;
+        };
+        let directory = destination.library.path.clone();
+        let entry_names = popup.entry_names.clone();
+        let options = MoveOptions {
+            strategy: match destination.strategy {
+                MoveStrategy::Symlink => popup.options.strategy,
+                MoveStrategy::Relocate => MoveStrategy::Relocate,
+            },
+            ..popup.options.clone()
+        };
+
+        match start_moves(state, &entry_names, &directory, &options) {
+            // The progress is shown in the project table
+            Ok(_) => state.close_popup(),
+            Err(err) => {
+                let popup = state.try_get_popup_mut::<MoveDialogPopup>().unwrap();
+                popup.last_error = Some(err);
+            }
+        }
+    }
+
+    /// Replace the dialog with a browser for moving the entry somewhere outside the libraries.
+    fn browse(state: &mut MoverrApp) {
+        // Popup shouldn't have changed
+        let popup = state.try_get_popup_mut::<MoveDialogPopup>().unwrap();
+        let start = popup
+            .list_state
+            .selected()
+            .and_then(|index| popup.destinations.get(index))
+            .map(|destination| destination.library.path.clone());
+        let title = format!("Move {} into", popup.entries_label());
+        let entry_names = popup.entry_names.clone();
+        let options = popup.options.clone();
+
+        state.close_popup();
+        let popup = FileBrowserPopup::new(title, start.as_deref(), move |state, path| {
+            start_moves(state, &entry_names, &path, &options)
+        });
+        state.open_popup(Box::new(popup)).unwrap();
+    }
+}
+
+/// Start moving the entries `entry_names` into `directory`, as many as can be moved. Fails if
+/// none of them can.
+fn start_moves(
+    state: &mut MoverrApp,
+    entry_names: &[String],
+    directory: &Path,
+    options: &MoveOptions,
+) -> Result<(), String> {
+    let project = state
+        .project_state
+        .as_mut()
+        .ok_or_else(|| "No project opened!".to_string())?;
+    let failed: Vec<_> = entry_names
+        .iter()
+        .filter(|name| {
+            project.find_directory(name).is_none_or(|dir| {
+                dir.try_start_move_to(project, directory.join(name), options.clone())
+                    .is_err()
+            })
+        })
+        .collect();
+    match failed.as_slice() {
+        [] => {}
+        [name] if entry_names.len() == 1 => {
+            return Err(format!("{} can't be moved there right now", name));
+        }
+        failed if failed.len() == entry_names.len() => {
+            return Err("None of the entries can be moved there right now".to_string());
+        }
+        failed => error!(
+            "{} of the entries couldn't be moved: {}",
+            failed.len(),
+            failed
+                .iter()
+                .map(|name| name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+    project.clear_marks();
+    Ok(())
+}
+
+impl Popup for MoveDialogPopup {
+    fn render(&mut self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        Clear.render(area, buf);
+
+        let block = Block::bordered()
+            .border_type(BorderType::Thick)
+            .padding(Padding::horizontal(1))
+            .title(format!("Move {}", self.entries_label()))
+            .title_bottom(
+                Line::from(format!(
+                    "[{}] Options [B] Browse [Enter] Move [Esc] Cancel",
+                    if self.launcher.is_some() {
+                        "V/L/H/R"
+                    } else {
+                        "V/L/H"
+                    }
+                ))
+                .right_aligned(),
+            );
+        let inner_area = block.inner(area);
+        block.render(area, buf);
+
+        let mut options = vec![
+            "Options".to_string(),
+            format!(
+                "[V] Verification: {}",
+                match self.options.verification {
+                    VerificationLevel::Sizes => "file sizes",
+                    VerificationLevel::Contents => "file contents",
+                }
+            ),
+            format!(
+                "[L] Links: {}",
+                match self.options.link_type {
+                    LinkType::Absolute => "absolute symlinks",
+                    LinkType::Relative => "relative symlinks",
+                }
+            ),
+            format!(
+                "[H] Hashes in manifest: {}",
+                if self.options.hash_files { "yes" } else { "no" }
+            ),
+        ];
+        if let Some(ref launcher) = self.launcher {
+            options.push(format!(
+                "[R] Leave behind: {}",
+                match self.options.strategy {
+                    MoveStrategy::Symlink => "a symlink".to_string(),
+                    MoveStrategy::Relocate =>
+                        format!("nothing, update {}'s install path", launcher),
+                }
+            ));
+        }
+
+        let [size_area, list_area, options_area, hint_area] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Fill(1),
+            Constraint::Length(options.len() as u16),
+            Constraint::Length(1),
+        ])
+        .areas(inner_area);
+
+        buf.set_line(
+            size_area.x,
+            size_area.y,
+            &Line::from(format!(
+                "{} in {} files, {} on disk",
+                self.stats.size, self.stats.file_count, self.stats.allocated_size
+            )),
+            size_area.width,
+        );
+
+        let items: Vec<_> = self
+            .destinations
+            .iter()
+            .map(|destination| {
+                ListItem::new(vec![
+                    Line::from(match destination.strategy {
+                        MoveStrategy::Symlink => format!(
+                            "{}  {}",
+                            destination.library.label,
+                            destination.library.path.display()
+                        ),
+                        // The path is part of the label already
+                        MoveStrategy::Relocate => {
+                            format!("{} (no symlink)", destination.library.label)
+                        }
+                    })
+                    .bold(),
+                    self.space_line(destination),
+                ])
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::new().title("Destination"))
+            .highlight_symbol("> ")
+            .highlight_style(Style::default().reversed());
+        StatefulWidget::render(list, list_area, buf, &mut self.list_state);
+
+        for (row, option) in options.into_iter().enumerate() {
+            let line = if row == 0 {
+                Line::from(option).bold()
+            } else {
+                Line::from(option)
+            };
+            buf.set_line(
+                options_area.x,
+                options_area.y + row as u16,
+                &line,
+                options_area.width,
+            );
+        }
+
+        if let Some(last_error) = &self.last_error {
+            buf.set_line(
+                hint_area.x,
+                hint_area.y,
+                &Line::from(last_error.as_ref()).red().right_aligned(),
+                hint_area.width,
+            );
+        }
+    }
+
+    fn handle_key_event(&mut self, key_event: KeyEvent) -> Option<&'static PopupFn> {
+        if key_event.kind != KeyEventKind::Press {
+            return None;
+        }
+
+        match key_event.code {
+            KeyCode::Esc => Some(&|state: &mut MoverrApp| {
+                state.close_popup();
+            }),
+            KeyCode::Enter => Some(&Self::confirm),
+            KeyCode::Char('b') => Some(&Self::browse),
+            KeyCode::Up => {
+                self.list_state.select_previous();
+                None
+            }
+            KeyCode::Down => {
+                self.list_state.select_next();
+                None
+            }
+            KeyCode::Char('v') => {
+                self.options.verification = match self.options.verification {
+                    VerificationLevel::Sizes => VerificationLevel::Contents,
+                    VerificationLevel::Contents => VerificationLevel::Sizes,
+                };
+                None
+            }
+            KeyCode::Char('l') => {
+                self.options.link_type = match self.options.link_type {
+                    LinkType::Absolute => LinkType::Relative,
+                    LinkType::Relative => LinkType::Absolute,
+                };
+                None
+            }
+            KeyCode::Char('h') => {
+                self.options.hash_files = !self.options.hash_files;
+                None
+            }
+            KeyCode::Char('r') if self.launcher.is_some() => {
+                self.options.strategy = match self.options.strategy {
+                    MoveStrategy::Symlink => MoveStrategy::Relocate,
+                    MoveStrategy::Relocate => MoveStrategy::Symlink,
+                };
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+impl_as_any_mut!(MoveDialogPopup);
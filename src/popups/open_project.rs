@@ -1,23 +1,44 @@
 use crate::app::MoverrApp;
 use crate::popups::{Popup, PopupFn};
 use crate::utils::{impl_as_any_mut, AsAny, AsAnyMut};
-use crate::widgets::{TextInput, TextInputState};
+use crate::widgets::{complete_path, expand_path, TextInput, TextInputState};
 use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
 use ratatui::buffer::Buffer;
 use ratatui::layout::{Constraint, Flex, Layout, Rect};
 use ratatui::prelude::StatefulWidget;
 use ratatui::prelude::Widget;
-use ratatui::style::Stylize;
+use ratatui::style::{Style, Stylize};
 use ratatui::text::Line;
-use ratatui::widgets::{Block, BorderType, Clear, Padding};
-use std::path::PathBuf;
+use ratatui::widgets::{Block, BorderType, Clear, List, ListState, Padding};
+
+/// How many completion candidates are shown below the input at most.
+const MAX_COMPLETION_ROWS: u16 = 6;
 
-#[derive(Default)]
 pub struct OpenProjectPopup {
     pub project_path_input_state: TextInputState,
     pub last_error: Option<String>,
 }
 
+impl Default for OpenProjectPopup {
+    fn default() -> Self {
+        Self {
+            project_path_input_state: TextInputState::default()
+                .with_completion_provider(complete_path),
+            last_error: None,
+        }
+    }
+}
+
+impl OpenProjectPopup {
+    fn completion_rows(&self) -> u16 {
+        self.project_path_input_state
+            .completion_candidates()
+            .map_or(0, |(candidates, _)| {
+                (candidates.len() as u16).min(MAX_COMPLETION_ROWS)
+            })
+    }
+}
+
 impl Popup for OpenProjectPopup {
     fn render(&mut self, area: Rect, buf: &mut Buffer)
     where
@@ -33,9 +54,10 @@ impl Popup for OpenProjectPopup {
         let inner_area = block.inner(area);
         block.render(area, buf);
 
-        let [label_area, input_area, hint_area] = Layout::vertical([
+        let [label_area, input_area, completion_area, hint_area] = Layout::vertical([
             Constraint::Length(1),
             Constraint::Length(1),
+            Constraint::Length(self.completion_rows()),
             Constraint::Length(1),
         ])
         .flex(Flex::Center)
@@ -48,6 +70,14 @@ impl Popup for OpenProjectPopup {
             label_area.width,
         );
         TextInput::default().render(input_area, buf, &mut self.project_path_input_state);
+        if let Some((candidates, selected)) = self.project_path_input_state.completion_candidates()
+        {
+            let list = List::new(candidates.iter().map(String::as_str))
+                .style(Style::new().on_dark_gray())
+                .highlight_style(Style::new().on_blue());
+            let mut list_state = ListState::default().with_selected(selected);
+            StatefulWidget::render(list, completion_area, buf, &mut list_state);
+        }
         if let Some(last_error) = &self.last_error {
             buf.set_line(
                 hint_area.x,
@@ -92,7 +122,7 @@ impl Popup for OpenProjectPopup {
                                     Some("Project path cannot be empty.".to_string());
                                 return;
                             }
-                            let path = PathBuf::from(path_str);
+                            let path = expand_path(&path_str);
                             if !path.exists() {
                                 popup.last_error = Some("Project path does not exist.".to_string());
                                 return;
@@ -120,7 +150,7 @@ impl Popup for OpenProjectPopup {
     }
 
     fn height_hint(&self) -> Option<Constraint> {
-        Some(Constraint::Length(5))
+        Some(Constraint::Length(5 + self.completion_rows()))
     }
 }
 
@@ -0,0 +1,307 @@
+use crate::app::MoverrApp;
+use crate::file_size::FileSize;
+use crate::path_ext::{DirectoryStats, DirectoryStatsError};
+use crate::popups::{Popup, PopupFn};
+use crate::size_tree::{scan_size_tree, SizeNode, SizeNodeKind};
+use crate::utils::{impl_as_any_mut, AsAny, AsAnyMut};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::prelude::StatefulWidget;
+use ratatui::prelude::Widget;
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, BorderType, Clear, List, ListItem, ListState, Padding};
+use smol::{spawn, Task};
+use std::cmp::Reverse;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+const BAR_WIDTH: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum View {
+    /// Subdirectories and files of the opened directory.
+    Tree,
+    /// Totals of the whole entry by file extension.
+    Extensions,
+    /// Largest files of the whole entry.
+    LargestFiles,
+}
+
+impl View {
+    fn next(self) -> Self {
+        match self {
+            View::Tree => View::Extensions,
+            View::Extensions => View::LargestFiles,
+            View::LargestFiles => View::Tree,
+        }
+    }
+}
+
+/// Format a row of the breakdown, with `share` of the total from 0 to 1.
+fn breakdown_line(size: FileSize, share: f64, label: &str) -> String {
+    let filled = (share * BAR_WIDTH as f64).round() as usize;
+    let bar = format!("{}{}", "█".repeat(filled), " ".repeat(BAR_WIDTH - filled));
+    format!(
+        "{:>11} {:>5.1}% [{}] {}",
+        size.to_string(),
+        share * 100.0,
+        bar,
+        label
+    )
+}
+
+/// Popup showing what takes up the space inside a directory entry, largest first.
+pub struct SizeBreakdownPopup {
+    entry_name: String,
+    /// Stats of the whole entry, if they were known when the popup was opened.
+    stats: Option<DirectoryStats>,
+    view: View,
+    tree: Arc<Mutex<Option<Result<SizeNode, DirectoryStatsError>>>>,
+    running_total: Arc<Mutex<DirectoryStats>>,
+    /// Dropping the task stops the scan when the popup is closed.
+    _scan: Task<()>,
+    /// Indices of the opened directories, starting from the entry.
+    opened: Vec<usize>,
+    list_state: ListState,
+}
+
+impl SizeBreakdownPopup {
+    pub fn new(entry_name: &str, entry_path: PathBuf, stats: Option<DirectoryStats>) -> Self {
+        let tree = Arc::new(Mutex::new(None));
+        let running_total = Arc::new(Mutex::new(DirectoryStats::default()));
+
+        let scan = spawn({
+            let tree = tree.clone();
+            let running_total = running_total.clone();
+            async move {
+                let result = scan_size_tree(&entry_path, Some(&running_total), None).await;
+                *tree.lock().unwrap() = Some(result);
+            }
+        });
+
+        Self {
+            entry_name: entry_name.to_string(),
+            stats,
+            view: View::Tree,
+            tree,
+            running_total,
+            _scan: scan,
+            opened: Vec::new(),
+            list_state: ListState::default().with_selected(Some(0)),
+        }
+    }
+
+    fn with_shown<T>(&self, f: impl FnOnce(&SizeNode) -> T) -> Option<T> {
+        let tree = self.tree.lock().unwrap();
+        let Some(Ok(tree)) = tree.as_ref() else {
+            return None;
+        };
+        tree.descendant(&self.opened).map(f)
+    }
+
+    fn open_selected(&mut self) {
+        let Some(selected) = self.list_state.selected() else {
+            return;
+        };
+        let is_directory = self
+            .with_shown(|shown| shown.children().get(selected).map(SizeNode::is_directory))
+            .flatten()
+            .unwrap_or(false);
+        if is_directory {
+            self.opened.push(selected);
+            self.list_state.select(Some(0));
+        }
+    }
+
+    fn open_parent(&mut self) {
+        // Keep the directory we came from selected
+        if let Some(index) = self.opened.pop() {
+            self.list_state.select(Some(index));
+        }
+    }
+
+    fn switch_view(&mut self) {
+        self.view = self.view.next();
+        self.opened.clear();
+        self.list_state.select(Some(0));
+    }
+
+    fn title(&self) -> String {
+        let mut title = format!("Size breakdown: {}", self.entry_name);
+        match self.view {
+            View::Tree => {}
+            View::Extensions => return title + " (by extension)",
+            View::LargestFiles => return title + " (largest files)",
+        }
+        let tree = self.tree.lock().unwrap();
+        if let Some(Ok(tree)) = tree.as_ref() {
+            let mut node = tree;
+            for &index in &self.opened {
+                node = &node.children()[index];
+                title.push('/');
+                title.push_str(&node.name);
+            }
+        }
+        title
+    }
+
+    fn tree_items(&self) -> (Vec<ListItem<'static>>, Line<'static>) {
+        let items = self.with_shown(|shown| {
+            shown
+                .children()
+                .iter()
+                .map(|child| {
+                    let line = breakdown_line(child.size, shown.share_of(child.size), &child.name);
+                    match child.kind {
+                        SizeNodeKind::Directory(_) => ListItem::new(line + "/").bold(),
+                        SizeNodeKind::Symlink => ListItem::new(line + " →").gray(),
+                        SizeNodeKind::File => ListItem::new(line),
+                    }
+                })
+                .collect::<Vec<_>>()
+        });
+
+        let hint = match self.tree.lock().unwrap().as_ref() {
+            None => Line::from(format!(
+                "Scanning… {} so far",
+                self.running_total.lock().unwrap().size
+            ))
+            .gray(),
+            Some(Err(err)) => Line::from(format!("Failed to scan: {:?}", err)).red(),
+            Some(Ok(_)) if items.as_ref().is_some_and(Vec::is_empty) => {
+                Line::from("Empty directory").gray()
+            }
+            Some(Ok(_)) => Line::default(),
+        };
+        (items.unwrap_or_default(), hint)
+    }
+
+    fn stats_items(&self) -> (Vec<ListItem<'static>>, Line<'static>) {
+        let Some(stats) = &self.stats else {
+            return (Vec::new(), Line::from("Size wasn't known yet").gray());
+        };
+        let share = |size: FileSize| {
+            if stats.size == FileSize::ZERO {
+                0.0
+            } else {
+                size.as_bytes() as f64 / stats.size.as_bytes() as f64
+            }
+        };
+
+        let items: Vec<_> = match self.view {
+            View::Extensions => {
+                let mut extensions: Vec<_> = stats.extensions.iter().collect();
+                extensions.sort_by_key(|(_, extension_stats)| Reverse(extension_stats.size));
+                extensions
+                    .into_iter()
+                    .map(|(extension, extension_stats)| {
+                        let label = if extension.is_empty() {
+                            format!("(none) × {}", extension_stats.file_count)
+                        } else {
+                            format!(".{} × {}", extension, extension_stats.file_count)
+                        };
+                        ListItem::new(breakdown_line(
+                            extension_stats.size,
+                            share(extension_stats.size),
+                            &label,
+                        ))
+                    })
+                    .collect()
+            }
+            _ => stats
+                .largest_files
+                .iter()
+                .map(|(path, size)| ListItem::new(breakdown_line(*size, share(*size), path)))
+                .collect(),
+        };
+        let hint = if items.is_empty() {
+            Line::from("No files").gray()
+        } else {
+            Line::default()
+        };
+        (items, hint)
+    }
+}
+
+impl Popup for SizeBreakdownPopup {
+    fn render(&mut self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        Clear.render(area, buf);
+
+        let block = Block::bordered()
+            .border_type(BorderType::Thick)
+            .padding(Padding::horizontal(1))
+            .title(self.title())
+            .title_bottom(
+                Line::from("[Enter] Open [Bksp] Up [Tab] View [Esc] Close").right_aligned(),
+            );
+        let inner_area = block.inner(area);
+        block.render(area, buf);
+
+        let [list_area, hint_area] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(inner_area);
+
+        let (items, hint) = match self.view {
+            View::Tree => self.tree_items(),
+            View::Extensions | View::LargestFiles => self.stats_items(),
+        };
+        buf.set_line(
+            hint_area.x,
+            hint_area.y,
+            &hint.right_aligned(),
+            hint_area.width,
+        );
+
+        let list = List::new(items)
+            .highlight_symbol("> ")
+            .highlight_style(Style::default().reversed());
+        StatefulWidget::render(list, list_area, buf, &mut self.list_state);
+    }
+
+    fn handle_key_event(&mut self, key_event: KeyEvent) -> Option<&'static PopupFn> {
+        if key_event.kind != KeyEventKind::Press {
+            return None;
+        }
+
+        match key_event.code {
+            KeyCode::Esc => Some(&|state: &mut MoverrApp| {
+                state.close_popup();
+            }),
+            KeyCode::Up => {
+                self.list_state.select_previous();
+                None
+            }
+            KeyCode::Down => {
+                self.list_state.select_next();
+                None
+            }
+            KeyCode::Home => {
+                self.list_state.select_first();
+                None
+            }
+            KeyCode::End => {
+                self.list_state.select_last();
+                None
+            }
+            KeyCode::Tab => {
+                self.switch_view();
+                None
+            }
+            KeyCode::Enter | KeyCode::Right if self.view == View::Tree => {
+                self.open_selected();
+                None
+            }
+            KeyCode::Backspace | KeyCode::Left if self.view == View::Tree => {
+                self.open_parent();
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+impl_as_any_mut!(SizeBreakdownPopup);
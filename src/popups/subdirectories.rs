@@ -0,0 +1,266 @@
+use crate::app::{MoverrApp, NO_LIBRARY_ERROR};
+use crate::popups::{Popup, PopupFn};
+use crate::project::ProjectState;
+use crate::utils::{impl_as_any_mut, AsAny, AsAnyMut};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::prelude::StatefulWidget;
+use ratatui::prelude::Widget;
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, BorderType, Clear, List, ListItem, ListState, Padding};
+use std::collections::BTreeMap;
+use std::fs::read_dir;
+use std::path::PathBuf;
+
+/// Popup for drilling into a directory entry and moving its subdirectories on their own.
+pub struct SubdirectoriesPopup {
+    pub entry_name: String,
+    entry_path: PathBuf,
+    /// `/`-separated path of the shown directory relative to the entry, empty for the entry itself.
+    relative: String,
+    subdirectories: Vec<String>,
+    /// Subdirectories of the entry that were moved on their own, with where they were moved to.
+    moved: BTreeMap<String, PathBuf>,
+    list_state: ListState,
+    pub last_error: Option<String>,
+}
+
+impl SubdirectoriesPopup {
+    pub fn new(project: &ProjectState, entry_name: &str) -> Self {
+        let mut popup = Self {
+            entry_name: entry_name.to_string(),
+            entry_path: project.directory.join(entry_name),
+            relative: String::new(),
+            subdirectories: Vec::new(),
+            moved: project.moved_subdirectories(entry_name),
+            list_state: ListState::default(),
+            last_error: None,
+        };
+        popup.refresh();
+        popup
+    }
+
+    /// Read the subdirectories of the shown directory again.
+    fn refresh(&mut self) {
+        let path = self
+            .relative
+            .split('/')
+            .filter(|name| !name.is_empty())
+            .fold(self.entry_path.clone(), |path, name| path.join(name));
+
+        self.last_error = None;
+        self.subdirectories = match read_dir(&path) {
+            Ok(children) => children
+                .filter_map(|child| {
+                    let child = child.ok()?;
+                    if !child.path().is_dir() {
+                        return None;
+                    }
+                    child.file_name().into_string().ok()
+                })
+                .collect(),
+            Err(err) => {
+                self.last_error = Some(format!("Failed to read directory: {}", err));
+                Vec::new()
+            }
+        };
+        self.subdirectories.sort();
+        self.list_state
+            .select((!self.subdirectories.is_empty()).then_some(0));
+    }
+
+    fn relative_path(&self, name: &str) -> String {
+        if self.relative.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", self.relative, name)
+        }
+    }
+
+    /// Get the path of the selected subdirectory relative to the entry.
+    fn selected(&self) -> Option<String> {
+        let name = self.subdirectories.get(self.list_state.selected()?)?;
+        Some(self.relative_path(name))
+    }
+
+    fn open_selected(&mut self) {
+        let Some(selected) = self.selected() else {
+            return;
+        };
+        if self.moved.contains_key(&selected) {
+            self.last_error = Some("Moved subdirectories can't be opened".to_string());
+            return;
+        }
+        self.relative = selected;
+        self.refresh();
+    }
+
+    fn open_parent(&mut self) {
+        if self.relative.is_empty() {
+            return;
+        }
+        let name = match self.relative.rsplit_once('/') {
+            Some((parent, name)) => {
+                let name = name.to_string();
+                self.relative = parent.to_string();
+                name
+            }
+            None => std::mem::take(&mut self.relative),
+        };
+        self.refresh();
+        // Keep the directory we came from selected
+        if let Some(index) = self.subdirectories.iter().position(|other| *other == name) {
+            self.list_state.select(Some(index));
+        }
+    }
+
+    fn move_selected(state: &mut MoverrApp) {
+        // Popup shouldn't have changed
+        let popup = state.try_get_popup_mut::<SubdirectoriesPopup>().unwrap();
+        let Some(relative) = popup.selected() else {
+            return;
+        };
+        let entry_name = popup.entry_name.clone();
+
+        let Some(project) = state.project_state.as_ref() else {
+            state.close_popup();
+            return;
+        };
+        let Some(dir) = project.find_directory(&entry_name) else {
+            state.close_popup();
+            return;
+        };
+
+        let Some(library) = project.default_library() else {
+            let popup = state.try_get_popup_mut::<SubdirectoriesPopup>().unwrap();
+            popup.last_error = Some(NO_LIBRARY_ERROR.to_string());
+            return;
+        };
+        let to_path = relative
+            .split('/')
+            .fold(library.path.join(&entry_name), |path, name| path.join(name));
+        match dir.try_start_subdirectory_move_to(project, &relative, to_path) {
+            // The progress is shown in the project table
+            Ok(_) => state.close_popup(),
+            Err(_) => {
+                let popup = state.try_get_popup_mut::<SubdirectoriesPopup>().unwrap();
+                popup.last_error = Some(format!("{} can't be moved right now", relative));
+            }
+        }
+    }
+
+    fn move_selected_back(state: &mut MoverrApp) {
+        // Popup shouldn't have changed
+        let popup = state.try_get_popup_mut::<SubdirectoriesPopup>().unwrap();
+        let Some(relative) = popup.selected() else {
+            return;
+        };
+        let entry_name = popup.entry_name.clone();
+
+        let Some(project) = state.project_state.as_ref() else {
+            state.close_popup();
+            return;
+        };
+        let Some(dir) = project.find_directory(&entry_name) else {
+            state.close_popup();
+            return;
+        };
+
+        match dir.try_start_subdirectory_move_back(project, &relative) {
+            // The progress is shown in the project table
+            Ok(_) => state.close_popup(),
+            Err(_) => {
+                let popup = state.try_get_popup_mut::<SubdirectoriesPopup>().unwrap();
+                popup.last_error = Some(format!("{} can't be moved back right now", relative));
+            }
+        }
+    }
+}
+
+impl Popup for SubdirectoriesPopup {
+    fn render(&mut self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        Clear.render(area, buf);
+
+        let block = Block::bordered()
+            .border_type(BorderType::Thick)
+            .padding(Padding::horizontal(1))
+            .title(if self.relative.is_empty() {
+                format!("Subdirectories: {}", self.entry_name)
+            } else {
+                format!("Subdirectories: {}/{}", self.entry_name, self.relative)
+            })
+            .title_bottom(
+                Line::from("[Enter] Open [Bksp] Up [←/→] Move [Esc] Close").right_aligned(),
+            );
+        let inner_area = block.inner(area);
+        block.render(area, buf);
+
+        let [list_area, hint_area] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(inner_area);
+
+        let items = self.subdirectories.iter().map(|name| {
+            match self.moved.get(&self.relative_path(name)) {
+                Some(path) => ListItem::new(format!("{} → {}", name, path.display())).green(),
+                None => ListItem::new(name.as_str()),
+            }
+        });
+        let list = List::new(items)
+            .highlight_symbol("> ")
+            .highlight_style(Style::default().reversed());
+        StatefulWidget::render(list, list_area, buf, &mut self.list_state);
+
+        if let Some(last_error) = &self.last_error {
+            buf.set_line(
+                hint_area.x,
+                hint_area.y,
+                &Line::from(last_error.as_ref()).red().right_aligned(),
+                hint_area.width,
+            );
+        } else if self.subdirectories.is_empty() {
+            buf.set_line(
+                hint_area.x,
+                hint_area.y,
+                &Line::from("No subdirectories").gray().right_aligned(),
+                hint_area.width,
+            );
+        }
+    }
+
+    fn handle_key_event(&mut self, key_event: KeyEvent) -> Option<&'static PopupFn> {
+        if key_event.kind != KeyEventKind::Press {
+            return None;
+        }
+
+        match key_event.code {
+            KeyCode::Esc => Some(&|state: &mut MoverrApp| {
+                state.close_popup();
+            }),
+            KeyCode::Up => {
+                self.list_state.select_previous();
+                None
+            }
+            KeyCode::Down => {
+                self.list_state.select_next();
+                None
+            }
+            KeyCode::Enter => {
+                self.open_selected();
+                None
+            }
+            KeyCode::Backspace => {
+                self.open_parent();
+                None
+            }
+            KeyCode::Right => Some(&Self::move_selected),
+            KeyCode::Left => Some(&Self::move_selected_back),
+            _ => None,
+        }
+    }
+}
+
+impl_as_any_mut!(SubdirectoriesPopup);
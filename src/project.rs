@@ -1,17 +1,34 @@
 use crate::app::MoverrApp;
+use crate::compatibility::{check_move_compatibility, CompatibilityError, CompatibilityIssue};
+use crate::config::{AppConfig, Preferences};
 use crate::file_size::num_ext::AsBytes;
 use crate::file_size::FileSize;
 use crate::fraction::Fraction;
+use crate::job_queue::{devices_of, JobQueue};
+use crate::launchers::{self, LauncherGame};
+use crate::manifest::{AuditReport, ManifestError, MoveManifest};
+use crate::move_filter::MoveFilter;
 use crate::path_ext::{
-    DirectoryStats, DirectoryStatsError, MoveAndSymlinkProgress, MoveAndSymlinkStage,
-    MoveBackProgress, MoveBackStage, PathExt,
+    allocated_size, collect_links_to, resolve_link_target, ArchiveError, ArchiveProgress,
+    ArchiveStage, DirectoryStats, DirectoryStatsError, MoveAndSymlinkProgress, MoveAndSymlinkStage,
+    MoveBackProgress, MoveBackStage, MoveOptions, MoveStrategy, PathExt, ProcessDirectoryProgress,
+    RestoreProgress, RestoreStage,
 };
 use crate::progress::progress_bar;
+use crate::project_settings::{DestinationLibrary, ProjectSettings, PROJECT_SETTINGS_FILE_NAME};
+use crate::stats_cache::{calc_directory_stats_cached, CachedDirectory, StatsCache};
+use crate::stats_scheduler::{
+    device_of, DeviceId, StatsPriority, StatsScheduler, DEFAULT_CONCURRENCY_PER_DEVICE,
+};
+use crate::steam::{self, read_app_manifests, AppManifest, InstallState};
 use crate::sync::CancellationToken;
+use crate::table_view::{SortKey, TableView};
 use crate::throbber::{throbber_with_style, ThrobberStyle};
+use crate::watcher::ProjectWatcher;
+use crate::widgets::{TextInput, TextInputState};
 use crate::IO_EXECUTOR;
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
 use futures_concurrency::concurrent_stream::IntoConcurrentStream;
-use futures_concurrency::future::Join;
 use log::{debug, error, info, warn};
 use ratatui::buffer::Buffer;
 use ratatui::layout::{Constraint, Flex, Layout, Rect};
@@ -21,25 +38,56 @@ use ratatui::text::{Line, ToLine, ToSpan};
 use ratatui::widgets::block::{title, Title};
 use ratatui::widgets::{Block, ListState, Row, Table, TableState, Widget};
 use ratatui::Frame;
-use smol::{spawn, Executor};
+use smol::Executor;
 use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::read_dir;
 use std::future::Future;
-use std::ops::Deref;
+use std::ops::{Deref, Range};
 use std::path::{Path, PathBuf};
 use std::process::Output;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 use std::{io, thread};
 
 pub struct ProjectState {
     pub directory: PathBuf,
     pub entries: Vec<ProjectEntry>,
+    /// Indices of the entries in the rows of the table, sorted and filtered.
+    rows: Vec<usize>,
+    /// Selects a row of the table, see [`selected_entry`](Self::selected_entry).
     pub table_state: TableState,
+    /// The filter being typed, while the table is being searched.
+    filter_input: Option<TextInputState>,
+    /// Names of the directories marked for running an operation on all of them at once.
+    marked: BTreeSet<String>,
+    pub settings: Arc<Mutex<ProjectSettings>>,
+    /// Preferences from the application config, for what the project settings don't cover.
+    preferences: Preferences,
+    /// Libraries from the application config, used if the project has none of its own.
+    config_libraries: Vec<DestinationLibrary>,
+    stats_cache: Arc<Mutex<StatsCache>>,
     cancellation_token: Arc<CancellationToken>,
+    stats_scheduler: StatsScheduler,
+    /// Moves, archives and restores of the entries, shared with the popup listing them.
+    pub job_queue: Arc<Mutex<JobQueue>>,
+    /// Rows of the project table visible when it was last drawn.
+    visible_rows: Range<usize>,
+    watcher: Option<ProjectWatcher>,
+    /// Entries changed on disk, with when they last changed.
+    changed_entries: BTreeMap<String, Instant>,
+    /// Manifests of the games in the project by their directory, if it's a Steam library.
+    pub steam_apps: BTreeMap<String, AppManifest>,
+    /// Games installed in the project by other launchers, by their directory.
+    pub launcher_games: BTreeMap<String, LauncherGame>,
 }
 
 impl ProjectState {
-    pub fn open(directory: &Path) -> Result<Self, String> {
+    /// How long an entry has to stay unchanged before the changes to it are applied, so that e.g.
+    /// an installation in progress doesn't get measured over and over.
+    const CHANGE_SETTLE_TIME: Duration = Duration::from_secs(2);
+
+    pub fn open(directory: &Path, config: &AppConfig) -> Result<Self, String> {
         let meta = directory.metadata();
 
         if meta.is_err() {
@@ -69,8 +117,19 @@ impl ProjectState {
         // let executor = Executor::new();
         // let executor_ref: &'a Executor = &executor;
 
-        let entries: Vec<ProjectEntry> = read_dir(&directory)
+        let settings = ProjectSettings::load(&directory)?;
+        let stats_cache = StatsCache::load().unwrap_or_else(|err| {
+            warn!(target: "project", "Ignoring stats cache: {}", err);
+            StatsCache::default()
+        });
+
+        let mut entries: Vec<ProjectEntry> = read_dir(&directory)
             .map_err(|e| format!("Failed to read directory: {}", e))?
+            .filter(|entry| {
+                entry.as_ref().map_or(true, |entry| {
+                    entry.file_name() != PROJECT_SETTINGS_FILE_NAME
+                })
+            })
             .map(|entry| {
                 let entry = entry.unwrap();
                 let name = entry
@@ -78,29 +137,18 @@ impl ProjectState {
                     .into_string()
                     .map_err(|name| format!("Failed to convert name to string: {:?}", name))
                     .unwrap();
-                if entry.path().is_dir() {
-                    let is_symlink = entry.path().symlink_metadata().unwrap().is_symlink();
-                    let dir_entry = ProjectEntry::Directory(ProjectDirectoryEntry {
-                        name,
-                        state: if is_symlink {
-                            Arc::new(Mutex::new(ProjectDirectoryEntryState::SymlinkedTo {
-                                path: entry.path().read_link().unwrap(),
-                            }))
-                        } else {
-                            Arc::new(Mutex::new(ProjectDirectoryEntryState::InOriginalLocation))
-                        },
-                        stats: Arc::new(Mutex::new(None)),
-                    });
-
-                    dir_entry
-                } else {
-                    ProjectEntry::File(ProjectFileEntry {
-                        name,
-                        size: entry.metadata().unwrap().len().bytes(),
-                    })
-                }
+                read_entry(&entry.path(), name, &settings).unwrap()
             })
             .collect();
+        // Archived entries are only known from the settings, as they're gone from the directory
+        for (name, entry) in &settings.entries {
+            if let Some(ref archive_path) = entry.archived_to {
+                if !entries.iter().any(|entry| entry.name() == name) {
+                    entries.push(archived_entry(name.clone(), archive_path.clone()));
+                }
+            }
+        }
+        entries.sort_by(|a, b| a.name().cmp(b.name()));
         entries.iter().for_each(|entry| match entry {
             ProjectEntry::Directory(directory) => {
                 debug!(
@@ -115,53 +163,460 @@ impl ProjectState {
             }
         });
 
-        Ok(Self {
+        let watcher = ProjectWatcher::new(&directory)
+            .inspect_err(
+                |err| warn!(target: "watcher", "Changes to the project won't be noticed: {}", err),
+            )
+            .ok();
+
+        let stats_scheduler = StatsScheduler::new(
+            settings
+                .stats_concurrency
+                .or(config.preferences.stats_concurrency)
+                .unwrap_or(DEFAULT_CONCURRENCY_PER_DEVICE),
+        );
+
+        let steam_apps = steam::steamapps_of(&directory)
+            .map(read_app_manifests)
+            .unwrap_or_default();
+        if !steam_apps.is_empty() {
+            info!(target: "steam", "Found {} games in the Steam library", steam_apps.len());
+        }
+        let launcher_games = launcher_games_in(&directory);
+        if !launcher_games.is_empty() {
+            info!(target: "launchers", "Found {} launcher games in the project", launcher_games.len());
+        }
+
+        let mut project_state = Self {
             directory,
             entries,
+            rows: Vec::new(),
             table_state: Default::default(),
+            filter_input: None,
+            marked: BTreeSet::new(),
+            settings: Arc::new(Mutex::new(settings)),
+            preferences: config.preferences.clone(),
+            config_libraries: config.libraries.clone(),
+            stats_cache: Arc::new(Mutex::new(stats_cache)),
             cancellation_token: Arc::new(CancellationToken::new()),
-        })
+            stats_scheduler,
+            job_queue: Arc::new(Mutex::new(JobQueue::new(&IO_EXECUTOR))),
+            visible_rows: 0..0,
+            watcher,
+            changed_entries: BTreeMap::new(),
+            steam_apps,
+            launcher_games,
+        };
+        project_state.update_rows(None);
+        Ok(project_state)
+    }
+
+    /// Apply the changes noticed in the project directory and the locations entries were moved
+    /// to, once they've settled down.
+    fn handle_fs_changes(&mut self) {
+        let Some(ref mut watcher) = self.watcher else {
+            return;
+        };
+        watcher.set_targets(moved_locations(
+            &self.entries,
+            &self.settings.lock().unwrap(),
+        ));
+
+        let now = Instant::now();
+        for path in watcher.changed_paths() {
+            let name = if let Ok(relative) = path.strip_prefix(&self.directory) {
+                self.stats_cache.lock().unwrap().invalidate(&path);
+                relative
+                    .components()
+                    .next()
+                    .map(|name| name.as_os_str().to_string_lossy().into_owned())
+            } else if let Some((target, name)) = watcher
+                .targets()
+                .iter()
+                .find(|(target, _)| path.starts_with(target))
+            {
+                // Fully moved entries are cached under their symlink in the project directory
+                let mut stats_cache = self.stats_cache.lock().unwrap();
+                stats_cache.invalidate(&path);
+                stats_cache.invalidate(
+                    &self
+                        .directory
+                        .join(name)
+                        .join(path.strip_prefix(target).unwrap()),
+                );
+                Some(name.clone())
+            } else {
+                None
+            };
+
+            match name {
+                Some(name) if name != PROJECT_SETTINGS_FILE_NAME => {
+                    self.changed_entries.insert(name, now);
+                }
+                _ => {}
+            }
+        }
+
+        let settled: Vec<String> = self
+            .changed_entries
+            .iter()
+            .filter(|(_, changed_at)| now.duration_since(**changed_at) >= Self::CHANGE_SETTLE_TIME)
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in settled {
+            self.changed_entries.remove(&name);
+            self.refresh_entry(&name);
+        }
+    }
+
+    /// Read the entry `name` again, adding or removing it if it appeared or disappeared.
+    fn refresh_entry(&mut self, name: &str) {
+        // The indices of the rows change with the entries
+        let selected = self.selected_entry().map(|entry| entry.name().to_string());
+        self.refresh_entry_keeping_rows(name);
+        if !self.entries.iter().any(|entry| entry.name() == name) {
+            self.marked.remove(name);
+        }
+        self.update_rows(selected);
+    }
+
+    fn refresh_entry_keeping_rows(&mut self, name: &str) {
+        let path = self.directory.join(name);
+        let index = self.entries.iter().position(|entry| entry.name() == name);
+        let exists = path.symlink_metadata().is_ok();
+
+        match index {
+            Some(index) if !exists => {
+                if let ProjectEntry::Directory(ref dir) = self.entries[index] {
+                    if dir.is_busy() || dir.is_archived() {
+                        return;
+                    }
+                }
+                self.entries.remove(index);
+                info!(target: "watcher", "{} was removed", name);
+            }
+            None if exists => {
+                let entry =
+                    match read_entry(&path, name.to_string(), &self.settings.lock().unwrap()) {
+                        Ok(entry) => entry,
+                        Err(err) => {
+                            warn!(target: "watcher", "Failed to read {}: {}", path.display(), err);
+                            return;
+                        }
+                    };
+                info!(target: "watcher", "{} was added", name);
+                let index = self
+                    .entries
+                    .iter()
+                    .position(|other| other.name() > name)
+                    .unwrap_or(self.entries.len());
+                self.entries.insert(index, entry);
+                self.queue_calc(index);
+            }
+            Some(index) => {
+                if let ProjectEntry::Directory(ref dir) = self.entries[index] {
+                    if dir.is_busy() {
+                        return;
+                    }
+                }
+
+                let settings = self.settings.lock().unwrap();
+                let result = match self.entries[index] {
+                    // The directory could've been moved or moved back outside of Moverr
+                    ProjectEntry::Directory(ref dir) if path.is_dir() => {
+                        read_directory_state(&path, name, &settings)
+                            .map(|state| *dir.state.lock().unwrap() = state)
+                    }
+                    ProjectEntry::File(ref mut file) if !path.is_dir() => {
+                        path.symlink_metadata().map(|metadata| {
+                            file.size = metadata.len().bytes();
+                            file.allocated_size = allocated_size(&path, &metadata);
+                        })
+                    }
+                    _ => read_entry(&path, name.to_string(), &settings).map(|entry| {
+                        info!(target: "watcher", "{} was replaced", name);
+                        self.entries[index] = entry;
+                    }),
+                };
+                drop(settings);
+
+                match result {
+                    Ok(_) => {
+                        debug!(target: "watcher", "{} changed", name);
+                        if let ProjectEntry::Directory(_) = self.entries[index] {
+                            self.restart_calc(Some(name));
+                        }
+                    }
+                    Err(err) => {
+                        warn!(target: "watcher", "Failed to read {}: {}", path.display(), err);
+                    }
+                }
+            }
+            None => {}
+        }
     }
 
     pub fn try_close(&mut self) -> Result<(), String> {
+        let queued_count = self.job_queue.lock().unwrap().queued_count();
+        if queued_count > 0 {
+            return Err(format!(
+                "{} jobs are still queued, cancel them first!",
+                queued_count
+            ));
+        }
         self.entries.clear();
+        self.rows.clear();
         Ok(())
     }
 
+    /// Get the entry in the selected row of the table.
+    pub fn selected_entry(&self) -> Option<&ProjectEntry> {
+        self.table_state
+            .selected()
+            .and_then(|row| self.rows.get(row))
+            .map(|&index| &self.entries[index])
+    }
+
+    pub fn has_marks(&self) -> bool {
+        !self.marked.is_empty()
+    }
+
+    /// Mark or unmark the selected directory, then select the next row.
+    pub fn toggle_mark(&mut self) {
+        if let Some(ProjectEntry::Directory(dir)) = self.selected_entry() {
+            let name = dir.name.clone();
+            if !self.marked.remove(&name) {
+                self.marked.insert(name);
+            }
+        }
+        self.table_state.select_next();
+    }
+
+    /// Mark all directories shown in the table.
+    pub fn mark_all(&mut self) {
+        for &index in &self.rows {
+            if let ProjectEntry::Directory(ref dir) = self.entries[index] {
+                self.marked.insert(dir.name.clone());
+            }
+        }
+    }
+
+    /// Mark the directories shown in the table that aren't marked, and unmark the ones that are.
+    pub fn invert_marks(&mut self) {
+        for &index in &self.rows {
+            if let ProjectEntry::Directory(ref dir) = self.entries[index] {
+                if !self.marked.remove(&dir.name) {
+                    self.marked.insert(dir.name.clone());
+                }
+            }
+        }
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.marked.clear();
+    }
+
+    /// Get the marked directories, in the order of the entries.
+    pub fn marked_directories(&self) -> impl Iterator<Item = &ProjectDirectoryEntry> {
+        self.entries.iter().filter_map(|entry| match entry {
+            ProjectEntry::Directory(dir) if self.marked.contains(&dir.name) => Some(dir),
+            _ => None,
+        })
+    }
+
+    /// Sort and filter the rows of the table again, keeping the entry `selected` selected if it's
+    /// still shown.
+    fn update_rows(&mut self, selected: Option<String>) {
+        let view = self.settings.lock().unwrap().view.clone();
+        let mut rows: Vec<usize> = (0..self.entries.len())
+            .filter(|&index| {
+                let name = self.entries[index].name();
+                view.matches(&[name, self.title_of(name)])
+            })
+            .collect();
+        // The entries are sorted by name already, which breaks the ties
+        match view.sort_key {
+            SortKey::Name => view.sort_rows(&mut rows, |&index| {
+                Some(self.title_of(self.entries[index].name()).to_lowercase())
+            }),
+            SortKey::Size => view.sort_rows(&mut rows, |&index| self.entries[index].size()),
+            SortKey::State => {
+                view.sort_rows(&mut rows, |&index| Some(self.entries[index].state_rank()))
+            }
+            SortKey::Accessed => view.sort_rows(&mut rows, |&index| self.entries[index].accessed()),
+        }
+
+        let selected_row = selected
+            .and_then(|name| {
+                rows.iter()
+                    .position(|&index| self.entries[index].name() == name)
+            })
+            .or_else(|| {
+                let last_row = rows.len().checked_sub(1)?;
+                Some(self.table_state.selected().unwrap_or(0).min(last_row))
+            });
+        self.rows = rows;
+        self.table_state.select(selected_row);
+    }
+
+    /// Change the view of the table and store it with the project.
+    fn update_view(&mut self, update: impl FnOnce(&mut TableView)) {
+        let selected = self.selected_entry().map(|entry| entry.name().to_string());
+        let mut settings = self.settings.lock().unwrap();
+        update(&mut settings.view);
+        if let Err(err) = settings.save(&self.directory) {
+            error!("Failed to save project settings: {}", err);
+        }
+        drop(settings);
+        self.update_rows(selected);
+    }
+
+    /// Sort the table by the next column.
+    pub fn cycle_sort_key(&mut self) {
+        self.update_view(|view| view.sort_key = view.sort_key.next());
+    }
+
+    pub fn toggle_sort_order(&mut self) {
+        self.update_view(|view| view.descending = !view.descending);
+    }
+
+    /// Start typing a filter for the table, starting from the current one.
+    pub fn start_filter(&mut self) {
+        let mut input = TextInputState::default();
+        input.set_input(&self.settings.lock().unwrap().view.filter);
+        self.filter_input = Some(input);
+    }
+
+    /// Handle a key while the filter is being typed, narrowing the table as it changes. Enter
+    /// keeps the filter and Esc clears it. Returns `false` if the key isn't for the filter, like
+    /// Up and Down, which still select rows.
+    pub fn handle_filter_key_event(&mut self, key_event: KeyEvent) -> bool {
+        let Some(ref mut input) = self.filter_input else {
+            return false;
+        };
+        if key_event.kind != KeyEventKind::Press {
+            return true;
+        }
+        match key_event.code {
+            KeyCode::Up | KeyCode::Down => return false,
+            KeyCode::Enter => {
+                self.filter_input = None;
+                // Only stored once it's done, not on every key
+                self.update_view(|_| {});
+            }
+            KeyCode::Esc => {
+                self.filter_input = None;
+                self.update_view(|view| view.filter.clear());
+            }
+            _ => {
+                if input.handle_key_event(key_event) {
+                    let filter = input.input_as_string();
+                    let selected = self.selected_entry().map(|entry| entry.name().to_string());
+                    self.settings.lock().unwrap().view.filter = filter;
+                    self.update_rows(selected);
+                }
+            }
+        }
+        true
+    }
+
     pub fn draw(&mut self, frame: &mut Frame, area: Rect, focused: bool) {
-        let widths = [
+        let view = self.settings.lock().unwrap().view.clone();
+        // Sizes and states change while the table is shown
+        if matches!(view.sort_key, SortKey::Size | SortKey::State) {
+            let selected = self.selected_entry().map(|entry| entry.name().to_string());
+            self.update_rows(selected);
+        }
+        let (area, filter_area) = match self.filter_input {
+            Some(_) => {
+                let [area, filter_area] =
+                    Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(area);
+                (area, Some(filter_area))
+            }
+            None => (area, None),
+        };
+
+        let is_steam_library = !self.steam_apps.is_empty();
+        let mut widths = vec![
             Constraint::Min(25),
-            Constraint::Length(10),
+            Constraint::Length(13),
+            Constraint::Length(13),
             Constraint::Percentage(70),
         ];
-        let progress_width = Layout::horizontal(widths).split(area)[2].width as usize;
+        let mut header = vec!["Name", "Size", "On disk", ""];
+        if is_steam_library {
+            widths.splice(
+                1..1,
+                [
+                    Constraint::Length(8),
+                    Constraint::Length(15),
+                    Constraint::Length(13),
+                ],
+            );
+            header.splice(1..1, ["App ID", "Steam state", "Steam size"]);
+        }
+        let progress_width =
+            Layout::horizontal(&widths).split(area)[widths.len() - 1].width as usize;
+        let settings = self.settings.lock().unwrap();
+        let job_queue = self.job_queue.lock().unwrap();
         let widget = Table::new(
-            self.entries.iter().enumerate().map(|(id, entry)| {
+            self.rows.iter().enumerate().map(|(row, &index)| {
+                let entry = &self.entries[index];
                 let mut style = Style::default();
-                let is_selected = self.table_state.selected() == Some(id);
+                let is_selected = self.table_state.selected() == Some(row);
                 // if is_selected {
                 //     style = style.reversed();
                 // }
                 match entry {
                     ProjectEntry::Directory(directory) => {
                         let name = &directory.name;
-                        let mut name_fmt = Line::from(name.clone());
+                        let mut name_fmt = if self.marked.contains(name) {
+                            Line::from(format!("● {}", self.title_of(name))).bold()
+                        } else {
+                            Line::from(self.title_of(name).to_string())
+                        };
                         if is_selected {
                             name_fmt = name_fmt.reversed();
                         }
                         let stats = directory.stats();
-                        let size_cell = match stats {
-                            Some(Ok(ref stats)) => stats.size.to_string(),
+                        let partial = directory.partial_stats();
+                        let size_cell = |size: fn(&DirectoryStats) -> FileSize| match stats {
+                            Some(Ok(ref stats)) => size(stats).to_string(),
                             Some(Err(_)) => "⚠️".into(),
-                            None => throbber_with_style(frame, &ThrobberStyle::BRAILLE_CIRCLE)
-                                .to_string(),
+                            None => match partial {
+                                Some(ref partial) if partial.file_count > 0 => {
+                                    format!("≥ {}…", size(partial))
+                                }
+                                _ if self.stats_scheduler.is_queued(name) => "queued".into(),
+                                _ => throbber_with_style(frame, &ThrobberStyle::BRAILLE_CIRCLE)
+                                    .to_string(),
+                            },
                         };
+                        let mut size_cells = [
+                            size_cell(|stats| stats.size),
+                            size_cell(|stats| stats.allocated_size),
+                        ];
+                        let queue_position = job_queue.queue_position(name);
                         let state: Line = match directory.state.lock().unwrap().deref() {
                             ProjectDirectoryEntryState::InOriginalLocation => match stats {
                                 Some(Ok(ref stats)) => {
+                                    let issue_count = directory.compatibility_issues().len();
+                                    let moved_count = settings
+                                        .entry(name)
+                                        .map_or(0, |entry| entry.moved_subdirectories.len());
                                     if stats.symlink_count > 0 {
                                         style = style.red();
                                         "Can't move: has symlinks".into()
+                                    } else if issue_count > 0 {
+                                        style = style.red();
+                                        format!(
+                                            "Can't move: {} incompatible names at destination",
+                                            issue_count
+                                        )
+                                        .into()
+                                    } else if moved_count > 0 {
+                                        style = style.green();
+                                        format!("⇢ {} subdirectories moved", moved_count).into()
                                     } else {
                                         "".into()
                                     }
@@ -174,7 +629,66 @@ impl ProjectState {
                             },
                             ProjectDirectoryEntryState::SymlinkedTo { path } => {
                                 style = style.green();
-                                format!("→ {}", path.display()).into()
+                                let target = format!("→ {}", path.display());
+                                match directory.audit.lock().unwrap().deref() {
+                                    None => target.into(),
+                                    Some(AuditState::Queued) => {
+                                        format!("{} (AUDIT QUEUED)", target).into()
+                                    }
+                                    Some(AuditState::InProgress { progress }) => {
+                                        let progress = progress.lock().unwrap();
+                                        let checked = if progress.total_size > FileSize::ZERO {
+                                            progress.copied_size_frac()
+                                        } else {
+                                            Fraction::try_from(0.0).unwrap()
+                                        };
+                                        let str = format!(
+                                            "{} (AUDITING {:.1}% {}/{})",
+                                            target,
+                                            checked.into_percent(),
+                                            progress.processed_size,
+                                            progress.total_size
+                                        );
+                                        progress_bar(Cow::Owned(str), checked, progress_width)
+                                    }
+                                    Some(AuditState::Finished(Ok(report))) => {
+                                        if report.is_clean() {
+                                            format!("{} (✔ Audited)", target).into()
+                                        } else {
+                                            style = style.red();
+                                            format!(
+                                                "{} (⚠ {} missing, {} extra, {} corrupted)",
+                                                target,
+                                                report.missing.len(),
+                                                report.extra.len(),
+                                                report.corrupted.len()
+                                            )
+                                            .into()
+                                        }
+                                    }
+                                    Some(AuditState::Finished(Err(err))) => {
+                                        style = style.yellow();
+                                        format!("{} (Couldn't audit: {:?})", target, err).into()
+                                    }
+                                }
+                            }
+                            ProjectDirectoryEntryState::PartiallySymlinkedTo { path } => {
+                                style = style.green();
+                                format!("⇢ {} (partially)", path.display()).into()
+                            }
+                            ProjectDirectoryEntryState::MovingTo { path, .. }
+                            | ProjectDirectoryEntryState::MovingFrom { path, .. }
+                            | ProjectDirectoryEntryState::Archiving { path, .. }
+                            | ProjectDirectoryEntryState::Restoring { path, .. }
+                                if queue_position.is_some() =>
+                            {
+                                style = style.blue();
+                                format!(
+                                    "{} (QUEUED #{})",
+                                    path.display(),
+                                    queue_position.unwrap() + 1
+                                )
+                                .into()
                             }
                             ProjectDirectoryEntryState::MovingTo { path, progress } => {
                                 let progress = progress.lock().unwrap();
@@ -192,6 +706,8 @@ impl ProjectState {
                                     throbber_with_style(frame, &ThrobberStyle::ARROW_RIGHT),
                                     path.display(),
                                     match stage {
+                                        MoveAndSymlinkStage::CheckingNames =>
+                                            "CHECKING NAMES".to_string(),
                                         MoveAndSymlinkStage::Copying => format!(
                                             "COPYING {:.1}% {}/{}",
                                             percentage, processed_size, total_size
@@ -200,7 +716,14 @@ impl ProjectState {
                                             "VERIFYING {:.1}% {}/{}",
                                             percentage, processed_size, total_size
                                         ),
+                                        MoveAndSymlinkStage::WritingManifest => format!(
+                                            "WRITING MANIFEST {:.1}% {}/{}",
+                                            percentage, processed_size, total_size
+                                        ),
                                         MoveAndSymlinkStage::Symlinking => "SYMLINKING".to_string(),
+                                        MoveAndSymlinkStage::RemovingOriginal => {
+                                            "REMOVING ORIGINAL".to_string()
+                                        }
                                         MoveAndSymlinkStage::Finished => String::new(),
                                     }
                                 );
@@ -237,8 +760,98 @@ impl ProjectState {
                                 );
                                 progress_bar(Cow::Owned(str), copied, progress_width)
                             }
+                            ProjectDirectoryEntryState::Archiving { path, progress } => {
+                                let progress = progress.lock().unwrap();
+                                let stage = progress.stage;
+                                style = style.blue();
+                                let stage_progress = progress.progress.lock().unwrap();
+                                let copied = stage_progress.copied_size_frac();
+                                let percentage = copied.into_percent();
+                                let processed_size = stage_progress.processed_size;
+                                let total_size = stage_progress.total_size;
+                                drop(stage_progress);
+                                drop(progress);
+                                let str = format!(
+                                    "{} {} ({})",
+                                    throbber_with_style(frame, &ThrobberStyle::ARROW_RIGHT),
+                                    path.display(),
+                                    match stage {
+                                        ArchiveStage::Indexing => format!(
+                                            "INDEXING {:.1}% {}/{}",
+                                            percentage, processed_size, total_size
+                                        ),
+                                        ArchiveStage::Packing => format!(
+                                            "PACKING {:.1}% {}/{}",
+                                            percentage, processed_size, total_size
+                                        ),
+                                        ArchiveStage::Verifying => format!(
+                                            "VERIFYING {:.1}% {}/{}",
+                                            percentage, processed_size, total_size
+                                        ),
+                                        ArchiveStage::RemovingOriginal =>
+                                            "REMOVING ORIGINAL".to_string(),
+                                        ArchiveStage::Finished => String::new(),
+                                    }
+                                );
+                                progress_bar(Cow::Owned(str), copied, progress_width)
+                            }
+                            ProjectDirectoryEntryState::Archived {
+                                path,
+                                archived_size,
+                            } => {
+                                style = style.cyan();
+                                size_cells[1] = archived_size.to_string();
+                                match stats {
+                                    Some(Ok(ref stats)) if *archived_size > FileSize::ZERO => {
+                                        let ratio = stats.size.as_bytes() as f64
+                                            / archived_size.as_bytes() as f64;
+                                        format!(
+                                            "▣ {} (archived, {:.2}× compression)",
+                                            path.display(),
+                                            ratio
+                                        )
+                                        .into()
+                                    }
+                                    _ => format!("▣ {} (archived)", path.display()).into(),
+                                }
+                            }
+                            ProjectDirectoryEntryState::Restoring { path, progress } => {
+                                let progress = progress.lock().unwrap();
+                                let stage = progress.stage;
+                                style = style.blue();
+                                let stage_progress = progress.progress.lock().unwrap();
+                                let copied = stage_progress.copied_size_frac();
+                                let percentage = copied.into_percent();
+                                let processed_size = stage_progress.processed_size;
+                                let total_size = stage_progress.total_size;
+                                drop(stage_progress);
+                                drop(progress);
+                                let str = format!(
+                                    "{} {} ({})",
+                                    throbber_with_style(frame, &ThrobberStyle::ARROW_LEFT),
+                                    path.display(),
+                                    match stage {
+                                        RestoreStage::Extracting => format!(
+                                            "EXTRACTING {:.1}% {}/{}",
+                                            percentage, processed_size, total_size
+                                        ),
+                                        RestoreStage::Verifying => format!(
+                                            "VERIFYING {:.1}% {}/{}",
+                                            percentage, processed_size, total_size
+                                        ),
+                                        RestoreStage::Finished => String::new(),
+                                    }
+                                );
+                                progress_bar(Cow::Owned(str), copied, progress_width)
+                            }
                         };
-                        Row::new([name_fmt, size_cell.into(), state]).style(style)
+                        let [size_cell, allocated_size_cell] = size_cells;
+                        let mut cells =
+                            vec![name_fmt, size_cell.into(), allocated_size_cell.into(), state];
+                        if is_steam_library {
+                            cells.splice(1..1, self.steam_cells(name));
+                        }
+                        Row::new(cells).style(style)
                     }
                     ProjectEntry::File(file) => {
                         let name = &file.name;
@@ -246,125 +859,1012 @@ impl ProjectState {
                         if is_selected {
                             name_fmt = name_fmt.reversed();
                         }
-                        Row::new(vec![name_fmt, file.size.to_string().into()]).style(style)
+                        let mut cells = vec![
+                            name_fmt,
+                            file.size.to_string().into(),
+                            file.allocated_size.to_string().into(),
+                        ];
+                        if is_steam_library {
+                            cells.splice(1..1, self.steam_cells(name));
+                        }
+                        Row::new(cells).style(style)
                     }
                 }
             }),
             widths,
         )
-        .header(Row::new(["Name", "Size", ""]).bold().reversed())
+        .header(Row::new(header).bold().reversed())
         .block(
             Block::bordered()
                 .title(format!("Project: {}", self.directory.display()))
+                .title(Line::from(view.describe()).right_aligned())
+                .title(
+                    Line::from(if self.marked.is_empty() {
+                        String::new()
+                    } else {
+                        format!("{} marked", self.marked.len())
+                    })
+                    .bold()
+                    .right_aligned(),
+                )
+                .title(Line::from(job_queue.describe()).right_aligned())
                 .title_bottom(
                     Line::from(if focused {
-                        "[↑/↓] Select [←/→] Move [Home/End] First/Last [Esc] Menu"
+                        "[↑/↓] Select [←/→] Move [Enter] Subdirectories [E] Exclude [B] Breakdown [D] Duplicates [Z] Archive [A] Audit [R] Rescan [J] Jobs [Space/M/I/U] Mark/All/Invert/None [S/O] Sort/Order [/] Filter [Home/End] First/Last [Esc] Menu"
                     } else {
                         ""
                     })
                     .right_aligned(),
                 ),
         );
+        drop(settings);
+        drop(job_queue);
         frame.render_stateful_widget(widget, area, &mut self.table_state);
+        if let (Some(filter_area), Some(filter_input)) = (filter_area, self.filter_input.as_mut()) {
+            let [label_area, input_area] =
+                Layout::horizontal([Constraint::Length(8), Constraint::Fill(1)]).areas(filter_area);
+            frame.render_widget(Line::from("Filter:").bold(), label_area);
+            frame.render_stateful_widget(TextInput::default(), input_area, filter_input);
+        }
+        // Without the borders and the header
+        let row_count = area.height.saturating_sub(3) as usize;
+        self.visible_rows = self.table_state.offset()..self.table_state.offset() + row_count;
     }
 
-    pub fn start_calc(&self) {
-        let futures: Vec<_> = self
-            .entries
-            .iter()
-            .filter_map(|entry| match entry {
-                ProjectEntry::Directory(dir) => Some({
-                    let path = self.directory.join(&dir.name);
-                    let cancellation_token = self.cancellation_token.clone();
-                    let stats_mutex = dir.stats.clone();
-
-                    // info!("Spawning…");
-                    async move {
-                        // info!("Spawned!");
-                        let result = path.calc_directory_stats(Some(&cancellation_token)).await;
-                        let mut stats = stats_mutex.lock().unwrap();
-                        if let Ok(ref result) = result {
-                            debug!(
-                                target: "io-thread",
-                                "Calculated that {} is {}",
-                                path.file_name().unwrap().to_str().unwrap(),
-                                result.size.to_string()
-                            );
-                        } else {
-                            warn!(
-                                target: "io-thread",
-                                "Failed to calculate stats for {}: {:?}",
-                                path.display(),
-                                result
-                            );
-                        }
-                        *stats = Some(result);
-                    }
-                }),
-                ProjectEntry::File(_) => None,
-            })
-            .collect();
-
-        spawn(async { futures.join().await }).detach();
+    /// Get the title of the game in the entry `name`, or the name if it isn't a known game.
+    fn title_of<'a>(&'a self, name: &'a str) -> &'a str {
+        match (self.steam_apps.get(name), self.launcher_games.get(name)) {
+            (Some(app), _) => &app.name,
+            (None, Some(game)) => &game.title,
+            (None, None) => name,
+        }
     }
-}
-
-#[derive(Debug)]
-pub enum ProjectDirectoryEntryState {
-    /// The directory is in its original location.
-    InOriginalLocation,
-    /// The directory is symlinked to another location.
-    SymlinkedTo { path: PathBuf },
-    /// The directory is being moved to another location.
-    MovingTo {
-        path: PathBuf,
-        progress: Arc<Mutex<MoveAndSymlinkProgress>>,
-    },
-    /// The directory is being moved back from another location.
-    MovingFrom {
-        path: PathBuf,
-        progress: Arc<Mutex<MoveBackProgress>>,
-    },
-}
-
-#[derive(Debug)]
-pub enum ProjectEntry {
-    Directory(ProjectDirectoryEntry),
-    File(ProjectFileEntry),
-}
 
-#[derive(Debug)]
-pub struct ProjectDirectoryEntry {
-    pub name: String,
-    pub state: Arc<Mutex<ProjectDirectoryEntryState>>,
-    stats: Arc<Mutex<Option<Result<DirectoryStats, DirectoryStatsError>>>>,
-}
-
-impl ProjectDirectoryEntry {
-    pub fn stats(&self) -> Option<Result<DirectoryStats, DirectoryStatsError>> {
-        self.stats.lock().ok()?.clone()
+    /// Get the cells with what Steam knows about the entry `name`, which are empty if it's not a
+    /// game.
+    fn steam_cells(&self, name: &str) -> Vec<Line<'static>> {
+        match self.steam_apps.get(name) {
+            Some(app) => {
+                let state = app.install_state();
+                vec![
+                    app.app_id.to_string().into(),
+                    if state == InstallState::Installed {
+                        state.label().into()
+                    } else {
+                        Line::from(state.label()).yellow()
+                    },
+                    app.size_on_disk.to_string().into(),
+                ]
+            }
+            None => vec![Line::default(); 3],
+        }
     }
 
-    pub fn set_stats(&self, stats: Result<DirectoryStats, DirectoryStatsError>) {
-        let mut mutex_guard = self.stats.lock().unwrap();
-        assert!(mutex_guard.is_none());
-        *mutex_guard = Some(stats);
+    pub fn start_calc(&mut self) {
+        for index in 0..self.entries.len() {
+            self.queue_calc(index);
+        }
     }
 
-    pub fn can_be_moved(&self) -> bool {
-        match self.state.lock().unwrap().deref() {
-            ProjectDirectoryEntryState::InOriginalLocation => self.stats().map_or(false, |stats| {
-                stats
-                    .as_ref()
-                    .map_or(false, |stats| stats.symlink_count == 0)
-            }),
-            _ => false,
+    /// Forget the stats of the entry `name`, or of all entries if `None`, and calculate them
+    /// again, e.g. after its exclude patterns changed.
+    pub fn restart_calc(&mut self, name: Option<&str>) {
+        for index in 0..self.entries.len() {
+            match self.entries[index] {
+                ProjectEntry::Directory(ref dir) if name.is_none_or(|name| name == dir.name) => {
+                    *dir.stats.lock().unwrap() = EntryStats::Unknown;
+                    self.queue_calc(index);
+                }
+                _ => {}
+            }
         }
     }
 
-    pub fn can_be_moved_back(&self) -> bool {
+    /// Forget everything cached about the entry `name` and measure it again from scratch, with the
+    /// highest priority if it's selected.
+    pub fn rescan(&mut self, name: &str) {
+        let Some(dir) = self.find_directory(name) else {
+            return;
+        };
+        let sources = stats_sources(
+            &self.directory,
+            &self.settings.lock().unwrap(),
+            name,
+            &dir.state.lock().unwrap(),
+        );
+        let mut stats_cache = self.stats_cache.lock().unwrap();
+        for (path, _) in sources {
+            stats_cache.remove(&path);
+        }
+        drop(stats_cache);
+        self.restart_calc(Some(name));
+    }
+
+    /// Queue calculating the stats of the directory entry at `index`, showing its cached stats
+    /// until then.
+    fn queue_calc(&mut self, index: usize) {
+        let ProjectEntry::Directory(ref dir) = self.entries[index] else {
+            return;
+        };
+        if let ProjectDirectoryEntryState::Archived { path, .. } = dir.state.lock().unwrap().deref()
+        {
+            // There's nothing to measure, but the index recorded what the entry contained
+            let index_path = MoveManifest::path_for(path);
+            let stats = dir.stats.clone();
+            IO_EXECUTOR
+                .spawn(async move {
+                    let result = MoveManifest::read(&index_path)
+                        .await
+                        .map(|manifest| manifest.stats)
+                        .map_err(|err| {
+                            warn!(target: "project", "Failed to read {:?}: {:?}", index_path, err);
+                            match err {
+                                ManifestError::Io(kind) => DirectoryStatsError::Io(kind),
+                                ManifestError::Parse => {
+                                    DirectoryStatsError::Io(io::ErrorKind::InvalidData)
+                                }
+                                ManifestError::Cancelled => DirectoryStatsError::Cancelled,
+                            }
+                        });
+                    *stats.lock().unwrap() = EntryStats::Known(result);
+                })
+                .detach();
+            return;
+        }
+        let sources = stats_sources(
+            &self.directory,
+            &self.settings.lock().unwrap(),
+            &dir.name,
+            &dir.state.lock().unwrap(),
+        );
+        show_cached_stats(&sources, &self.stats_cache.lock().unwrap(), &dir.stats);
+        let device = sources.first().and_then(|(path, _)| device_of(path));
+        let future = calc_entry_stats(
+            dir.name.clone(),
+            sources,
+            dir.stats.clone(),
+            self.stats_cache.clone(),
+            self.cancellation_token.clone(),
+        );
+        self.stats_scheduler
+            .enqueue(dir.name.clone(), device, future);
+    }
+
+    /// Do the periodic work of the project: applying changes on disk and starting queued jobs and
+    /// stats calculations.
+    pub fn tick(&mut self) {
+        self.handle_fs_changes();
+        self.job_queue.lock().unwrap().pump();
+
+        let selected = self
+            .table_state
+            .selected()
+            .and_then(|row| self.rows.get(row))
+            .map(|&index| self.entries[index].name());
+        let visible: Vec<&str> = self
+            .rows
+            .get(self.visible_rows.start..self.visible_rows.end.min(self.rows.len()))
+            .unwrap_or_default()
+            .iter()
+            .map(|&index| self.entries[index].name())
+            .collect();
+        let finished = self.stats_scheduler.pump(|name| {
+            if selected == Some(name) {
+                StatsPriority::Selected
+            } else if visible.contains(&name) {
+                StatsPriority::Visible
+            } else {
+                StatsPriority::Background
+            }
+        });
+        if finished {
+            save_stats_cache(&self.stats_cache);
+        }
+    }
+
+    /// Build the filter for the entry `name` from the project settings.
+    pub fn filter_for(&self, name: &str) -> MoveFilter {
+        filter_or_default(self.settings.lock().unwrap().filter_for(name), name)
+    }
+
+    /// Get the subdirectories of the entry `name` that were moved on their own.
+    pub fn moved_subdirectories(&self, name: &str) -> BTreeMap<String, PathBuf> {
+        self.settings
+            .lock()
+            .unwrap()
+            .entry(name)
+            .map(|entry| entry.moved_subdirectories.clone())
+            .unwrap_or_default()
+    }
+
+    /// Get the project directory and the locations its entries were moved to.
+    pub fn locations(&self) -> Vec<PathBuf> {
+        let moved = moved_locations(&self.entries, &self.settings.lock().unwrap());
+        std::iter::once(self.directory.clone())
+            .chain(moved.into_keys())
+            .collect()
+    }
+
+    /// Get the options entries are moved with unless chosen otherwise.
+    pub fn move_options(&self) -> MoveOptions {
+        MoveOptions {
+            hash_files: self.preferences.hash_files,
+            ..MoveOptions::default()
+        }
+    }
+
+    /// Get the libraries entries can be moved to, which are the project's own if it has any.
+    pub fn libraries(&self) -> Vec<DestinationLibrary> {
+        let settings = self.settings.lock().unwrap();
+        if settings.libraries.is_empty() {
+            self.config_libraries.clone()
+        } else {
+            settings.libraries.clone()
+        }
+    }
+
+    /// Get the library entries are moved to unless chosen otherwise.
+    pub fn default_library(&self) -> Option<DestinationLibrary> {
+        let libraries = self.libraries();
+        self.settings
+            .lock()
+            .unwrap()
+            .default_library(&libraries)
+            .cloned()
+    }
+
+    /// Get how much of `library` the entries of the project take up, as far as their stats are
+    /// known. Subdirectories moved on their own count toward the library they were moved to.
+    pub fn library_usage(&self, library: &DestinationLibrary) -> FileSize {
+        let mut usage = FileSize::ZERO;
+        for entry in &self.entries {
+            let ProjectEntry::Directory(dir) = entry else {
+                continue;
+            };
+            let size = match dir.state.lock().unwrap().deref() {
+                ProjectDirectoryEntryState::Archived {
+                    path,
+                    archived_size,
+                } if path.starts_with(&library.path) => Some(*archived_size),
+                ProjectDirectoryEntryState::SymlinkedTo { path }
+                | ProjectDirectoryEntryState::PartiallySymlinkedTo { path }
+                | ProjectDirectoryEntryState::MovingTo { path, .. }
+                | ProjectDirectoryEntryState::Archiving { path, .. }
+                    if path.starts_with(&library.path) =>
+                {
+                    Some(
+                        dir.stats()
+                            .and_then(Result::ok)
+                            .map_or(FileSize::ZERO, |stats| stats.size),
+                    )
+                }
+                _ => None,
+            };
+            usage += size.unwrap_or_else(|| self.moved_subdirectories_usage(&dir.name, library));
+        }
+        usage
+    }
+
+    /// Get how much of `library` the subdirectories of the entry `name` moved on their own take
+    /// up, as far as their stats are known.
+    fn moved_subdirectories_usage(&self, name: &str, library: &DestinationLibrary) -> FileSize {
+        let settings = self.settings.lock().unwrap();
+        let Some(entry) = settings.entry(name) else {
+            return FileSize::ZERO;
+        };
+        let filter = filter_or_default(settings.filter_for(name), name);
+        let stats_cache = self.stats_cache.lock().unwrap();
+        entry
+            .moved_subdirectories
+            .iter()
+            .filter(|(_, path)| path.starts_with(&library.path))
+            .filter_map(|(relative, path)| stats_cache.get(path, &filter.descend_path(relative)))
+            .fold(FileSize::ZERO, |total, tree| total + tree.total().size)
+    }
+
+    /// Get how much more can be moved to `to_path` before the quota of the library it's in is
+    /// used up, or `None` if there's no limit.
+    pub fn remaining_quota(&self, to_path: &Path) -> Option<FileSize> {
+        let library = self
+            .libraries()
+            .into_iter()
+            .find(|library| to_path.starts_with(&library.path))?;
+        let quota = library.quota?;
+        let usage = self.library_usage(&library);
+        Some(FileSize::from_bytes(
+            quota.as_bytes().saturating_sub(usage.as_bytes()),
+        ))
+    }
+
+    pub fn find_directory(&self, name: &str) -> Option<&ProjectDirectoryEntry> {
+        self.entries.iter().find_map(|entry| match entry {
+            ProjectEntry::Directory(dir) if dir.name == name => Some(dir),
+            _ => None,
+        })
+    }
+
+    /// Start auditing the moved directories against their manifests, only the ones called
+    /// `names` if given.
+    ///
+    /// Returns the number of directories queued for auditing.
+    pub fn start_audit(&self, names: Option<&[String]>) -> usize {
+        let audits: Vec<_> = self
+            .entries
+            .iter()
+            .filter_map(|entry| match entry {
+                ProjectEntry::Directory(dir)
+                    if names.is_none_or(|names| names.contains(&dir.name)) =>
+                {
+                    if dir.is_being_audited() {
+                        return None;
+                    }
+                    match dir.state.lock().unwrap().deref() {
+                        ProjectDirectoryEntryState::SymlinkedTo { path }
+                        | ProjectDirectoryEntryState::PartiallySymlinkedTo { path } => {
+                            *dir.audit.lock().unwrap() = Some(AuditState::Queued);
+                            Some((path.clone(), dir.audit.clone()))
+                        }
+                        _ => None,
+                    }
+                }
+                _ => None,
+            })
+            .collect();
+        let count = audits.len();
+        let cancellation_token = self.cancellation_token.clone();
+
+        IO_EXECUTOR
+            .spawn(async move {
+                // Auditing one directory at a time, as moved directories usually share the same
+                // destination drive
+                for (path, audit) in audits {
+                    let result = audit_directory(&path, &audit, cancellation_token.clone()).await;
+                    match result {
+                        Ok(ref report) if report.is_clean() => {
+                            info!(target: "audit", "{} matches its manifest", path.display());
+                        }
+                        Ok(ref report) => {
+                            warn!(
+                                target: "audit",
+                                "{} doesn't match its manifest: {} missing, {} extra, {} corrupted",
+                                path.display(),
+                                report.missing.len(),
+                                report.extra.len(),
+                                report.corrupted.len()
+                            );
+                            report
+                                .missing
+                                .iter()
+                                .for_each(|file| warn!(target: "audit", "Missing: {}", file));
+                            report
+                                .extra
+                                .iter()
+                                .for_each(|file| warn!(target: "audit", "Extra: {}", file));
+                            report
+                                .corrupted
+                                .iter()
+                                .for_each(|file| error!(target: "audit", "Corrupted: {}", file));
+                        }
+                        Err(err) => {
+                            error!(
+                                target: "audit",
+                                "Failed to audit {}: {:?}",
+                                path.display(),
+                                err
+                            );
+                        }
+                    }
+                    *audit.lock().unwrap() = Some(AuditState::Finished(result));
+                }
+            })
+            .detach();
+
+        count
+    }
+}
+
+/// Audit the moved directory at `path` against the manifest stored next to it.
+async fn audit_directory(
+    path: &Path,
+    audit: &Mutex<Option<AuditState>>,
+    cancellation_token: Arc<CancellationToken>,
+) -> Result<AuditReport, ManifestError> {
+    let manifest = MoveManifest::read(&MoveManifest::path_for(path)).await?;
+    let progress = Arc::new(Mutex::new(ProcessDirectoryProgress::from(&manifest.stats)));
+    *audit.lock().unwrap() = Some(AuditState::InProgress {
+        progress: progress.clone(),
+    });
+    manifest
+        .audit(path, Some(progress), Some(cancellation_token))
+        .await
+}
+
+/// Read the entry at `path` in the project directory.
+fn read_entry(path: &Path, name: String, settings: &ProjectSettings) -> io::Result<ProjectEntry> {
+    if path.is_dir() {
+        let dir_entry = ProjectEntry::Directory(ProjectDirectoryEntry {
+            state: Arc::new(Mutex::new(read_directory_state(path, &name, settings)?)),
+            name,
+            stats: Arc::new(Mutex::new(EntryStats::Unknown)),
+            audit: Arc::new(Mutex::new(None)),
+            compatibility_issues: Arc::new(Mutex::new(Vec::new())),
+            // Through the symlink, if it was moved
+            accessed: path
+                .metadata()
+                .and_then(|metadata| metadata.accessed())
+                .ok(),
+        });
+
+        Ok(dir_entry)
+    } else {
+        let metadata = path.symlink_metadata()?;
+        Ok(ProjectEntry::File(ProjectFileEntry {
+            name,
+            size: metadata.len().bytes(),
+            allocated_size: allocated_size(path, &metadata),
+            accessed: metadata.accessed().ok(),
+        }))
+    }
+}
+
+/// Get the state of an entry that was packed into the archive at `archive_path`.
+fn archived_state(archive_path: PathBuf) -> ProjectDirectoryEntryState {
+    let archived_size = archive_path
+        .metadata()
+        .map(|metadata| metadata.len().bytes())
+        .unwrap_or_else(|err| {
+            warn!(target: "project", "Failed to read archive {:?}: {}", archive_path, err);
+            FileSize::ZERO
+        });
+    ProjectDirectoryEntryState::Archived {
+        path: archive_path,
+        archived_size,
+    }
+}
+
+/// Create the entry `name` that was packed into the archive at `archive_path`.
+fn archived_entry(name: String, archive_path: PathBuf) -> ProjectEntry {
+    ProjectEntry::Directory(ProjectDirectoryEntry {
+        state: Arc::new(Mutex::new(archived_state(archive_path))),
+        name,
+        stats: Arc::new(Mutex::new(EntryStats::Unknown)),
+        audit: Arc::new(Mutex::new(None)),
+        compatibility_issues: Arc::new(Mutex::new(Vec::new())),
+        accessed: None,
+    })
+}
+
+/// Find out where the directory entry at `path` in the project directory is.
+fn read_directory_state(
+    path: &Path,
+    name: &str,
+    settings: &ProjectSettings,
+) -> io::Result<ProjectDirectoryEntryState> {
+    if path.symlink_metadata()?.is_symlink() {
+        return Ok(ProjectDirectoryEntryState::SymlinkedTo {
+            path: resolve_link_target(path, &path.read_link()?),
+        });
+    }
+    // Removing the original after archiving it failed or was interrupted
+    if let Some(archive_path) = settings
+        .entry(name)
+        .and_then(|entry| entry.archived_to.clone())
+    {
+        warn!(
+            target: "project",
+            "{} was archived to {:?}, remove the leftover files in {:?}",
+            name,
+            archive_path,
+            path
+        );
+        return Ok(archived_state(archive_path));
+    }
+    let partially_moved_to = settings
+        .entry(name)
+        .and_then(|entry| entry.partially_moved_to.clone());
+    Ok(match partially_moved_to {
+        Some(path) => ProjectDirectoryEntryState::PartiallySymlinkedTo { path },
+        None => ProjectDirectoryEntryState::InOriginalLocation,
+    })
+}
+
+/// Get the locations the entries' content was moved to, with the name of the entry each belongs
+/// to.
+fn moved_locations(
+    entries: &[ProjectEntry],
+    settings: &ProjectSettings,
+) -> BTreeMap<PathBuf, String> {
+    let mut locations = BTreeMap::new();
+    for entry in entries {
+        let ProjectEntry::Directory(dir) = entry else {
+            continue;
+        };
+        match dir.state.lock().unwrap().deref() {
+            ProjectDirectoryEntryState::SymlinkedTo { path }
+            | ProjectDirectoryEntryState::PartiallySymlinkedTo { path } => {
+                locations.insert(path.clone(), dir.name.clone());
+            }
+            _ => {}
+        }
+        if let Some(entry) = settings.entry(&dir.name) {
+            for path in entry.moved_subdirectories.values() {
+                locations.insert(path.clone(), dir.name.clone());
+            }
+        }
+    }
+    locations
+}
+
+/// Get the games other launchers installed directly in `directory`, by their directory name.
+fn launcher_games_in(directory: &Path) -> BTreeMap<String, LauncherGame> {
+    // The launchers may have recorded the paths through symlinks
+    let directory = directory
+        .canonicalize()
+        .unwrap_or_else(|_| directory.to_path_buf());
+    launchers::installed_games()
+        .into_iter()
+        .filter_map(|game| {
+            let parent = game.install_path.parent()?;
+            let parent = parent
+                .canonicalize()
+                .unwrap_or_else(|_| parent.to_path_buf());
+            if parent != directory {
+                return None;
+            }
+            let name = game
+                .install_path
+                .file_name()?
+                .to_string_lossy()
+                .into_owned();
+            Some((name, game))
+        })
+        .collect()
+}
+
+fn filter_or_default(filter: Result<MoveFilter, String>, name: &str) -> MoveFilter {
+    filter.unwrap_or_else(|err| {
+        warn!(target: "project", "Ignoring exclude patterns of {}: {}", name, err);
+        MoveFilter::default()
+    })
+}
+
+/// Get the directories whose stats add up to the stats of the entry `name`, together with the
+/// filters to measure them with.
+fn stats_sources(
+    project_directory: &Path,
+    settings: &ProjectSettings,
+    name: &str,
+    state: &ProjectDirectoryEntryState,
+) -> Vec<(PathBuf, MoveFilter)> {
+    let filter = filter_or_default(settings.filter_for(name), name);
+    match state {
+        // Partially moved directories only have the excluded content left in place, so the
+        // moved content is what gets measured, like with fully moved ones
+        ProjectDirectoryEntryState::PartiallySymlinkedTo { path } => vec![(path.clone(), filter)],
+        _ => {
+            // Moved subdirectories are measured where they were moved to instead of following
+            // the symlinks left in their place
+            let local_filter = filter_or_default(settings.local_filter_for(name), name);
+            let mut sources = vec![(project_directory.join(name), local_filter)];
+            if let Some(entry) = settings.entry(name) {
+                sources.extend(
+                    entry
+                        .moved_subdirectories
+                        .iter()
+                        .map(|(relative, path)| (path.clone(), filter.descend_path(relative))),
+                );
+            }
+            sources
+        }
+    }
+}
+
+async fn calc_entry_stats(
+    name: String,
+    sources: Vec<(PathBuf, MoveFilter)>,
+    stats_mutex: Arc<Mutex<EntryStats>>,
+    stats_cache: Arc<Mutex<StatsCache>>,
+    cancellation_token: Arc<CancellationToken>,
+) {
+    let cached: Vec<Option<CachedDirectory>> = {
+        let stats_cache = stats_cache.lock().unwrap();
+        sources
+            .iter()
+            .map(|(path, filter)| stats_cache.get(path, filter).cloned())
+            .collect()
+    };
+    let running_total = Arc::new(Mutex::new(DirectoryStats::default()));
+    {
+        // Cached stats are closer to the real ones than the running totals
+        let mut stats = stats_mutex.lock().unwrap();
+        if !matches!(*stats, EntryStats::Known(_)) {
+            *stats = EntryStats::Partial(running_total.clone());
+        }
+    }
+
+    let mut result = Ok(DirectoryStats::default());
+    for ((path, filter), cached) in sources.into_iter().zip(cached) {
+        let tree = calc_directory_stats_cached(
+            &path,
+            &filter,
+            cached.as_ref(),
+            Some(&running_total),
+            Some(&cancellation_token),
+        )
+        .await;
+        result = result.and_then(|mut total| {
+            let tree = tree?;
+            total.add_nested(filter.prefix(), &tree.total());
+            stats_cache.lock().unwrap().insert(path, &filter, tree);
+            Ok(total)
+        });
+        if result.is_err() {
+            break;
+        }
+    }
+
+    if let Ok(ref result) = result {
+        debug!(
+            target: "io-thread",
+            "Calculated that {} is {}",
+            name,
+            result.size.to_string()
+        );
+    } else {
+        warn!(
+            target: "io-thread",
+            "Failed to calculate stats for {}: {:?}",
+            name,
+            result
+        );
+    }
+    *stats_mutex.lock().unwrap() = EntryStats::Known(result);
+}
+
+/// Show the cached stats of the entry measured from `sources`, if all of them are cached, while
+/// checking whether they're still up to date.
+fn show_cached_stats(
+    sources: &[(PathBuf, MoveFilter)],
+    stats_cache: &StatsCache,
+    stats_mutex: &Mutex<EntryStats>,
+) {
+    let mut total = DirectoryStats::default();
+    for (path, filter) in sources {
+        let Some(tree) = stats_cache.get(path, filter) else {
+            return;
+        };
+        total.add_nested(filter.prefix(), &tree.total());
+    }
+    *stats_mutex.lock().unwrap() = EntryStats::Known(Ok(total));
+}
+
+/// Forget the stats of the entry `name` and calculate them again, e.g. after some of its content
+/// was moved.
+async fn recalc_entry_stats(
+    project_directory: &Path,
+    settings: &Mutex<ProjectSettings>,
+    name: String,
+    state: &Mutex<ProjectDirectoryEntryState>,
+    stats_mutex: Arc<Mutex<EntryStats>>,
+    stats_cache: Arc<Mutex<StatsCache>>,
+    cancellation_token: Arc<CancellationToken>,
+) {
+    *stats_mutex.lock().unwrap() = EntryStats::Unknown;
+    let sources = stats_sources(
+        project_directory,
+        &settings.lock().unwrap(),
+        &name,
+        &state.lock().unwrap(),
+    );
+    show_cached_stats(&sources, &stats_cache.lock().unwrap(), &stats_mutex);
+    calc_entry_stats(
+        name,
+        sources,
+        stats_mutex,
+        stats_cache.clone(),
+        cancellation_token,
+    )
+    .await;
+    save_stats_cache(&stats_cache);
+}
+
+fn save_stats_cache(stats_cache: &Mutex<StatsCache>) {
+    if let Err(err) = stats_cache.lock().unwrap().save() {
+        warn!(target: "project", "Failed to save stats cache: {}", err);
+    }
+}
+
+/// Check whether the `/`-separated `path` is `ancestor` or inside it.
+fn is_same_or_inside(path: &str, ancestor: &str) -> bool {
+    path.strip_prefix(ancestor)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Join the `/`-separated `relative` path onto `root`.
+fn join_relative(root: &Path, relative: &str) -> PathBuf {
+    relative
+        .split('/')
+        .fold(root.to_path_buf(), |path, name| path.join(name))
+}
+
+/// Check the names in `from_path` against the file system of `to_path`, logging and storing the
+/// problems found. Returns whether the move can go ahead.
+async fn check_names(
+    from_path: &Path,
+    to_path: &Path,
+    compatibility_issues: &Mutex<Vec<CompatibilityIssue>>,
+) -> bool {
+    let issues = match check_move_compatibility(from_path, to_path, None).await {
+        Ok(issues) => issues,
+        Err(CompatibilityError::Io(kind)) => {
+            error!("Failed to check names in {:?}: {}", from_path, kind);
+            return false;
+        }
+        Err(CompatibilityError::Cancelled) => return false,
+    };
+    if !issues.is_empty() {
+        error!(
+            target: "compatibility",
+            "Can't move {:?}: {} names won't survive the destination file system",
+            from_path,
+            issues.len()
+        );
+        for issue in &issues {
+            warn!(
+                target: "compatibility",
+                "{}: {}",
+                issue.path.display(),
+                issue.problem
+            );
+        }
+    }
+    let is_compatible = issues.is_empty();
+    *compatibility_issues.lock().unwrap() = issues;
+    is_compatible
+}
+
+/// Check that a directory with the given `stats` fits into the `remaining_quota` of the library
+/// `to_path` is in, if it's limited.
+fn check_quota(to_path: &Path, stats: &DirectoryStats, remaining_quota: Option<FileSize>) -> bool {
+    match remaining_quota {
+        Some(remaining) if stats.size > remaining => {
+            error!(
+                "Can't move to {:?}: {} are needed, but only {} of the library's quota are left",
+                to_path, stats.size, remaining
+            );
+            false
+        }
+        _ => true,
+    }
+}
+
+/// Check that the volume `to_path` is on has room for a directory with the given `stats`.
+fn check_space(to_path: &Path, stats: &DirectoryStats) -> bool {
+    let space = match to_path.get_volume_space() {
+        Ok(space) => space,
+        Err(err) => {
+            // Not knowing the free space is no reason not to try
+            warn!("Failed to get the free space at {:?}: {:?}", to_path, err);
+            return true;
+        }
+    };
+    let needed = stats.estimated_allocated_size(space.block_size);
+    if needed > space.available {
+        error!(
+            "Can't move to {:?}: about {} are needed, but only {} are free",
+            to_path, needed, space.available
+        );
+        return false;
+    }
+    true
+}
+
+#[derive(Debug)]
+pub enum ProjectDirectoryEntryState {
+    /// The directory is in its original location.
+    InOriginalLocation,
+    /// The directory is symlinked to another location.
+    SymlinkedTo { path: PathBuf },
+    /// The directory stays in place, but everything in it except for the excluded content is
+    /// symlinked to another location.
+    PartiallySymlinkedTo { path: PathBuf },
+    /// The directory is being moved to another location.
+    MovingTo {
+        path: PathBuf,
+        progress: Arc<Mutex<MoveAndSymlinkProgress>>,
+    },
+    /// The directory is being moved back from another location.
+    MovingFrom {
+        path: PathBuf,
+        progress: Arc<Mutex<MoveBackProgress>>,
+    },
+    /// The directory is being packed into an archive.
+    Archiving {
+        path: PathBuf,
+        progress: Arc<Mutex<ArchiveProgress>>,
+    },
+    /// The directory only exists as an archive, with the size of the archive.
+    Archived {
+        path: PathBuf,
+        archived_size: FileSize,
+    },
+    /// The directory is being restored from an archive.
+    Restoring {
+        path: PathBuf,
+        progress: Arc<Mutex<RestoreProgress>>,
+    },
+}
+
+/// The state of auditing a moved directory against its manifest.
+#[derive(Debug)]
+pub enum AuditState {
+    /// The directory is waiting for other audits to finish.
+    Queued,
+    /// The directory is being audited.
+    InProgress {
+        progress: Arc<Mutex<ProcessDirectoryProgress>>,
+    },
+    /// The audit has finished.
+    Finished(Result<AuditReport, ManifestError>),
+}
+
+/// How the launcher of a game moved without leaving a symlink is told where it is now.
+#[derive(Debug, Clone)]
+enum Relocation {
+    /// A Steam game, moved between the `steamapps` directories of two libraries.
+    Steam {
+        app: AppManifest,
+        from_steamapps: PathBuf,
+        to_steamapps: PathBuf,
+    },
+    /// A game of another launcher, which gets its new install path.
+    Launcher(LauncherGame),
+}
+
+impl Relocation {
+    fn title(&self) -> &str {
+        match self {
+            Relocation::Steam { app, .. } => &app.name,
+            Relocation::Launcher(game) => &game.title,
+        }
+    }
+
+    fn log_target(&self) -> &'static str {
+        match self {
+            Relocation::Steam { .. } => "steam",
+            Relocation::Launcher(_) => "launchers",
+        }
+    }
+
+    /// Tell the launcher the game was moved to `to_path`. Blocks on updating its files.
+    fn record(&self, to_path: &Path) -> Result<(), String> {
+        match self {
+            Relocation::Steam {
+                app,
+                from_steamapps,
+                to_steamapps,
+            } => {
+                // The move may have waited in the job queue long enough for Steam to be started,
+                // and Steam would overwrite the changes when it exits
+                if steam::is_steam_running() {
+                    return Err(
+                        "Steam was started during the move, close it and move the game again"
+                            .to_string(),
+                    );
+                }
+                steam::relocate_app(app, from_steamapps, to_steamapps)
+            }
+            Relocation::Launcher(game) => launchers::update_install_path(game, to_path),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ProjectEntry {
+    Directory(ProjectDirectoryEntry),
+    File(ProjectFileEntry),
+}
+
+impl ProjectEntry {
+    pub fn name(&self) -> &str {
+        match self {
+            ProjectEntry::Directory(dir) => &dir.name,
+            ProjectEntry::File(file) => &file.name,
+        }
+    }
+
+    /// Get the apparent size of the entry, if it's known.
+    pub fn size(&self) -> Option<FileSize> {
+        match self {
+            ProjectEntry::Directory(dir) => dir.size(),
+            ProjectEntry::File(file) => Some(file.size),
+        }
+    }
+
+    /// Get when the entry was last accessed, if the file system keeps track of it.
+    pub fn accessed(&self) -> Option<SystemTime> {
+        match self {
+            ProjectEntry::Directory(dir) => dir.accessed,
+            ProjectEntry::File(file) => file.accessed,
+        }
+    }
+
+    /// Get where the entry is for sorting by state: in place first, then moved, archived, busy,
+    /// and files last.
+    fn state_rank(&self) -> u8 {
+        let ProjectEntry::Directory(dir) = self else {
+            return 5;
+        };
+        match dir.state.lock().unwrap().deref() {
+            ProjectDirectoryEntryState::InOriginalLocation => 0,
+            ProjectDirectoryEntryState::PartiallySymlinkedTo { .. } => 1,
+            ProjectDirectoryEntryState::SymlinkedTo { .. } => 2,
+            ProjectDirectoryEntryState::Archived { .. } => 3,
+            ProjectDirectoryEntryState::MovingTo { .. }
+            | ProjectDirectoryEntryState::MovingFrom { .. }
+            | ProjectDirectoryEntryState::Archiving { .. }
+            | ProjectDirectoryEntryState::Restoring { .. } => 4,
+        }
+    }
+}
+
+/// What's known about the stats of a directory entry.
+#[derive(Debug, Clone, Default)]
+pub enum EntryStats {
+    #[default]
+    Unknown,
+    /// The directory is being measured for the first time. Holds the running totals.
+    Partial(Arc<Mutex<DirectoryStats>>),
+    /// The directory was measured, or its cached stats are shown while it's measured again.
+    Known(Result<DirectoryStats, DirectoryStatsError>),
+}
+
+#[derive(Debug)]
+pub struct ProjectDirectoryEntry {
+    pub name: String,
+    pub state: Arc<Mutex<ProjectDirectoryEntryState>>,
+    stats: Arc<Mutex<EntryStats>>,
+    audit: Arc<Mutex<Option<AuditState>>>,
+    /// Problems found with the names in the directory during the last attempt to move it.
+    compatibility_issues: Arc<Mutex<Vec<CompatibilityIssue>>>,
+    /// When the directory, or where it was moved to, was last accessed.
+    accessed: Option<SystemTime>,
+}
+
+impl ProjectDirectoryEntry {
+    /// Get the stats of the directory, once they're known.
+    pub fn stats(&self) -> Option<Result<DirectoryStats, DirectoryStatsError>> {
+        match self.stats.lock().ok()?.deref() {
+            EntryStats::Known(stats) => Some(stats.clone()),
+            _ => None,
+        }
+    }
+
+    /// Get the apparent size of the directory, once it's known, without copying all its stats.
+    pub fn size(&self) -> Option<FileSize> {
+        match self.stats.lock().ok()?.deref() {
+            EntryStats::Known(Ok(stats)) => Some(stats.size),
+            _ => None,
+        }
+    }
+
+    /// Get the stats of the directory counted so far, while it's being measured.
+    pub fn partial_stats(&self) -> Option<DirectoryStats> {
+        match self.stats.lock().ok()?.deref() {
+            EntryStats::Partial(running_total) => Some(running_total.lock().unwrap().clone()),
+            _ => None,
+        }
+    }
+
+    pub fn set_stats(&self, stats: Result<DirectoryStats, DirectoryStatsError>) {
+        let mut mutex_guard = self.stats.lock().unwrap();
+        assert!(!matches!(*mutex_guard, EntryStats::Known(_)));
+        *mutex_guard = EntryStats::Known(stats);
+    }
+
+    pub fn can_be_moved(&self) -> bool {
         match self.state.lock().unwrap().deref() {
-            ProjectDirectoryEntryState::SymlinkedTo { .. } => self.stats().map_or(false, |stats| {
+            ProjectDirectoryEntryState::InOriginalLocation => self.stats().map_or(false, |stats| {
                 stats
                     .as_ref()
                     .map_or(false, |stats| stats.symlink_count == 0)
@@ -373,20 +1873,130 @@ impl ProjectDirectoryEntry {
         }
     }
 
+    pub fn compatibility_issues(&self) -> Vec<CompatibilityIssue> {
+        self.compatibility_issues.lock().unwrap().clone()
+    }
+
+    pub fn is_being_audited(&self) -> bool {
+        matches!(
+            self.audit.lock().unwrap().deref(),
+            Some(AuditState::Queued | AuditState::InProgress { .. })
+        )
+    }
+
+    /// Whether Moverr itself is working with the directory, so changes to it are expected.
+    pub fn is_busy(&self) -> bool {
+        self.is_being_audited()
+            || matches!(
+                self.state.lock().unwrap().deref(),
+                ProjectDirectoryEntryState::MovingTo { .. }
+                    | ProjectDirectoryEntryState::MovingFrom { .. }
+                    | ProjectDirectoryEntryState::Archiving { .. }
+                    | ProjectDirectoryEntryState::Restoring { .. }
+            )
+    }
+
+    /// Whether the directory's content was moved somewhere else, fully or partially.
+    pub fn is_moved(&self) -> bool {
+        matches!(
+            self.state.lock().unwrap().deref(),
+            ProjectDirectoryEntryState::SymlinkedTo { .. }
+                | ProjectDirectoryEntryState::PartiallySymlinkedTo { .. }
+        )
+    }
+
+    pub fn is_archived(&self) -> bool {
+        matches!(
+            self.state.lock().unwrap().deref(),
+            ProjectDirectoryEntryState::Archived { .. }
+        )
+    }
+
+    pub fn can_be_moved_back(&self) -> bool {
+        if self.is_being_audited() {
+            return false;
+        }
+
+        match self.state.lock().unwrap().deref() {
+            ProjectDirectoryEntryState::SymlinkedTo { .. }
+            | ProjectDirectoryEntryState::PartiallySymlinkedTo { .. } => self
+                .stats()
+                .is_some_and(|stats| stats.as_ref().is_ok_and(|stats| stats.symlink_count == 0)),
+            _ => false,
+        }
+    }
+
+    /// Queue `future` working on the directory on `devices`, see
+    /// [`JobQueue`](crate::job_queue::JobQueue). The directory is put back in its `previous` state
+    /// if the job is cancelled before it starts.
+    fn enqueue_job(
+        &self,
+        project_state: &ProjectState,
+        label: String,
+        devices: Vec<Option<DeviceId>>,
+        previous: ProjectDirectoryEntryState,
+        future: impl Future<Output = ()> + Send + 'static,
+    ) {
+        let state = self.state.clone();
+        let name = self.name.clone();
+        let cancelled_label = label.clone();
+        project_state.job_queue.lock().unwrap().enqueue(
+            self.name.clone(),
+            label,
+            devices,
+            future,
+            move || {
+                info!(target: "project", "Cancelled {}: {}", name, cancelled_label);
+                *state.lock().unwrap() = previous;
+            },
+        );
+    }
+
+    /// Start moving the directory to `to_path` with the given `options`, leaving a symlink in its
+    /// place. The filter of the options is replaced with the entry's own.
     pub fn try_start_move_to(
         &self,
         project_state: &ProjectState,
         to_path: PathBuf,
+        options: MoveOptions,
     ) -> Result<(), ()> {
         if !self.can_be_moved() {
             return Err(());
         }
+        // The subdirectories would have to be moved back first, or they'd be symlinks inside the
+        // moved directory
+        if !project_state.moved_subdirectories(&self.name).is_empty() {
+            warn!(
+                target: "project",
+                "{} has moved subdirectories, move them back first!",
+                self.name
+            );
+            return Err(());
+        }
 
         let from_path = project_state.directory.join(&self.name);
 
-        let progress = Arc::new(Mutex::new(MoveAndSymlinkProgress::from(
-            &self.stats().unwrap().unwrap(),
-        )));
+        // Launchers keep track of where their games are, so they're told about the move instead
+        // of leaving a symlink behind
+        let relocation = match options.strategy {
+            MoveStrategy::Symlink => None,
+            MoveStrategy::Relocate => match self.relocation(project_state, &to_path) {
+                Ok(relocation) => Some(relocation),
+                Err(err) => {
+                    error!(target: "project", "{}", err);
+                    return Err(());
+                }
+            },
+        };
+
+        let stats = self.stats().unwrap().unwrap();
+        if !check_space(&to_path, &stats)
+            || !check_quota(&to_path, &stats, project_state.remaining_quota(&to_path))
+        {
+            return Err(());
+        }
+        let progress = Arc::new(Mutex::new(MoveAndSymlinkProgress::from(&stats)));
+        progress.lock().unwrap().stage = MoveAndSymlinkStage::CheckingNames;
 
         *self.state.lock().unwrap() = ProjectDirectoryEntryState::MovingTo {
             path: to_path.clone(),
@@ -394,76 +2004,683 @@ impl ProjectDirectoryEntry {
         };
 
         let state = self.state.clone();
+        let compatibility_issues = self.compatibility_issues.clone();
+        let options = MoveOptions {
+            filter: match options.strategy {
+                MoveStrategy::Symlink => project_state.filter_for(&self.name),
+                MoveStrategy::Relocate => MoveFilter::default(),
+            },
+            ..options
+        };
+        let name = self.name.clone();
+        let project_directory = project_state.directory.clone();
+        let settings = project_state.settings.clone();
+
+        let label = format!("Move to {}", to_path.display());
+        let devices = devices_of(&[&from_path, &to_path]);
+        self.enqueue_job(
+            project_state,
+            label,
+            devices,
+            ProjectDirectoryEntryState::InOriginalLocation,
+            async move {
+                if !check_names(&from_path, &to_path, &compatibility_issues).await {
+                    *state.lock().unwrap() = ProjectDirectoryEntryState::InOriginalLocation;
+                    return;
+                }
 
-        IO_EXECUTOR
-            .spawn(async move {
                 let result = from_path
-                    .move_and_symlink(&to_path, Some(progress), None)
+                    .move_and_symlink(&to_path, &options, Some(progress.clone()), None)
                     .await;
 
+                // Linking out the content may have failed partway, leaving some of it linked
+                let partially_linked = result.is_err()
+                    && matches!(
+                        progress.lock().unwrap().stage,
+                        MoveAndSymlinkStage::Symlinking
+                    )
+                    && !from_path.is_symlink()
+                    && collect_links_to(&from_path, &to_path)
+                        .await
+                        .is_ok_and(|links| !links.is_empty());
+
+                // The launcher is told about the copy before the original is removed, so it never
+                // points to a game that's gone
+                if let (Ok(_), Some(relocation)) = (&result, &relocation) {
+                    let record_res = {
+                        let (relocation, to_path) = (relocation.clone(), to_path.clone());
+                        smol::unblock(move || relocation.record(&to_path)).await
+                    };
+                    match record_res {
+                        Ok(_) => {
+                            progress.lock().unwrap().stage = MoveAndSymlinkStage::RemovingOriginal;
+                            match async_fs::remove_dir_all(&from_path).await {
+                                Ok(_) => info!(
+                                    target: relocation.log_target(),
+                                    "Moved {} to {}",
+                                    relocation.title(),
+                                    to_path.display()
+                                ),
+                                Err(err) => error!(
+                                    target: relocation.log_target(),
+                                    "{} was moved to {}, but its original couldn't be removed, remove the leftover files in {}: {}",
+                                    relocation.title(),
+                                    to_path.display(),
+                                    from_path.display(),
+                                    err
+                                ),
+                            }
+                            progress.lock().unwrap().stage = MoveAndSymlinkStage::Finished;
+                        }
+                        Err(err) => {
+                            error!(
+                                target: relocation.log_target(),
+                                "{} wasn't moved, as its launcher couldn't be told about it: {}",
+                                relocation.title(),
+                                err
+                            );
+                            if let Err(err) = async_fs::remove_dir_all(&to_path).await {
+                                warn!("Failed to remove the copy {:?}: {}", to_path, err);
+                            }
+                            let manifest_path = MoveManifest::path_for(&to_path);
+                            if let Err(err) = async_fs::remove_file(&manifest_path).await {
+                                warn!("Failed to remove manifest {:?}: {}", manifest_path, err);
+                            }
+                        }
+                    }
+                }
+
                 let mut state = state.lock().unwrap();
 
                 match result {
-                    Ok(_) => {
+                    // The entry is gone from the project now, which the watcher picks up, unless
+                    // its launcher couldn't be told and it stayed
+                    Ok(_) if relocation.is_some() => {
+                        *state = ProjectDirectoryEntryState::InOriginalLocation;
+                    }
+                    Ok(_) if from_path.is_symlink() => {
                         *state = ProjectDirectoryEntryState::SymlinkedTo { path: to_path };
                     }
-                    Err(err) => {
+                    Err(err) if !partially_linked => {
                         error!("Failed to move directory: {:?}", err);
                         *state = ProjectDirectoryEntryState::InOriginalLocation;
                     }
+                    result => {
+                        if let Err(err) = result {
+                            error!(
+                                "Failed to move directory, it's left partially moved to {:?}: {:?}",
+                                to_path, err
+                            );
+                        }
+                        // Only the content that wasn't excluded got linked out, so the directory
+                        // itself doesn't tell where it was moved to
+                        let mut settings = settings.lock().unwrap();
+                        settings.update_entry(&name, |entry| {
+                            entry.partially_moved_to = Some(to_path.clone());
+                        });
+                        if let Err(err) = settings.save(&project_directory) {
+                            error!("Failed to save project settings: {}", err);
+                        }
+                        *state = ProjectDirectoryEntryState::PartiallySymlinkedTo { path: to_path };
+                    }
                 }
-            })
-            .detach();
+            },
+        );
 
         Ok(())
     }
 
+    /// Check that the entry is a game whose launcher can be told it was moved to `to_path`, and
+    /// get how to tell it.
+    fn relocation(
+        &self,
+        project_state: &ProjectState,
+        to_path: &Path,
+    ) -> Result<Relocation, String> {
+        if let Some(game) = project_state.launcher_games.get(&self.name) {
+            return match launchers::provider(game.launcher) {
+                Some(provider) if provider.can_update_install_path() && provider.is_running() => {
+                    Err(format!("Close {} before moving its games", game.launcher))
+                }
+                Some(provider) if provider.can_update_install_path() => {
+                    Ok(Relocation::Launcher(game.clone()))
+                }
+                _ => Err(format!(
+                    "{} can't be told where {} was moved to",
+                    game.launcher, game.title
+                )),
+            };
+        }
+        let app = project_state
+            .steam_apps
+            .get(&self.name)
+            .ok_or_else(|| format!("{} isn't a game of a known launcher", self.name))?;
+        if app.install_state() != InstallState::Installed {
+            return Err(format!(
+                "{} can only be moved while it's fully installed, it's {}",
+                app.name,
+                app.install_state().label().to_lowercase()
+            ));
+        }
+        let from_steamapps = steam::steamapps_of(&project_state.directory)
+            .ok_or_else(|| format!("{} isn't in a Steam library", self.name))?;
+        let to_steamapps = to_path
+            .parent()
+            .and_then(steam::steamapps_of)
+            .ok_or_else(|| format!("{} isn't in a Steam library", to_path.display()))?;
+        if steam::is_steam_running() {
+            return Err("Close Steam before moving games between its libraries".to_string());
+        }
+        Ok(Relocation::Steam {
+            app: app.clone(),
+            from_steamapps: from_steamapps.to_path_buf(),
+            to_steamapps: to_steamapps.to_path_buf(),
+        })
+    }
+
     pub fn try_start_move_back(&self, project_state: &ProjectState) -> Result<(), ()> {
         if !self.can_be_moved_back() {
             return Err(());
         }
 
         let from_path = project_state.directory.join(&self.name);
-        let to_path = match self.state.lock().unwrap().deref() {
-            ProjectDirectoryEntryState::SymlinkedTo { path } => path.clone(),
+        let (to_path, is_partial) = match self.state.lock().unwrap().deref() {
+            ProjectDirectoryEntryState::SymlinkedTo { path } => (path.clone(), false),
+            ProjectDirectoryEntryState::PartiallySymlinkedTo { path } => (path.clone(), true),
             _ => unreachable!(),
         };
 
-        let progress = Arc::new(Mutex::new(MoveBackProgress::from(
-            &self.stats().unwrap().unwrap(),
-        )));
+        let stats = self.stats().unwrap().unwrap();
+        if !check_space(&project_state.directory, &stats) {
+            return Err(());
+        }
+        let progress = Arc::new(Mutex::new(MoveBackProgress::from(&stats)));
 
         *self.state.lock().unwrap() = ProjectDirectoryEntryState::MovingFrom {
             path: to_path.clone(),
             progress: progress.clone(),
         };
+        *self.audit.lock().unwrap() = None;
 
         let state = self.state.clone();
+        let name = self.name.clone();
+        let project_directory = project_state.directory.clone();
+        let settings = project_state.settings.clone();
 
-        IO_EXECUTOR
-            .spawn(async move {
+        let label = format!("Move back from {}", to_path.display());
+        let devices = devices_of(&[&project_directory, &to_path]);
+        self.enqueue_job(
+            project_state,
+            label,
+            devices,
+            if is_partial {
+                ProjectDirectoryEntryState::PartiallySymlinkedTo {
+                    path: to_path.clone(),
+                }
+            } else {
+                ProjectDirectoryEntryState::SymlinkedTo {
+                    path: to_path.clone(),
+                }
+            },
+            async move {
                 let result = from_path.move_back(&to_path, Some(progress), None).await;
 
                 let mut state = state.lock().unwrap();
 
                 match result {
                     Ok(_) => {
+                        if is_partial {
+                            let mut settings = settings.lock().unwrap();
+                            settings.update_entry(&name, |entry| entry.partially_moved_to = None);
+                            if let Err(err) = settings.save(&project_directory) {
+                                error!("Failed to save project settings: {}", err);
+                            }
+                        }
                         *state = ProjectDirectoryEntryState::InOriginalLocation;
                     }
                     Err(err) => {
                         error!("Failed to move directory back: {:?}", err);
-                        *state = ProjectDirectoryEntryState::SymlinkedTo { path: to_path };
+                        *state = if is_partial {
+                            ProjectDirectoryEntryState::PartiallySymlinkedTo { path: to_path }
+                        } else {
+                            ProjectDirectoryEntryState::SymlinkedTo { path: to_path }
+                        };
                     }
                 }
-            })
-            .detach();
+            },
+        );
 
         Ok(())
     }
+
+    /// Start packing the directory into a new archive at `archive_path` and removing it from the
+    /// project directory once the archive is verified.
+    pub fn try_start_archive(
+        &self,
+        project_state: &ProjectState,
+        archive_path: PathBuf,
+    ) -> Result<(), ()> {
+        if !self.can_be_moved() {
+            return Err(());
+        }
+        // The moved subdirectories would only be archived as symlinks
+        if !project_state.moved_subdirectories(&self.name).is_empty() {
+            warn!(
+                target: "project",
+                "{} has moved subdirectories, move them back first!",
+                self.name
+            );
+            return Err(());
+        }
+
+        let from_path = project_state.directory.join(&self.name);
+
+        let stats = self.stats().unwrap().unwrap();
+        if !check_space(&archive_path, &stats)
+            || !check_quota(
+                &archive_path,
+                &stats,
+                project_state.remaining_quota(&archive_path),
+            )
+        {
+            return Err(());
+        }
+        let progress = Arc::new(Mutex::new(ArchiveProgress::from(&stats)));
+
+        *self.state.lock().unwrap() = ProjectDirectoryEntryState::Archiving {
+            path: archive_path.clone(),
+            progress: progress.clone(),
+        };
+
+        let state = self.state.clone();
+        let name = self.name.clone();
+        let project_directory = project_state.directory.clone();
+        let settings = project_state.settings.clone();
+
+        let label = format!("Archive to {}", archive_path.display());
+        let devices = devices_of(&[&from_path, &archive_path]);
+        self.enqueue_job(
+            project_state,
+            label,
+            devices,
+            ProjectDirectoryEntryState::InOriginalLocation,
+            async move {
+                let result = from_path
+                    .archive_to(&archive_path, Some(progress.clone()), None)
+                    .await;
+                if let Err(err) = result {
+                    match err {
+                        ArchiveError::Io(kind) => error!("Failed to archive {}: {}", name, kind),
+                        err => error!("Failed to archive {}: {:?}", name, err),
+                    }
+                    *state.lock().unwrap() = ProjectDirectoryEntryState::InOriginalLocation;
+                    return;
+                }
+
+                // The archive is recorded before the original is removed, so the entry isn't
+                // lost if removing it fails or Moverr quits halfway
+                let save_res = {
+                    let mut settings = settings.lock().unwrap();
+                    settings.update_entry(&name, |entry| {
+                        entry.archived_to = Some(archive_path.clone());
+                    });
+                    settings.save(&project_directory)
+                };
+                if let Err(err) = save_res {
+                    error!(
+                        "Failed to save project settings, keeping {} next to the archive {:?}: {}",
+                        name, archive_path, err
+                    );
+                    *state.lock().unwrap() = ProjectDirectoryEntryState::InOriginalLocation;
+                    return;
+                }
+
+                progress.lock().unwrap().stage = ArchiveStage::RemovingOriginal;
+                if let Err(err) = async_fs::remove_dir_all(&from_path).await {
+                    error!(
+                        "{} was archived to {:?}, but its original couldn't be removed, remove the leftover files in {:?}: {}",
+                        name, archive_path, from_path, err
+                    );
+                }
+                progress.lock().unwrap().stage = ArchiveStage::Finished;
+
+                let archived_state = archived_state(archive_path);
+                if let ProjectDirectoryEntryState::Archived { path, archived_size } = &archived_state {
+                    info!(target: "project", "Archived {} to {:?}, {}", name, path, archived_size);
+                }
+                *state.lock().unwrap() = archived_state;
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Start restoring the archived directory to the project directory, removing the archive once
+    /// the restored content is verified against its index.
+    pub fn try_start_restore(&self, project_state: &ProjectState) -> Result<(), ()> {
+        let (archive_path, archived_size) = match self.state.lock().unwrap().deref() {
+            ProjectDirectoryEntryState::Archived {
+                path,
+                archived_size,
+            } => (path.clone(), *archived_size),
+            _ => return Err(()),
+        };
+        let Some(Ok(stats)) = self.stats() else {
+            return Err(());
+        };
+
+        let to_path = project_state.directory.join(&self.name);
+
+        if !check_space(&project_state.directory, &stats) {
+            return Err(());
+        }
+        let progress = Arc::new(Mutex::new(RestoreProgress::from(&stats)));
+
+        *self.state.lock().unwrap() = ProjectDirectoryEntryState::Restoring {
+            path: archive_path.clone(),
+            progress: progress.clone(),
+        };
+
+        let state = self.state.clone();
+        let name = self.name.clone();
+        let project_directory = project_state.directory.clone();
+        let settings = project_state.settings.clone();
+
+        let label = format!("Restore from {}", archive_path.display());
+        let devices = devices_of(&[&project_directory, &archive_path]);
+        self.enqueue_job(
+            project_state,
+            label,
+            devices,
+            ProjectDirectoryEntryState::Archived {
+                path: archive_path.clone(),
+                archived_size,
+            },
+            async move {
+                let result = to_path
+                    .restore_from_archive(&archive_path, Some(progress), None)
+                    .await;
+
+                let mut state = state.lock().unwrap();
+
+                match result {
+                    Ok(_) => {
+                        let mut settings = settings.lock().unwrap();
+                        settings.update_entry(&name, |entry| entry.archived_to = None);
+                        if let Err(err) = settings.save(&project_directory) {
+                            error!("Failed to save project settings: {}", err);
+                        }
+                        info!(target: "project", "Restored {} from {:?}", name, archive_path);
+                        *state = ProjectDirectoryEntryState::InOriginalLocation;
+                    }
+                    Err(err) => {
+                        match err {
+                            ArchiveError::Io(kind) => {
+                                error!("Failed to restore {}: {}", name, kind)
+                            }
+                            err => error!("Failed to restore {}: {:?}", name, err),
+                        }
+                        *state = ProjectDirectoryEntryState::Archived {
+                            path: archive_path,
+                            archived_size,
+                        };
+                    }
+                }
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Start moving the subdirectory at the `/`-separated `relative` path inside the entry to
+    /// `to_path`, leaving a symlink in its place.
+    ///
+    /// The entry itself stays in its original location and keeps track of the moved
+    /// subdirectory in the project settings, counting its size in its own.
+    pub fn try_start_subdirectory_move_to(
+        &self,
+        project_state: &ProjectState,
+        relative: &str,
+        to_path: PathBuf,
+    ) -> Result<(), ()> {
+        if !self.can_change_subdirectories() {
+            return Err(());
+        }
+        let overlapping = project_state
+            .moved_subdirectories(&self.name)
+            .into_keys()
+            .find(|moved| is_same_or_inside(moved, relative) || is_same_or_inside(relative, moved));
+        if let Some(moved) = overlapping {
+            warn!(
+                target: "project",
+                "{}/{} overlaps with the already moved {}/{}!",
+                self.name,
+                relative,
+                self.name,
+                moved
+            );
+            return Err(());
+        }
+
+        let from_path = join_relative(&project_state.directory.join(&self.name), relative);
+
+        // The totals are only known once the subdirectory is measured
+        let progress = Arc::new(Mutex::new(MoveAndSymlinkProgress::new(0, FileSize::ZERO)));
+        progress.lock().unwrap().stage = MoveAndSymlinkStage::CheckingNames;
+
+        *self.state.lock().unwrap() = ProjectDirectoryEntryState::MovingTo {
+            path: to_path.clone(),
+            progress: progress.clone(),
+        };
+
+        let state = self.state.clone();
+        let stats_mutex = self.stats.clone();
+        let compatibility_issues = self.compatibility_issues.clone();
+        let options = MoveOptions {
+            filter: project_state.filter_for(&self.name).descend_path(relative),
+            ..project_state.move_options()
+        };
+        let name = self.name.clone();
+        let relative = relative.to_string();
+        let project_directory = project_state.directory.clone();
+        let settings = project_state.settings.clone();
+        let stats_cache = project_state.stats_cache.clone();
+        let cancellation_token = project_state.cancellation_token.clone();
+        let remaining_quota = project_state.remaining_quota(&to_path);
+
+        let label = format!("Move {} to {}", relative, to_path.display());
+        let devices = devices_of(&[&from_path, &to_path]);
+        self.enqueue_job(
+            project_state,
+            label,
+            devices,
+            ProjectDirectoryEntryState::InOriginalLocation,
+            async move {
+                let stats = from_path
+                    .calc_directory_stats(&options.filter, Some(&cancellation_token))
+                    .await;
+                let can_move = match stats {
+                    Ok(ref stats) if stats.symlink_count > 0 => {
+                        error!("Can't move {:?}: it has symlinks", from_path);
+                        false
+                    }
+                    Ok(ref stats)
+                        if !check_space(&to_path, stats)
+                            || !check_quota(&to_path, stats, remaining_quota) =>
+                    {
+                        false
+                    }
+                    Ok(ref stats) => {
+                        let mut progress = progress.lock().unwrap();
+                        *progress = MoveAndSymlinkProgress::from(stats);
+                        progress.stage = MoveAndSymlinkStage::CheckingNames;
+                        true
+                    }
+                    Err(err) => {
+                        error!("Failed to calculate stats for {:?}: {:?}", from_path, err);
+                        false
+                    }
+                };
+
+                if can_move && check_names(&from_path, &to_path, &compatibility_issues).await {
+                    let result = from_path
+                        .move_and_symlink(&to_path, &options, Some(progress), None)
+                        .await;
+                    match result {
+                        Ok(_) => {
+                            info!(target: "project", "Moved {}/{} to {:?}", name, relative, to_path);
+                            let mut settings = settings.lock().unwrap();
+                            settings.update_entry(&name, |entry| {
+                                entry.moved_subdirectories.insert(relative, to_path);
+                            });
+                            if let Err(err) = settings.save(&project_directory) {
+                                error!("Failed to save project settings: {}", err);
+                            }
+                        }
+                        Err(err) => {
+                            error!("Failed to move subdirectory: {:?}", err);
+                        }
+                    }
+                }
+
+                *state.lock().unwrap() = ProjectDirectoryEntryState::InOriginalLocation;
+                recalc_entry_stats(
+                    &project_directory,
+                    &settings,
+                    name,
+                    &state,
+                    stats_mutex,
+                    stats_cache,
+                    cancellation_token,
+                )
+                .await;
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Start moving the subdirectory at the `/`-separated `relative` path inside the entry back
+    /// from where it was moved to.
+    pub fn try_start_subdirectory_move_back(
+        &self,
+        project_state: &ProjectState,
+        relative: &str,
+    ) -> Result<(), ()> {
+        if !self.can_change_subdirectories() {
+            return Err(());
+        }
+        let Some(to_path) = project_state
+            .moved_subdirectories(&self.name)
+            .remove(relative)
+        else {
+            warn!(target: "project", "{}/{} wasn't moved!", self.name, relative);
+            return Err(());
+        };
+
+        let from_path = join_relative(&project_state.directory.join(&self.name), relative);
+
+        // The totals are only known once the moved subdirectory is measured
+        let progress = Arc::new(Mutex::new(MoveBackProgress::new(0, FileSize::ZERO)));
+
+        *self.state.lock().unwrap() = ProjectDirectoryEntryState::MovingFrom {
+            path: to_path.clone(),
+            progress: progress.clone(),
+        };
+
+        let state = self.state.clone();
+        let stats_mutex = self.stats.clone();
+        let filter = project_state.filter_for(&self.name).descend_path(relative);
+        let name = self.name.clone();
+        let relative = relative.to_string();
+        let project_directory = project_state.directory.clone();
+        let settings = project_state.settings.clone();
+        let stats_cache = project_state.stats_cache.clone();
+        let cancellation_token = project_state.cancellation_token.clone();
+
+        let label = format!("Move {} back from {}", relative, to_path.display());
+        let devices = devices_of(&[&project_directory, &to_path]);
+        self.enqueue_job(
+            project_state,
+            label,
+            devices,
+            ProjectDirectoryEntryState::InOriginalLocation,
+            async move {
+                let stats = to_path
+                    .calc_directory_stats(&filter, Some(&cancellation_token))
+                    .await;
+                match stats {
+                    Ok(ref stats) if !check_space(&project_directory, stats) => {
+                        *state.lock().unwrap() = ProjectDirectoryEntryState::InOriginalLocation;
+                        return;
+                    }
+                    Ok(ref stats) => *progress.lock().unwrap() = MoveBackProgress::from(stats),
+                    Err(err) => {
+                        warn!("Failed to calculate stats for {:?}: {:?}", to_path, err);
+                    }
+                }
+
+                let result = from_path.move_back(&to_path, Some(progress), None).await;
+                match result {
+                    Ok(_) => {
+                        info!(target: "project", "Moved {}/{} back", name, relative);
+                        let mut settings = settings.lock().unwrap();
+                        settings.update_entry(&name, |entry| {
+                            entry.moved_subdirectories.remove(&relative);
+                        });
+                        if let Err(err) = settings.save(&project_directory) {
+                            error!("Failed to save project settings: {}", err);
+                        }
+                    }
+                    Err(err) => {
+                        error!("Failed to move subdirectory back: {:?}", err);
+                    }
+                }
+
+                *state.lock().unwrap() = ProjectDirectoryEntryState::InOriginalLocation;
+                recalc_entry_stats(
+                    &project_directory,
+                    &settings,
+                    name,
+                    &state,
+                    stats_mutex,
+                    stats_cache,
+                    cancellation_token,
+                )
+                .await;
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Whether subdirectories of the entry can be moved on their own, or moved back, right now.
+    pub fn can_change_subdirectories(&self) -> bool {
+        !self.is_being_audited()
+            && matches!(
+                self.state.lock().unwrap().deref(),
+                ProjectDirectoryEntryState::InOriginalLocation
+            )
+    }
 }
 
 #[derive(Debug)]
 pub struct ProjectFileEntry {
     pub name: String,
     pub size: FileSize,
+    pub allocated_size: FileSize,
+    pub accessed: Option<SystemTime>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_same_or_inside() {
+        assert!(is_same_or_inside("data", "data"));
+        assert!(is_same_or_inside("data/textures", "data"));
+        assert!(!is_same_or_inside("database", "data"));
+        assert!(!is_same_or_inside("data", "data/textures"));
+    }
 }
@@ -0,0 +1,210 @@
+use crate::file_size::FileSize;
+use crate::move_filter::MoveFilter;
+use crate::table_view::TableView;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+
+/// Name of the file in the project directory the project settings are stored in.
+pub const PROJECT_SETTINGS_FILE_NAME: &str = ".moverr-project.json";
+
+/// Settings stored together with a project, in its directory.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectSettings {
+    /// Exclude patterns applied to every entry in the project. See [`MoveFilter`].
+    pub exclude: Vec<String>,
+    /// Settings of specific entries, keyed by their name.
+    pub entries: BTreeMap<String, EntrySettings>,
+    /// How many directories on the same device get measured at once, or `None` for the
+    /// [default](crate::stats_scheduler::DEFAULT_CONCURRENCY_PER_DEVICE).
+    pub stats_concurrency: Option<NonZeroUsize>,
+    /// Libraries the entries of the project can be moved to.
+    pub libraries: Vec<DestinationLibrary>,
+    /// Label of the library entries are moved to by default, or `None` for the first one.
+    pub default_library: Option<String>,
+    /// How the project table was last sorted and filtered.
+    pub view: TableView,
+}
+
+/// A directory entries get moved to, usually on another drive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DestinationLibrary {
+    pub label: String,
+    pub path: PathBuf,
+    /// How much of the library the project's entries may take up, if limited.
+    #[serde(default)]
+    pub quota: Option<FileSize>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EntrySettings {
+    /// Exclude patterns applied to this entry, after the project-wide ones.
+    pub exclude: Vec<String>,
+    /// Where the entry's content was moved to, if it was only moved partially.
+    ///
+    /// Fully moved entries are symlinks themselves, so they don't need to be tracked here.
+    pub partially_moved_to: Option<PathBuf>,
+    /// Subdirectories of the entry that were moved on their own, keyed by their `/`-separated
+    /// path relative to the entry, with where they were moved to.
+    pub moved_subdirectories: BTreeMap<String, PathBuf>,
+    /// Archive the entry was packed into, if it's in cold storage.
+    ///
+    /// The entry doesn't exist in the project directory while it's archived, so this is the only
+    /// record of it.
+    pub archived_to: Option<PathBuf>,
+}
+
+impl EntrySettings {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl ProjectSettings {
+    pub fn path_for(project_directory: &Path) -> PathBuf {
+        project_directory.join(PROJECT_SETTINGS_FILE_NAME)
+    }
+
+    /// Load the settings of the project in `project_directory`, or the defaults if it has none.
+    pub fn load(project_directory: &Path) -> Result<Self, String> {
+        let path = Self::path_for(project_directory);
+        match fs::read(&path) {
+            Ok(json) => serde_json::from_slice(&json)
+                .map_err(|e| format!("Failed to parse {}: {}", path.display(), e)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(format!("Failed to read {}: {}", path.display(), err)),
+        }
+    }
+
+    pub fn save(&self, project_directory: &Path) -> Result<(), String> {
+        let path = Self::path_for(project_directory);
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|e| format!("Failed to serialize project settings: {}", e))?;
+        fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    pub fn entry(&self, name: &str) -> Option<&EntrySettings> {
+        self.entries.get(name)
+    }
+
+    /// Update the settings of the entry `name`, dropping them if they end up being the defaults.
+    pub fn update_entry(&mut self, name: &str, update: impl FnOnce(&mut EntrySettings)) {
+        let entry = self.entries.entry(name.to_string()).or_default();
+        update(entry);
+        if entry.is_default() {
+            self.entries.remove(name);
+        }
+    }
+
+    /// Get the library entries are moved to by default, out of `libraries`.
+    pub fn default_library<'a>(
+        &self,
+        libraries: &'a [DestinationLibrary],
+    ) -> Option<&'a DestinationLibrary> {
+        self.default_library
+            .as_ref()
+            .and_then(|label| libraries.iter().find(|library| library.label == *label))
+            .or(libraries.first())
+    }
+
+    /// Build the filter for the entry `name` from the project-wide and entry patterns.
+    pub fn filter_for(&self, name: &str) -> Result<MoveFilter, String> {
+        let entry_patterns = self
+            .entry(name)
+            .map(|entry| entry.exclude.as_slice())
+            .unwrap_or_default();
+        MoveFilter::new(&[self.exclude.as_slice(), entry_patterns].concat())
+    }
+
+    /// Build the filter for the content of the entry `name` that's still in place, which is like
+    /// [`filter_for`](Self::filter_for), but also excludes the entry's moved subdirectories.
+    pub fn local_filter_for(&self, name: &str) -> Result<MoveFilter, String> {
+        let mut patterns = self.exclude.clone();
+        if let Some(entry) = self.entry(name) {
+            patterns.extend(entry.exclude.iter().cloned());
+            // Without a trailing `/`, so the symlinks left in place of the subdirectories match
+            patterns.extend(
+                entry
+                    .moved_subdirectories
+                    .keys()
+                    .map(|path| format!("/{}", globset::escape(path))),
+            );
+        }
+        MoveFilter::new(&patterns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_entry() {
+        let mut settings = ProjectSettings::default();
+        settings.update_entry("Game", |entry| entry.exclude = vec!["*.log".to_string()]);
+        assert_eq!(settings.entry("Game").unwrap().exclude, ["*.log"]);
+
+        settings.update_entry("Game", |entry| entry.exclude.clear());
+        assert!(settings.entry("Game").is_none());
+    }
+
+    #[test]
+    fn test_default_library() {
+        let library = |label: &str| DestinationLibrary {
+            label: label.to_string(),
+            path: PathBuf::from(label),
+            quota: None,
+        };
+        let libraries = [library("SSD"), library("HDD")];
+        let mut settings = ProjectSettings::default();
+        assert_eq!(settings.default_library(&libraries), Some(&libraries[0]));
+
+        settings.default_library = Some("HDD".to_string());
+        assert_eq!(settings.default_library(&libraries), Some(&libraries[1]));
+
+        // A removed library falls back to the first one
+        settings.default_library = Some("NAS".to_string());
+        assert_eq!(settings.default_library(&libraries), Some(&libraries[0]));
+        assert_eq!(settings.default_library(&[]), None);
+    }
+
+    #[test]
+    fn test_filter_for() {
+        let mut settings = ProjectSettings {
+            exclude: vec!["*.log".to_string()],
+            ..Default::default()
+        };
+        settings.update_entry("Game", |entry| {
+            entry.exclude = vec!["!keep.log".to_string()]
+        });
+
+        let filter = settings.filter_for("Game").unwrap();
+        assert!(!filter.is_excluded("keep.log", false));
+        assert!(filter.is_excluded("crash.log", false));
+        assert!(settings
+            .filter_for("Other")
+            .unwrap()
+            .is_excluded("keep.log", false));
+    }
+
+    #[test]
+    fn test_local_filter_for() {
+        let mut settings = ProjectSettings::default();
+        settings.update_entry("Game", |entry| {
+            entry
+                .moved_subdirectories
+                .insert("data/[big]".to_string(), PathBuf::from("elsewhere"));
+        });
+
+        let filter = settings.local_filter_for("Game").unwrap();
+        assert!(filter.descend("data").is_excluded("[big]", false));
+        assert!(!filter.descend("data").is_excluded("b", true));
+        assert!(!filter.is_excluded("[big]", true));
+        assert!(settings.filter_for("Game").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+use crate::file_size::num_ext::AsBytes;
+use crate::file_size::FileSize;
+use crate::path_ext::{allocated_size, DirectoryStats, DirectoryStatsError};
+use crate::sync::CancellationToken;
+use futures_lite::StreamExt;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// A file, symlink or directory together with its size, for showing what takes up the space
+/// inside a directory.
+#[derive(Debug, Clone)]
+pub struct SizeNode {
+    pub name: String,
+    pub size: FileSize,
+    pub kind: SizeNodeKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum SizeNodeKind {
+    File,
+    Symlink,
+    /// Children sorted by size, largest first.
+    Directory(Vec<SizeNode>),
+}
+
+impl SizeNode {
+    pub fn children(&self) -> &[SizeNode] {
+        match &self.kind {
+            SizeNodeKind::Directory(children) => children,
+            _ => &[],
+        }
+    }
+
+    pub fn is_directory(&self) -> bool {
+        matches!(self.kind, SizeNodeKind::Directory(_))
+    }
+
+    /// Get the node reached by following the child `indices` from this one.
+    pub fn descendant(&self, indices: &[usize]) -> Option<&SizeNode> {
+        indices
+            .iter()
+            .try_fold(self, |node, &index| node.children().get(index))
+    }
+
+    /// Share of `size` in this node's size, from 0 to 1.
+    pub fn share_of(&self, size: FileSize) -> f64 {
+        if self.size == FileSize::ZERO {
+            0.0
+        } else {
+            size.as_bytes() as f64 / self.size.as_bytes() as f64
+        }
+    }
+
+    fn directory(name: String, mut children: Vec<SizeNode>) -> Self {
+        // Largest first, ties by name so the order doesn't change between scans
+        children.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+        Self {
+            name,
+            size: children
+                .iter()
+                .fold(FileSize::ZERO, |size, child| size + child.size),
+            kind: SizeNodeKind::Directory(children),
+        }
+    }
+}
+
+/// Walk the directory at `path` and build its tree of sizes. Symlinks aren't followed.
+///
+/// Everything counted is also added to `running_total` as the walk goes.
+pub async fn scan_size_tree(
+    path: &Path,
+    running_total: Option<&Mutex<DirectoryStats>>,
+    cancellation_token: Option<&CancellationToken>,
+) -> Result<SizeNode, DirectoryStatsError> {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut children = Vec::new();
+
+    let mut entries = async_fs::read_dir(path)
+        .await
+        .map_err(|e| DirectoryStatsError::Io(e.kind()))?;
+    while let Some(child) = entries
+        .try_next()
+        .await
+        .map_err(|e| DirectoryStatsError::Io(e.kind()))?
+    {
+        if let Some(cancellation_token) = cancellation_token {
+            if cancellation_token.is_cancelled() {
+                return Err(DirectoryStatsError::Cancelled);
+            }
+        }
+
+        let metadata = async_fs::symlink_metadata(child.path())
+            .await
+            .map_err(|e| DirectoryStatsError::Io(e.kind()))?;
+        let child_name = child.file_name().to_string_lossy().into_owned();
+
+        if metadata.is_symlink() {
+            if let Some(running_total) = running_total {
+                running_total.lock().unwrap().symlink_count += 1;
+            }
+            children.push(SizeNode {
+                name: child_name,
+                size: FileSize::ZERO,
+                kind: SizeNodeKind::Symlink,
+            });
+        } else if metadata.is_dir() {
+            if let Some(running_total) = running_total {
+                running_total.lock().unwrap().subfolder_count += 1;
+            }
+            let subdirectory = Box::pin(scan_size_tree(
+                &child.path(),
+                running_total,
+                cancellation_token,
+            ))
+            .await?;
+            children.push(subdirectory);
+        } else {
+            if let Some(running_total) = running_total {
+                let mut running_total = running_total.lock().unwrap();
+                running_total.file_count += 1;
+                running_total.size += metadata.len().bytes();
+                running_total.allocated_size += allocated_size(&child.path(), &metadata);
+            }
+            children.push(SizeNode {
+                name: child_name,
+                size: metadata.len().bytes(),
+                kind: SizeNodeKind::File,
+            });
+        }
+    }
+
+    Ok(SizeNode::directory(name, children))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str, size: u64) -> SizeNode {
+        SizeNode {
+            name: name.to_string(),
+            size: size.bytes(),
+            kind: SizeNodeKind::File,
+        }
+    }
+
+    #[test]
+    fn test_directory_sorts_by_size() {
+        let tree = SizeNode::directory(
+            "game".to_string(),
+            vec![
+                file("b", 10),
+                SizeNode::directory("data".to_string(), vec![file("c", 30), file("d", 50)]),
+                file("a", 10),
+            ],
+        );
+
+        assert_eq!(tree.size, 100.bytes());
+        let names: Vec<_> = tree.children().iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["data", "a", "b"]);
+        assert_eq!(tree.descendant(&[0, 0]).unwrap().name, "d");
+        assert!(tree.descendant(&[1, 0]).is_none());
+        assert_eq!(tree.share_of(20.bytes()), 0.2);
+    }
+}
@@ -0,0 +1,454 @@
+use crate::file_size::num_ext::AsBytes;
+use crate::file_size::FileSize;
+use crate::move_filter::MoveFilter;
+use crate::path_ext::{allocated_size, DirectoryStats, DirectoryStatsError};
+use crate::sync::CancellationToken;
+use futures_lite::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{fs, io};
+
+/// Name of the file in the user's cache directory the stats cache is stored in.
+pub const STATS_CACHE_FILE_NAME: &str = "stats-cache.json";
+/// Version of the stats cache format, bumped on incompatible changes.
+pub const STATS_CACHE_VERSION: u32 = 3;
+
+/// How recently a directory has to have been modified for its modification time not to be
+/// trusted, as further changes within the same timestamp tick wouldn't change it.
+const MODIFICATION_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+/// How long a tree that isn't measured again is kept, e.g. one of a project that isn't opened
+/// anymore.
+const UNUSED_TREE_LIFETIME: Duration = Duration::from_secs(90 * 24 * 60 * 60);
+
+/// Directory stats remembered between runs, so that unchanged directories don't have to be walked
+/// file by file again.
+///
+/// Each directory is remembered together with its modification time, which changes whenever an
+/// entry is added to it, removed from it or renamed inside it. Directories with the same
+/// modification time only have their subdirectories checked again. Changes to the content of
+/// existing files don't touch their directory, so those aren't noticed until the directory
+/// changes otherwise.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StatsCache {
+    version: u32,
+    trees: BTreeMap<PathBuf, CachedTree>,
+}
+
+/// A cached directory tree, together with the filter it was measured with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedTree {
+    patterns: Vec<String>,
+    prefix: String,
+    root: CachedDirectory,
+    /// When the tree was last measured, in seconds since the Unix epoch.
+    #[serde(default = "now_secs")]
+    used: u64,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CachedDirectory {
+    /// Modification time in nanoseconds since the Unix epoch, or `None` if it can't be trusted.
+    modified: Option<u128>,
+    /// Inode number, so that a directory replaced by another one isn't mistaken for it.
+    file_id: Option<u64>,
+    /// Stats of the files and symlinks directly inside the directory.
+    own: DirectoryStats,
+    subdirectories: BTreeMap<String, CachedDirectory>,
+}
+
+impl CachedDirectory {
+    /// Get the stats of the whole directory tree.
+    pub fn total(&self) -> DirectoryStats {
+        let mut total = self.own.clone();
+        for (name, subdirectory) in &self.subdirectories {
+            total.subfolder_count += 1;
+            total.add_nested(name, &subdirectory.total());
+        }
+        total
+    }
+
+    fn is_up_to_date(&self, modified: Option<u128>, file_id: Option<u64>) -> bool {
+        self.modified.is_some() && self.modified == modified && self.file_id == file_id
+    }
+}
+
+impl StatsCache {
+    pub fn path() -> Option<PathBuf> {
+        Some(
+            dirs::cache_dir()?
+                .join(env!("CARGO_PKG_NAME").to_lowercase())
+                .join(STATS_CACHE_FILE_NAME),
+        )
+    }
+
+    /// Load the stats cache, or an empty one if there's none or it can't be used.
+    pub fn load() -> Result<Self, String> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+        let cache: Self = match fs::read(&path) {
+            Ok(json) => serde_json::from_slice(&json)
+                .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(format!("Failed to read {}: {}", path.display(), err)),
+        };
+        if cache.version != STATS_CACHE_VERSION {
+            return Ok(Self::default());
+        }
+        Ok(cache)
+    }
+
+    /// Save the cache, leaving out the trees that are gone or haven't been measured for a while.
+    pub fn save(&mut self) -> Result<(), String> {
+        let Some(path) = Self::path() else {
+            return Err("No cache directory to save the stats cache to".to_string());
+        };
+        self.version = STATS_CACHE_VERSION;
+        self.prune(now_secs(), |root| root.exists());
+        let json = serde_json::to_vec(self)
+            .map_err(|e| format!("Failed to serialize stats cache: {}", e))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// Get the cached tree of `path`, if it was measured with the same filter.
+    pub fn get(&self, path: &Path, filter: &MoveFilter) -> Option<&CachedDirectory> {
+        self.trees
+            .get(path)
+            .filter(|tree| tree.patterns == filter.patterns() && tree.prefix == filter.prefix())
+            .map(|tree| &tree.root)
+    }
+
+    pub fn insert(&mut self, path: PathBuf, filter: &MoveFilter, root: CachedDirectory) {
+        self.trees.insert(
+            path,
+            CachedTree {
+                patterns: filter.patterns().to_vec(),
+                prefix: filter.prefix().to_string(),
+                root,
+                used: now_secs(),
+            },
+        );
+    }
+
+    /// Forget the trees whose root doesn't `exist` anymore, or that weren't measured within
+    /// [`UNUSED_TREE_LIFETIME`] before `now`.
+    fn prune(&mut self, now: u64, exists: impl Fn(&Path) -> bool) {
+        let oldest = now.saturating_sub(UNUSED_TREE_LIFETIME.as_secs());
+        self.trees
+            .retain(|root, tree| tree.used >= oldest && exists(root));
+    }
+
+    /// Forget the cached tree of `path`.
+    pub fn remove(&mut self, path: &Path) {
+        self.trees.remove(path);
+    }
+
+    /// Make sure the directory containing `path` gets walked again, e.g. after a file in it
+    /// changed, which doesn't change the directory's modification time.
+    pub fn invalidate(&mut self, path: &Path) {
+        for (root, tree) in self.trees.iter_mut() {
+            let Ok(relative) = path.strip_prefix(root) else {
+                continue;
+            };
+            let mut directory = &mut tree.root;
+            for component in relative.components() {
+                let name = component.as_os_str().to_string_lossy();
+                if !directory.subdirectories.contains_key(name.as_ref()) {
+                    break;
+                }
+                directory = directory.subdirectories.get_mut(name.as_ref()).unwrap();
+            }
+            directory.modified = None;
+        }
+    }
+}
+
+/// Like [`PathExt::calc_directory_stats`], but only walks the directories that changed since
+/// `cached` was measured, and returns the tree to cache next time.
+///
+/// Everything counted is also added to `running_total` as the walk goes, so the stats measured so
+/// far can be shown before it's done.
+///
+/// [`PathExt::calc_directory_stats`]: crate::path_ext::PathExt::calc_directory_stats
+pub async fn calc_directory_stats_cached(
+    path: &Path,
+    filter: &MoveFilter,
+    cached: Option<&CachedDirectory>,
+    running_total: Option<&Mutex<DirectoryStats>>,
+    cancellation_token: Option<&CancellationToken>,
+) -> Result<CachedDirectory, DirectoryStatsError> {
+    let metadata = async_fs::metadata(path)
+        .await
+        .map_err(|e| DirectoryStatsError::Io(e.kind()))?;
+    let modified = trusted_modification_time(&metadata);
+    let file_id = file_id(&metadata);
+
+    if let Some(cached) = cached.filter(|cached| cached.is_up_to_date(modified, file_id)) {
+        // Nothing was added, removed or renamed here, but subdirectories could have changed
+        add_to(running_total, filter.prefix(), &cached.own);
+        let mut subdirectories = BTreeMap::new();
+        for (name, subdirectory) in &cached.subdirectories {
+            add_to(running_total, "", &ONE_SUBFOLDER);
+            let subdirectory = Box::pin(calc_directory_stats_cached(
+                &path.join(name),
+                &filter.descend(name),
+                Some(subdirectory),
+                running_total,
+                cancellation_token,
+            ))
+            .await?;
+            subdirectories.insert(name.clone(), subdirectory);
+        }
+        return Ok(CachedDirectory {
+            modified,
+            file_id,
+            own: cached.own.clone(),
+            subdirectories,
+        });
+    }
+
+    let mut directory = CachedDirectory {
+        modified,
+        file_id,
+        ..Default::default()
+    };
+
+    let mut children = async_fs::read_dir(path)
+        .await
+        .map_err(|e| DirectoryStatsError::Io(e.kind()))?;
+
+    while let Some(child) = children
+        .try_next()
+        .await
+        .map_err(|e| DirectoryStatsError::Io(e.kind()))?
+    {
+        if let Some(cancellation_token) = cancellation_token {
+            if cancellation_token.is_cancelled() {
+                return Err(DirectoryStatsError::Cancelled);
+            }
+        }
+
+        let metadata = async_fs::symlink_metadata(child.path())
+            .await
+            .map_err(|e| DirectoryStatsError::Io(e.kind()))?;
+        let name = child.file_name().to_string_lossy().into_owned();
+        if filter.is_excluded(&name, metadata.is_dir()) {
+            continue;
+        }
+
+        if metadata.is_symlink() {
+            directory.own.symlink_count += 1;
+            add_to(
+                running_total,
+                "",
+                &DirectoryStats {
+                    symlink_count: 1,
+                    ..Default::default()
+                },
+            );
+        } else if metadata.is_dir() {
+            add_to(running_total, "", &ONE_SUBFOLDER);
+            // Subdirectories can still be up to date even if this directory isn't
+            let cached = cached.and_then(|cached| cached.subdirectories.get(&name));
+            let subdirectory = Box::pin(calc_directory_stats_cached(
+                &child.path(),
+                &filter.descend(&name),
+                cached,
+                running_total,
+                cancellation_token,
+            ))
+            .await?;
+            directory.subdirectories.insert(name, subdirectory);
+        } else {
+            let mut file = DirectoryStats::default();
+            file.add_file(
+                &name,
+                metadata.len().bytes(),
+                allocated_size(&child.path(), &metadata),
+            );
+            directory.own += &file;
+            add_to(running_total, filter.prefix(), &file);
+        }
+    }
+
+    Ok(directory)
+}
+
+const ONE_SUBFOLDER: DirectoryStats = DirectoryStats {
+    subfolder_count: 1,
+    file_count: 0,
+    symlink_count: 0,
+    size: FileSize::ZERO,
+    allocated_size: FileSize::ZERO,
+    extensions: BTreeMap::new(),
+    largest_files: Vec::new(),
+};
+
+/// Add `stats` of the directory at the `/`-separated `relative` path inside the measured entry to
+/// its running total.
+fn add_to(running_total: Option<&Mutex<DirectoryStats>>, relative: &str, stats: &DirectoryStats) {
+    if let Some(running_total) = running_total {
+        running_total.lock().unwrap().add_nested(relative, stats);
+    }
+}
+
+fn trusted_modification_time(metadata: &Metadata) -> Option<u128> {
+    let modified = metadata.modified().ok()?;
+    // The directory could still change without its modification time changing
+    if SystemTime::now()
+        .duration_since(modified)
+        .map_or(true, |age| age < MODIFICATION_GRACE_PERIOD)
+    {
+        return None;
+    }
+    modified
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_nanos())
+}
+
+#[cfg(unix)]
+fn file_id(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directory(files: u64, subdirectories: &[(&str, CachedDirectory)]) -> CachedDirectory {
+        CachedDirectory {
+            modified: Some(1),
+            file_id: None,
+            own: DirectoryStats {
+                file_count: files,
+                size: files.bytes(),
+                ..Default::default()
+            },
+            subdirectories: subdirectories
+                .iter()
+                .map(|(name, dir)| (name.to_string(), dir.clone()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_total() {
+        let tree = directory(1, &[("a", directory(2, &[("b", directory(3, &[]))]))]);
+        let total = tree.total();
+        assert_eq!(total.file_count, 6);
+        assert_eq!(total.subfolder_count, 2);
+        assert_eq!(total.size, 6.bytes());
+    }
+
+    #[test]
+    fn test_total_largest_files() {
+        let mut own = DirectoryStats::default();
+        own.add_file("crash.DMP", 50.bytes(), 50.bytes());
+        let mut tree = directory(0, &[("logs", directory(0, &[]))]);
+        tree.subdirectories.get_mut("logs").unwrap().own = own;
+        tree.own.add_file("game.exe", 20.bytes(), 20.bytes());
+        tree.own.add_file("readme", 1.bytes(), 1.bytes());
+
+        let total = tree.total();
+        assert_eq!(
+            total.largest_files,
+            [
+                ("logs/crash.DMP".to_string(), 50.bytes()),
+                ("game.exe".to_string(), 20.bytes()),
+                ("readme".to_string(), 1.bytes()),
+            ]
+        );
+        assert_eq!(total.extensions["dmp"].size, 50.bytes());
+        assert_eq!(total.extensions[""].file_count, 1);
+    }
+
+    #[test]
+    fn test_prune() {
+        let mut cache = StatsCache::default();
+        for root in ["/games/old", "/games/gone", "/games/kept"] {
+            cache.insert(
+                PathBuf::from(root),
+                &MoveFilter::default(),
+                directory(1, &[]),
+            );
+        }
+        let now = now_secs() + UNUSED_TREE_LIFETIME.as_secs();
+        cache.trees.get_mut(Path::new("/games/old")).unwrap().used = 0;
+        cache.prune(now, |root| root != Path::new("/games/gone"));
+        assert_eq!(
+            cache.trees.keys().collect::<Vec<_>>(),
+            [Path::new("/games/kept")]
+        );
+    }
+
+    #[test]
+    fn test_is_up_to_date() {
+        let tree = directory(1, &[]);
+        assert!(tree.is_up_to_date(Some(1), None));
+        assert!(!tree.is_up_to_date(Some(2), None));
+        assert!(!tree.is_up_to_date(Some(1), Some(7)));
+        assert!(!CachedDirectory::default().is_up_to_date(None, None));
+    }
+
+    #[test]
+    fn test_get_checks_filter() {
+        let mut cache = StatsCache::default();
+        let filter = MoveFilter::new(&["*.log"]).unwrap();
+        cache.insert(PathBuf::from("/Game"), &filter, directory(1, &[]));
+
+        assert!(cache.get(Path::new("/Game"), &filter).is_some());
+        assert!(cache
+            .get(Path::new("/Game"), &MoveFilter::default())
+            .is_none());
+        assert!(cache
+            .get(Path::new("/Game"), &filter.descend("data"))
+            .is_none());
+        assert!(cache.get(Path::new("/Other"), &filter).is_none());
+    }
+
+    #[test]
+    fn test_invalidate() {
+        let mut cache = StatsCache::default();
+        let filter = MoveFilter::default();
+        let tree = directory(0, &[("a", directory(1, &[("b", directory(1, &[]))]))]);
+        cache.insert(PathBuf::from("/Game"), &filter, tree);
+
+        cache.invalidate(Path::new("/Game/a/file"));
+        let root = cache.get(Path::new("/Game"), &filter).unwrap();
+        assert!(root.modified.is_some());
+        assert!(root.subdirectories["a"].modified.is_none());
+        assert!(root.subdirectories["a"].subdirectories["b"]
+            .modified
+            .is_some());
+
+        cache.invalidate(Path::new("/Other/a"));
+        cache.invalidate(Path::new("/Game"));
+        assert!(cache
+            .get(Path::new("/Game"), &filter)
+            .unwrap()
+            .modified
+            .is_none());
+    }
+}
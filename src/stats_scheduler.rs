@@ -0,0 +1,182 @@
+use smol::{spawn, Task};
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::pin::Pin;
+
+/// How many directories on the same device get measured at once by default.
+///
+/// Walking several directories at once on a spinning disk makes it seek back and forth between
+/// them, which is slower than walking them one after another.
+pub const DEFAULT_CONCURRENCY_PER_DEVICE: NonZeroUsize = NonZeroUsize::new(1).unwrap();
+
+/// Identifies the device a directory is stored on.
+pub type DeviceId = u64;
+
+/// The priority of a queued calculation. Calculations with lower priorities start first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StatsPriority {
+    /// The entry is selected.
+    Selected,
+    /// The entry is visible in the project table.
+    Visible,
+    Background,
+}
+
+struct QueuedCalc {
+    name: String,
+    device: Option<DeviceId>,
+    future: Pin<Box<dyn Future<Output = ()> + Send>>,
+}
+
+struct RunningCalc {
+    name: String,
+    device: Option<DeviceId>,
+    task: Task<()>,
+}
+
+/// Runs the stats calculations of project entries a few at a time per device, the most important
+/// ones first.
+pub struct StatsScheduler {
+    concurrency_per_device: NonZeroUsize,
+    queue: Vec<QueuedCalc>,
+    running: Vec<RunningCalc>,
+}
+
+impl StatsScheduler {
+    pub fn new(concurrency_per_device: NonZeroUsize) -> Self {
+        Self {
+            concurrency_per_device,
+            queue: Vec::new(),
+            running: Vec::new(),
+        }
+    }
+
+    /// Queue `future` calculating the stats of the entry `name` stored on `device`, replacing
+    /// the queued calculation of the entry, if any.
+    pub fn enqueue(
+        &mut self,
+        name: String,
+        device: Option<DeviceId>,
+        future: impl Future<Output = ()> + Send + 'static,
+    ) {
+        self.queue.retain(|calc| calc.name != name);
+        self.queue.push(QueuedCalc {
+            name,
+            device,
+            future: Box::pin(future),
+        });
+    }
+
+    pub fn is_queued(&self, name: &str) -> bool {
+        self.queue.iter().any(|calc| calc.name == name)
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.queue.is_empty() && self.running.is_empty()
+    }
+
+    /// Start queued calculations while their devices have capacity left, the ones with the lowest
+    /// `priority` first and otherwise in the order they were queued in.
+    ///
+    /// Returns whether the last running calculation has just finished.
+    pub fn pump(&mut self, priority: impl Fn(&str) -> StatsPriority) -> bool {
+        let was_idle = self.is_idle();
+        self.running.retain(|calc| !calc.task.is_finished());
+
+        while let Some(index) = self.next(&priority) {
+            let calc = self.queue.remove(index);
+            self.running.push(RunningCalc {
+                name: calc.name,
+                device: calc.device,
+                task: spawn(calc.future),
+            });
+        }
+
+        !was_idle && self.is_idle()
+    }
+
+    /// Get the index of the queued calculation to start next, if any can be started.
+    fn next(&self, priority: impl Fn(&str) -> StatsPriority) -> Option<usize> {
+        self.queue
+            .iter()
+            .enumerate()
+            .filter(|(_, calc)| {
+                // A calculation of the same entry could be overwritten by the running one
+                !self.running.iter().any(|running| running.name == calc.name)
+                    && self
+                        .running
+                        .iter()
+                        .filter(|running| running.device == calc.device)
+                        .count()
+                        < self.concurrency_per_device.get()
+            })
+            .min_by_key(|(index, calc)| (priority(&calc.name), *index))
+            .map(|(index, _)| index)
+    }
+}
+
+/// Get the device `path` is stored on, following symlinks.
+#[cfg(unix)]
+pub fn device_of(path: &Path) -> Option<DeviceId> {
+    use std::os::unix::fs::MetadataExt;
+    path.metadata().ok().map(|metadata| metadata.dev())
+}
+
+/// Get the device `path` is stored on, following symlinks.
+#[cfg(windows)]
+pub fn device_of(path: &Path) -> Option<DeviceId> {
+    use crate::path_ext::PathExt;
+    use std::hash::{DefaultHasher, Hash, Hasher};
+
+    let path = path.canonicalize().ok()?;
+    let mut hasher = DefaultHasher::new();
+    path.find_volume_root()?.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_priority_and_concurrency() {
+        let started = Arc::new(AtomicUsize::new(0));
+        let mut scheduler = StatsScheduler::new(NonZeroUsize::new(1).unwrap());
+        for (name, device) in [("a", 1), ("b", 1), ("c", 2)] {
+            let started = started.clone();
+            scheduler.enqueue(name.to_string(), Some(device), async move {
+                started.fetch_add(1, Ordering::SeqCst);
+                smol::Timer::after(Duration::from_millis(50)).await;
+            });
+        }
+
+        scheduler.pump(|name| {
+            if name == "b" {
+                StatsPriority::Selected
+            } else {
+                StatsPriority::Background
+            }
+        });
+        // One per device, with "b" preferred over "a"
+        assert!(scheduler.is_queued("a"));
+        assert!(!scheduler.is_queued("b"));
+        assert!(!scheduler.is_queued("c"));
+
+        while !scheduler.pump(|_| StatsPriority::Background) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(started.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_enqueue_replaces() {
+        let mut scheduler = StatsScheduler::new(DEFAULT_CONCURRENCY_PER_DEVICE);
+        scheduler.enqueue("a".to_string(), None, async {});
+        scheduler.enqueue("a".to_string(), None, async {});
+        assert_eq!(scheduler.queue.len(), 1);
+    }
+}
@@ -0,0 +1,677 @@
+//! Integration with Steam: finding its libraries and reading the manifests of the games in them.
+
+pub mod vdf;
+
+use crate::file_size::num_ext::AsBytes;
+use crate::file_size::FileSize;
+use log::warn;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use vdf::VdfObject;
+
+/// Directory of a Steam library with the manifests and the `common` directory in it.
+pub const STEAMAPPS_DIR_NAME: &str = "steamapps";
+
+/// Directory in `steamapps` the games are installed in.
+pub const COMMON_DIR_NAME: &str = "common";
+
+/// File in the `steamapps` directory of the Steam installation listing all libraries.
+pub const LIBRARY_FOLDERS_FILE_NAME: &str = "libraryfolders.vdf";
+
+/// A directory Steam installs games in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SteamLibrary {
+    pub path: PathBuf,
+    pub label: String,
+    /// The apps in the library with their sizes, as Steam last recorded them.
+    pub apps: BTreeMap<u32, FileSize>,
+}
+
+impl SteamLibrary {
+    pub fn steamapps(&self) -> PathBuf {
+        self.path.join(STEAMAPPS_DIR_NAME)
+    }
+
+    /// Get the directory the games are in, which is what's opened as a project.
+    pub fn common(&self) -> PathBuf {
+        self.steamapps().join(COMMON_DIR_NAME)
+    }
+
+    /// Get the name to show for the library, which is its label if it has one.
+    pub fn display_name(&self) -> String {
+        if self.label.is_empty() {
+            self.path.display().to_string()
+        } else {
+            format!("{} ({})", self.label, self.path.display())
+        }
+    }
+}
+
+/// Get the directories Steam is installed in, e.g. `~/.local/share/Steam`.
+pub fn steam_roots() -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Some(home) = dirs::home_dir() {
+        candidates.push(home.join(".steam/steam"));
+        candidates.push(home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam"));
+    }
+    if let Some(data) = dirs::data_dir() {
+        candidates.push(data.join("Steam"));
+    }
+    #[cfg(windows)]
+    candidates.push(PathBuf::from(r"C:\Program Files (x86)\Steam"));
+
+    let mut roots: Vec<_> = candidates
+        .into_iter()
+        .filter(|candidate| candidate.join(STEAMAPPS_DIR_NAME).is_dir())
+        // `~/.steam/steam` is usually a symlink to one of the others
+        .filter_map(|candidate| candidate.canonicalize().ok())
+        .collect();
+    roots.sort();
+    roots.dedup();
+    roots
+}
+
+/// Find the libraries of all Steam installations.
+pub fn find_libraries() -> Vec<SteamLibrary> {
+    let mut libraries: Vec<SteamLibrary> = Vec::new();
+    for root in steam_roots() {
+        match read_library_folders(&root) {
+            Ok(found) => {
+                for library in found {
+                    if !libraries.iter().any(|known| known.path == library.path) {
+                        libraries.push(library);
+                    }
+                }
+            }
+            Err(err) => warn!(target: "steam", "{}", err),
+        }
+    }
+    libraries
+}
+
+/// Read the libraries listed in the `libraryfolders.vdf` of the Steam installation at `root`.
+pub fn read_library_folders(root: &Path) -> Result<Vec<SteamLibrary>, String> {
+    let path = root
+        .join(STEAMAPPS_DIR_NAME)
+        .join(LIBRARY_FOLDERS_FILE_NAME);
+    let text = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    parse_library_folders(&text).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+fn parse_library_folders(text: &str) -> Result<Vec<SteamLibrary>, String> {
+    let root = vdf::parse(text)?;
+    let folders = root
+        .get_object("libraryfolders")
+        .ok_or("missing libraryfolders")?;
+    Ok(folders
+        .0
+        .iter()
+        .filter_map(|(_, folder)| match folder {
+            vdf::Vdf::Object(folder) => Some(folder),
+            // Old versions also list e.g. the content ID next to the folders
+            vdf::Vdf::Value(_) => None,
+        })
+        .filter_map(|folder| {
+            Some(SteamLibrary {
+                path: PathBuf::from(folder.get_str("path")?),
+                label: folder.get_str("label").unwrap_or_default().to_string(),
+                apps: folder
+                    .get_object("apps")
+                    .map_or_else(BTreeMap::new, parse_apps),
+            })
+        })
+        .collect())
+}
+
+fn parse_apps(apps: &VdfObject) -> BTreeMap<u32, FileSize> {
+    apps.0
+        .iter()
+        .filter_map(|(app_id, size)| match size {
+            vdf::Vdf::Value(size) => {
+                Some((app_id.parse().ok()?, size.parse::<u64>().ok()?.bytes()))
+            }
+            vdf::Vdf::Object(_) => None,
+        })
+        .collect()
+}
+
+/// Get the `steamapps` directory of the library whose `common` directory is `directory`, if it's
+/// one.
+pub fn steamapps_of(directory: &Path) -> Option<&Path> {
+    let steamapps = directory.parent()?;
+    (directory.file_name()? == COMMON_DIR_NAME && steamapps.file_name()? == STEAMAPPS_DIR_NAME)
+        .then_some(steamapps)
+}
+
+/// How far along the installation of an app is, from the `StateFlags` of its manifest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstallState {
+    Installed,
+    UpdateRequired,
+    Updating,
+    UpdatePaused,
+    Validating,
+    Uninstalling,
+    FilesMissing,
+    NotInstalled,
+}
+
+impl InstallState {
+    const UPDATE_REQUIRED: u32 = 1 << 1;
+    const FULLY_INSTALLED: u32 = 1 << 2;
+    const FILES_MISSING: u32 = 1 << 5;
+    const FILES_CORRUPT: u32 = 1 << 7;
+    const UPDATE_RUNNING: u32 = 1 << 8;
+    const UPDATE_PAUSED: u32 = 1 << 9;
+    const UPDATE_STARTED: u32 = 1 << 10;
+    const UNINSTALLING: u32 = 1 << 11;
+    const VALIDATING: u32 = 1 << 17;
+    /// Adding files, preallocating, downloading, staging and committing.
+    const DOWNLOADING: u32 = 0b11111 << 18;
+
+    pub fn from_flags(flags: u32) -> Self {
+        if flags & Self::UNINSTALLING != 0 {
+            Self::Uninstalling
+        } else if flags & Self::VALIDATING != 0 {
+            Self::Validating
+        } else if flags & (Self::UPDATE_RUNNING | Self::UPDATE_STARTED | Self::DOWNLOADING) != 0 {
+            Self::Updating
+        } else if flags & Self::UPDATE_PAUSED != 0 {
+            Self::UpdatePaused
+        } else if flags & (Self::FILES_MISSING | Self::FILES_CORRUPT) != 0 {
+            Self::FilesMissing
+        } else if flags & Self::UPDATE_REQUIRED != 0 {
+            Self::UpdateRequired
+        } else if flags & Self::FULLY_INSTALLED != 0 {
+            Self::Installed
+        } else {
+            Self::NotInstalled
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Installed => "Installed",
+            Self::UpdateRequired => "Update required",
+            Self::Updating => "Updating",
+            Self::UpdatePaused => "Update paused",
+            Self::Validating => "Validating",
+            Self::Uninstalling => "Uninstalling",
+            Self::FilesMissing => "Files missing",
+            Self::NotInstalled => "Not installed",
+        }
+    }
+}
+
+/// What Steam knows about an installed app, from its `appmanifest_<app ID>.acf`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppManifest {
+    pub app_id: u32,
+    pub name: String,
+    /// Name of the app's directory in `steamapps/common`.
+    pub install_dir: String,
+    pub state_flags: u32,
+    pub size_on_disk: FileSize,
+}
+
+impl AppManifest {
+    pub fn file_name(app_id: u32) -> String {
+        format!("appmanifest_{}.acf", app_id)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let root = vdf::parse(text)?;
+        let app_state = root.get_object("AppState").ok_or("missing AppState")?;
+        let field = |key: &str| {
+            app_state
+                .get_str(key)
+                .ok_or_else(|| format!("missing {}", key))
+        };
+        Ok(Self {
+            app_id: field("appid")?
+                .parse()
+                .map_err(|e| format!("invalid appid: {}", e))?,
+            name: field("name")?.to_string(),
+            install_dir: field("installdir")?.to_string(),
+            state_flags: app_state
+                .get_str("StateFlags")
+                .and_then(|flags| flags.parse().ok())
+                .unwrap_or_default(),
+            size_on_disk: app_state
+                .get_str("SizeOnDisk")
+                .and_then(|size| size.parse::<u64>().ok())
+                .unwrap_or_default()
+                .bytes(),
+        })
+    }
+
+    pub fn install_state(&self) -> InstallState {
+        InstallState::from_flags(self.state_flags)
+    }
+}
+
+/// Read the manifests of the apps in `steamapps`, by the directory they're installed in.
+pub fn read_app_manifests(steamapps: &Path) -> BTreeMap<String, AppManifest> {
+    let Ok(entries) = fs::read_dir(steamapps) else {
+        return BTreeMap::new();
+    };
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("appmanifest_") && name.ends_with(".acf"))
+        })
+        .filter_map(|path| {
+            let manifest = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| AppManifest::parse(&text));
+            match manifest {
+                Ok(manifest) => Some((manifest.install_dir.clone(), manifest)),
+                Err(err) => {
+                    warn!(target: "steam", "Ignoring {}: {}", path.display(), err);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Check whether Steam is running, as it keeps its own copy of the library folders and would
+/// overwrite any changes to them. Detected on Linux from the PID file Steam leaves behind, on
+/// Windows from the list of processes.
+pub fn is_steam_running() -> bool {
+    #[cfg(unix)]
+    {
+        let Some(pid_file) = dirs::home_dir().map(|home| home.join(".steam/steam.pid")) else {
+            return false;
+        };
+        let Ok(pid) = fs::read_to_string(pid_file) else {
+            return false;
+        };
+        // The PID might have been reused since Steam exited
+        fs::read_to_string(format!("/proc/{}/comm", pid.trim()))
+            .is_ok_and(|comm| comm.trim().starts_with("steam"))
+    }
+    #[cfg(windows)]
+    {
+        let output = std::process::Command::new("tasklist")
+            .args(["/FI", "IMAGENAME eq steam.exe", "/FO", "CSV", "/NH"])
+            .output();
+        match output {
+            Ok(output) => String::from_utf8_lossy(&output.stdout)
+                .to_lowercase()
+                .contains("\"steam.exe\""),
+            // Better refuse to move games than have Steam undo the changes
+            Err(err) => {
+                warn!(target: "steam", "Failed to check whether Steam is running: {}", err);
+                true
+            }
+        }
+    }
+}
+
+/// A change to a file made as part of [`replace_files`], with the content it has before and
+/// after, `None` if it doesn't exist.
+struct FileChange {
+    path: PathBuf,
+    old: Option<Vec<u8>>,
+    new: Option<Vec<u8>>,
+}
+
+impl FileChange {
+    fn temp_path(&self) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(".moverr-tmp");
+        self.path.with_file_name(name)
+    }
+
+    fn reverted(&self) -> Self {
+        Self {
+            path: self.path.clone(),
+            old: self.new.clone(),
+            new: self.old.clone(),
+        }
+    }
+}
+
+/// Make all `changes` or none of them. The new contents are written to temporary files first,
+/// which are renamed into place once all are written. If one of the changes fails, the ones made
+/// before it are reverted.
+fn replace_files(changes: &[FileChange]) -> Result<(), String> {
+    for (index, change) in changes.iter().enumerate() {
+        let Some(new) = &change.new else {
+            continue;
+        };
+        let temp_path = change.temp_path();
+        if let Err(err) = fs::write(&temp_path, new) {
+            for change in changes[..=index]
+                .iter()
+                .filter(|change| change.new.is_some())
+            {
+                let _ = fs::remove_file(change.temp_path());
+            }
+            return Err(format!("Failed to write {}: {}", temp_path.display(), err));
+        }
+    }
+
+    for (index, change) in changes.iter().enumerate() {
+        let res = match change.new {
+            Some(_) => fs::rename(change.temp_path(), &change.path),
+            None => fs::remove_file(&change.path),
+        };
+        if let Err(err) = res {
+            for change in &changes[index + 1..] {
+                let _ = fs::remove_file(change.temp_path());
+            }
+            let reverted: Vec<_> = changes[..index]
+                .iter()
+                .rev()
+                .map(FileChange::reverted)
+                .collect();
+            if let Err(revert_err) = replace_files(&reverted) {
+                warn!(target: "steam", "Failed to revert the changes: {}", revert_err);
+            }
+            return Err(format!(
+                "Failed to replace {}: {}",
+                change.path.display(),
+                err
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Record that `app` was moved from the library with `from_steamapps` to the one with
+/// `to_steamapps`, before the original directory is removed: the manifest is moved along and the
+/// app is moved to the other library in the `libraryfolders.vdf` of the Steam installations
+/// listing both. Either all of these files are changed or none of them.
+pub fn relocate_app(
+    app: &AppManifest,
+    from_steamapps: &Path,
+    to_steamapps: &Path,
+) -> Result<(), String> {
+    let file_name = AppManifest::file_name(app.app_id);
+    let from_manifest = from_steamapps.join(&file_name);
+    let to_manifest = to_steamapps.join(&file_name);
+    let manifest = fs::read(&from_manifest)
+        .map_err(|e| format!("Failed to read {}: {}", from_manifest.display(), e))?;
+
+    let (Some(from_library), Some(to_library)) = (from_steamapps.parent(), to_steamapps.parent())
+    else {
+        return Err("Steam libraries have to contain the steamapps directory".to_string());
+    };
+    let mut changes = Vec::new();
+    for root in steam_roots() {
+        let path = root
+            .join(STEAMAPPS_DIR_NAME)
+            .join(LIBRARY_FOLDERS_FILE_NAME);
+        let text = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let mut library_folders =
+            vdf::parse(&text).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+        if move_app_between_folders(
+            &mut library_folders,
+            app.app_id,
+            app.size_on_disk,
+            from_library,
+            to_library,
+        ) {
+            changes.push(FileChange {
+                path,
+                old: Some(text.into_bytes()),
+                new: Some(vdf::serialize(&library_folders).into_bytes()),
+            });
+        }
+    }
+    if changes.is_empty() {
+        warn!(
+            target: "steam",
+            "No Steam installation lists both {} and {}, Steam has to find {} itself",
+            from_library.display(),
+            to_library.display(),
+            app.name
+        );
+    }
+    // The old manifest goes last, so the app is never listed in neither library
+    changes.insert(
+        0,
+        FileChange {
+            path: to_manifest,
+            old: None,
+            new: Some(manifest.clone()),
+        },
+    );
+    changes.push(FileChange {
+        path: from_manifest,
+        old: Some(manifest),
+        new: None,
+    });
+    replace_files(&changes)
+}
+
+/// Move `app_id` from the app list of the library at `from` to the one at `to`. Returns `false`
+/// without changing anything if either library isn't listed.
+fn move_app_between_folders(
+    library_folders: &mut VdfObject,
+    app_id: u32,
+    size: FileSize,
+    from: &Path,
+    to: &Path,
+) -> bool {
+    let Some(folders) = library_folders.get_object_mut("libraryfolders") else {
+        return false;
+    };
+    // Steam might list the libraries through symlinks
+    let same_library = |folder: &VdfObject, library: &Path| {
+        folder.get_str("path").is_some_and(|path| {
+            let path = Path::new(path);
+            path == library || path.canonicalize().is_ok_and(|path| path == library)
+        })
+    };
+    let find = |library: &Path| {
+        folders.0.iter().position(|(_, folder)| match folder {
+            vdf::Vdf::Object(folder) => same_library(folder, library),
+            vdf::Vdf::Value(_) => false,
+        })
+    };
+    let (Some(from_index), Some(to_index)) = (find(from), find(to)) else {
+        return false;
+    };
+
+    let app_id = app_id.to_string();
+    for (index, value) in [
+        (from_index, None),
+        (to_index, Some(size.as_bytes().to_string())),
+    ] {
+        let vdf::Vdf::Object(folder) = &mut folders.0[index].1 else {
+            unreachable!();
+        };
+        if folder.get_object("apps").is_none() {
+            folder.insert("apps", vdf::Vdf::Object(VdfObject::default()));
+        }
+        let apps = folder.get_object_mut("apps").unwrap();
+        match value {
+            Some(value) => apps.insert(&app_id, vdf::Vdf::Value(value)),
+            None => {
+                apps.remove(&app_id);
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_files() {
+        let dir = std::env::temp_dir().join(format!("moverr-replace-files-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (a, b, missing) = (dir.join("a"), dir.join("b"), dir.join("missing"));
+        fs::write(&a, "old a").unwrap();
+        let change = |path: &Path, old: Option<&str>, new: Option<&str>| FileChange {
+            path: path.to_path_buf(),
+            old: old.map(|old| old.as_bytes().to_vec()),
+            new: new.map(|new| new.as_bytes().to_vec()),
+        };
+
+        // Removing a file that isn't there fails, so the changes before it are reverted
+        let res = replace_files(&[
+            change(&a, Some("old a"), Some("new a")),
+            change(&b, None, Some("new b")),
+            change(&missing, Some("gone"), None),
+        ]);
+        assert!(res.is_err());
+        assert_eq!(fs::read_to_string(&a).unwrap(), "old a");
+        assert!(!b.exists());
+
+        replace_files(&[
+            change(&a, Some("old a"), None),
+            change(&b, None, Some("new b")),
+        ])
+        .unwrap();
+        assert!(!a.exists());
+        assert_eq!(fs::read_to_string(&b).unwrap(), "new b");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_library_folders() {
+        let text = r#"
+"libraryfolders"
+{
+	"0"
+	{
+		"path"		"/home/user/.local/share/Steam"
+		"label"		""
+		"contentid"		"123"
+		"totalsize"		"0"
+		"apps"
+		{
+			"228980"		"1024"
+			"620"		"12884901888"
+		}
+	}
+	"1"
+	{
+		"path"		"/mnt/games/SteamLibrary"
+		"label"		"Games"
+		"apps"
+		{
+		}
+	}
+}
+"#;
+        let libraries = parse_library_folders(text).unwrap();
+        assert_eq!(libraries.len(), 2);
+        assert_eq!(
+            libraries[0].path,
+            PathBuf::from("/home/user/.local/share/Steam")
+        );
+        assert_eq!(libraries[0].apps.get(&620), Some(&12884901888.bytes()));
+        assert_eq!(libraries[1].label, "Games");
+        assert_eq!(
+            libraries[1].common(),
+            PathBuf::from("/mnt/games/SteamLibrary/steamapps/common")
+        );
+        assert!(libraries[1].apps.is_empty());
+    }
+
+    #[test]
+    fn test_parse_app_manifest() {
+        let text = r#"
+"AppState"
+{
+	"appid"		"620"
+	"Universe"		"1"
+	"name"		"Portal 2"
+	"StateFlags"		"6"
+	"installdir"		"Portal 2"
+	"SizeOnDisk"		"12884901888"
+}
+"#;
+        let manifest = AppManifest::parse(text).unwrap();
+        assert_eq!(manifest.app_id, 620);
+        assert_eq!(manifest.name, "Portal 2");
+        assert_eq!(manifest.install_dir, "Portal 2");
+        assert_eq!(manifest.size_on_disk, 12884901888.bytes());
+        assert_eq!(manifest.install_state(), InstallState::UpdateRequired);
+        assert_eq!(
+            AppManifest::parse("\"AppState\" { \"appid\" \"620\" }"),
+            Err("missing name".to_string())
+        );
+    }
+
+    #[test]
+    fn test_install_state() {
+        assert_eq!(InstallState::from_flags(4), InstallState::Installed);
+        assert_eq!(InstallState::from_flags(1026), InstallState::Updating);
+        assert_eq!(
+            InstallState::from_flags(1 << 20 | 4),
+            InstallState::Updating
+        );
+        assert_eq!(InstallState::from_flags(516), InstallState::UpdatePaused);
+        assert_eq!(InstallState::from_flags(0), InstallState::NotInstalled);
+    }
+
+    #[test]
+    fn test_move_app_between_folders() {
+        let text = r#"
+"libraryfolders"
+{
+	"0"
+	{
+		"path"		"/home/user/.local/share/Steam"
+		"apps"
+		{
+			"620"		"1024"
+			"228980"		"2048"
+		}
+	}
+	"1"
+	{
+		"path"		"/mnt/games/SteamLibrary"
+	}
+}
+"#;
+        let mut library_folders = vdf::parse(text).unwrap();
+        let from = Path::new("/home/user/.local/share/Steam");
+        let to = Path::new("/mnt/games/SteamLibrary");
+        assert!(!move_app_between_folders(
+            &mut library_folders.clone(),
+            620,
+            4096.bytes(),
+            from,
+            Path::new("/mnt/other")
+        ));
+        assert!(move_app_between_folders(
+            &mut library_folders,
+            620,
+            4096.bytes(),
+            from,
+            to
+        ));
+
+        let libraries = parse_library_folders(&vdf::serialize(&library_folders)).unwrap();
+        assert_eq!(
+            libraries[0].apps.keys().copied().collect::<Vec<_>>(),
+            [228980]
+        );
+        assert_eq!(libraries[1].apps.get(&620), Some(&4096.bytes()));
+    }
+
+    #[test]
+    fn test_steamapps_of() {
+        assert_eq!(
+            steamapps_of(Path::new("/mnt/games/SteamLibrary/steamapps/common")),
+            Some(Path::new("/mnt/games/SteamLibrary/steamapps"))
+        );
+        assert_eq!(steamapps_of(Path::new("/mnt/games/common")), None);
+    }
+}
@@ -0,0 +1,289 @@
+//! Parser for Valve's KeyValues text format (VDF), used by Steam's `libraryfolders.vdf` and
+//! `appmanifest_*.acf` files.
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// A value in a VDF file, either a string or a nested object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Vdf {
+    Value(String),
+    Object(VdfObject),
+}
+
+/// Keys and values of an object, in the order they're in the file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VdfObject(pub Vec<(String, Vdf)>);
+
+impl VdfObject {
+    /// Get the value of `key`, ignoring case as Steam isn't consistent about it.
+    pub fn get(&self, key: &str) -> Option<&Vdf> {
+        self.0
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value)
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        match self.get(key)? {
+            Vdf::Value(value) => Some(value),
+            Vdf::Object(_) => None,
+        }
+    }
+
+    pub fn get_object(&self, key: &str) -> Option<&VdfObject> {
+        match self.get(key)? {
+            Vdf::Object(object) => Some(object),
+            Vdf::Value(_) => None,
+        }
+    }
+
+    pub fn get_object_mut(&mut self, key: &str) -> Option<&mut VdfObject> {
+        match self
+            .0
+            .iter_mut()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))?
+        {
+            (_, Vdf::Object(object)) => Some(object),
+            (_, Vdf::Value(_)) => None,
+        }
+    }
+
+    /// Set `key` to `value`, keeping its place if it's there already.
+    pub fn insert(&mut self, key: &str, value: Vdf) {
+        match self.0.iter_mut().find(|(k, _)| k.eq_ignore_ascii_case(key)) {
+            Some((_, old)) => *old = value,
+            None => self.0.push((key.to_string(), value)),
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Vdf> {
+        let index = self
+            .0
+            .iter()
+            .position(|(k, _)| k.eq_ignore_ascii_case(key))?;
+        Some(self.0.remove(index).1)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    String(String),
+    Open,
+    Close,
+}
+
+struct Tokenizer<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl Tokenizer<'_> {
+    fn next_token(&mut self) -> Result<Option<Token>, String> {
+        loop {
+            let Some(c) = self.chars.next() else {
+                return Ok(None);
+            };
+            match c {
+                '\n' => self.line += 1,
+                c if c.is_whitespace() => {}
+                '/' if self.chars.peek() == Some(&'/') => {
+                    for c in self.chars.by_ref() {
+                        if c == '\n' {
+                            self.line += 1;
+                            break;
+                        }
+                    }
+                }
+                '{' => return Ok(Some(Token::Open)),
+                '}' => return Ok(Some(Token::Close)),
+                '"' => return self.quoted().map(|string| Some(Token::String(string))),
+                c => {
+                    let mut string = String::from(c);
+                    while let Some(&c) = self.chars.peek() {
+                        if c.is_whitespace() || matches!(c, '{' | '}' | '"') {
+                            break;
+                        }
+                        string.push(c);
+                        self.chars.next();
+                    }
+                    // Conditionals like `[$WIN32]` only matter to the game
+                    if string.starts_with('[') && string.ends_with(']') {
+                        continue;
+                    }
+                    return Ok(Some(Token::String(string)));
+                }
+            }
+        }
+    }
+
+    fn quoted(&mut self) -> Result<String, String> {
+        let mut string = String::new();
+        loop {
+            match self.chars.next() {
+                None => return Err(self.error("unterminated string")),
+                Some('"') => return Ok(string),
+                Some('\\') => match self.chars.next() {
+                    Some('n') => string.push('\n'),
+                    Some('t') => string.push('\t'),
+                    Some(c) => string.push(c),
+                    None => return Err(self.error("unterminated string")),
+                },
+                Some(c) => {
+                    if c == '\n' {
+                        self.line += 1;
+                    }
+                    string.push(c);
+                }
+            }
+        }
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("{} on line {}", message, self.line)
+    }
+}
+
+/// Parse the contents of a VDF file into its top level object.
+pub fn parse(text: &str) -> Result<VdfObject, String> {
+    let mut tokenizer = Tokenizer {
+        chars: text.chars().peekable(),
+        line: 1,
+    };
+    parse_object(&mut tokenizer, false)
+}
+
+/// Write `object` the way Steam does, indenting with tabs.
+pub fn serialize(object: &VdfObject) -> String {
+    let mut text = String::new();
+    write_object(&mut text, object, 0);
+    text
+}
+
+fn write_object(text: &mut String, object: &VdfObject, depth: usize) {
+    let indent = "\t".repeat(depth);
+    for (key, value) in &object.0 {
+        match value {
+            Vdf::Value(value) => {
+                text.push_str(&format!("{}{}\t\t{}\n", indent, quote(key), quote(value)));
+            }
+            Vdf::Object(object) => {
+                text.push_str(&format!("{}{}\n{}{{\n", indent, quote(key), indent));
+                write_object(text, object, depth + 1);
+                text.push_str(&format!("{}}}\n", indent));
+            }
+        }
+    }
+}
+
+fn quote(string: &str) -> String {
+    format!("\"{}\"", string.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn parse_object(tokenizer: &mut Tokenizer, nested: bool) -> Result<VdfObject, String> {
+    let mut object = VdfObject::default();
+    loop {
+        let key = match tokenizer.next_token()? {
+            Some(Token::String(key)) => key,
+            Some(Token::Close) if nested => return Ok(object),
+            None if !nested => return Ok(object),
+            Some(Token::Close) => return Err(tokenizer.error("unexpected '}'")),
+            Some(Token::Open) => return Err(tokenizer.error("unexpected '{'")),
+            None => return Err(tokenizer.error("unexpected end of file")),
+        };
+        let value = match tokenizer.next_token()? {
+            Some(Token::String(value)) => Vdf::Value(value),
+            Some(Token::Open) => Vdf::Object(parse_object(tokenizer, true)?),
+            Some(Token::Close) => return Err(tokenizer.error("unexpected '}'")),
+            None => return Err(tokenizer.error("unexpected end of file")),
+        };
+        object.0.push((key, value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let text = r#"
+// Comment
+"AppState"
+{
+	"appid"		"620"
+	"name"		"Portal 2"
+	"path"		"C:\\Games\\Steam"
+	"quote"		"say \"hi\""
+	"InstalledDepots"
+	{
+		"621"
+		{
+			"size"		"12345"
+		}
+	}
+	unquoted value [$WIN32]
+}
+"#;
+        let root = parse(text).unwrap();
+        let app_state = root.get_object("appstate").unwrap();
+        assert_eq!(app_state.get_str("AppID"), Some("620"));
+        assert_eq!(app_state.get_str("name"), Some("Portal 2"));
+        assert_eq!(app_state.get_str("path"), Some(r"C:\Games\Steam"));
+        assert_eq!(app_state.get_str("quote"), Some(r#"say "hi""#));
+        assert_eq!(app_state.get_str("unquoted"), Some("value"));
+        let depot = app_state
+            .get_object("InstalledDepots")
+            .and_then(|depots| depots.get_object("621"))
+            .unwrap();
+        assert_eq!(depot.get_str("size"), Some("12345"));
+    }
+
+    #[test]
+    fn test_serialize() {
+        let text = r#""libraryfolders"
+{
+	"0"
+	{
+		"path"		"C:\\Steam"
+		"apps"
+		{
+		}
+	}
+}
+"#;
+        let mut root = parse(text).unwrap();
+        assert_eq!(serialize(&root), text);
+
+        let apps = root
+            .get_object_mut("libraryfolders")
+            .and_then(|folders| folders.get_object_mut("0"))
+            .and_then(|folder| folder.get_object_mut("apps"))
+            .unwrap();
+        apps.insert("620", Vdf::Value("1024".to_string()));
+        assert_eq!(parse(&serialize(&root)).unwrap(), root);
+        let apps = root
+            .get_object_mut("libraryfolders")
+            .and_then(|folders| folders.get_object_mut("0"))
+            .and_then(|folder| folder.get_object_mut("apps"))
+            .unwrap();
+        assert_eq!(apps.remove("620"), Some(Vdf::Value("1024".to_string())));
+        assert_eq!(serialize(&root), text);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse("\"a\"\n{\n\"b\" \"c\"\n"),
+            Err("unexpected end of file on line 4".to_string())
+        );
+        assert_eq!(
+            parse("\"a\" }"),
+            Err("unexpected '}' on line 1".to_string())
+        );
+        assert_eq!(
+            parse("\"a"),
+            Err("unterminated string on line 1".to_string())
+        );
+    }
+}
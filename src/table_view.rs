@@ -0,0 +1,114 @@
+//! How the project table is sorted and filtered.
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+/// What the rows of the project table are sorted by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortKey {
+    #[default]
+    Name,
+    Size,
+    /// Where the entry is: in place, moved, archived or busy.
+    State,
+    /// When the entry was last accessed.
+    Accessed,
+}
+
+impl SortKey {
+    pub fn next(self) -> Self {
+        match self {
+            SortKey::Name => SortKey::Size,
+            SortKey::Size => SortKey::State,
+            SortKey::State => SortKey::Accessed,
+            SortKey::Accessed => SortKey::Name,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortKey::Name => "name",
+            SortKey::Size => "size",
+            SortKey::State => "state",
+            SortKey::Accessed => "last access",
+        }
+    }
+}
+
+/// Sorting and filtering of the project table, stored with the project.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TableView {
+    pub sort_key: SortKey,
+    pub descending: bool,
+    /// Only the entries whose name contains this, ignoring case, are shown.
+    pub filter: String,
+}
+
+impl TableView {
+    /// Whether an entry with any of `names` is shown with the filter.
+    pub fn matches(&self, names: &[&str]) -> bool {
+        let filter = self.filter.to_lowercase();
+        names
+            .iter()
+            .any(|name| name.to_lowercase().contains(&filter))
+    }
+
+    /// Sort `rows` by the values `key` gets for them. Rows with equal values keep their order,
+    /// whichever the direction, and rows without a value, like ones with an unknown size, go
+    /// last.
+    pub fn sort_rows<T, K: Ord>(&self, rows: &mut [T], key: impl Fn(&T) -> Option<K>) {
+        rows.sort_by(|a, b| match (key(a), key(b)) {
+            (Some(a), Some(b)) if self.descending => b.cmp(&a),
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        });
+    }
+
+    /// Describe the sorting and the filter for the title of the table.
+    pub fn describe(&self) -> String {
+        let mut description = format!(
+            "Sorted by {} {}",
+            self.sort_key.label(),
+            if self.descending { "↓" } else { "↑" }
+        );
+        if !self.filter.is_empty() {
+            description.push_str(&format!(", filtered by \"{}\"", self.filter));
+        }
+        description
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let mut view = TableView::default();
+        assert!(view.matches(&["anything"]));
+        view.filter = "life".to_string();
+        assert!(view.matches(&["half-life 2"]));
+        assert!(view.matches(&["hl2", "Half-Life 2"]));
+        assert!(!view.matches(&["portal"]));
+    }
+
+    #[test]
+    fn test_sort_rows() {
+        let mut view = TableView::default();
+        let mut rows = [("b", Some(2)), ("x", None), ("a", Some(1)), ("c", Some(2))];
+        view.sort_rows(&mut rows, |row| row.1);
+        assert_eq!(
+            rows,
+            [("a", Some(1)), ("b", Some(2)), ("c", Some(2)), ("x", None)]
+        );
+        view.descending = true;
+        view.sort_rows(&mut rows, |row| row.1);
+        assert_eq!(
+            rows,
+            [("b", Some(2)), ("c", Some(2)), ("a", Some(1)), ("x", None)]
+        );
+    }
+}
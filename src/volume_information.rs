@@ -1,3 +1,8 @@
+use crate::file_size::num_ext::AsBytes;
+use crate::file_size::FileSize;
+use std::io;
+use std::path::{Path, PathBuf};
+
 pub struct VolumeInformation {
     pub volume_name: String,
     pub volume_serial_number: u32,
@@ -22,4 +27,323 @@ impl VolumeInformation {
     //         file_system_name,
     //     }
     // }
+
+    /// Query information about the volume whose root is at `root`.
+    #[cfg(windows)]
+    pub fn query(root: &Path) -> Result<Self, Option<io::Error>> {
+        use std::os::windows::ffi::OsStrExt;
+        use windows::{
+            core::PCWSTR,
+            Win32::{Foundation::MAX_PATH, Storage::FileSystem::GetVolumeInformationW},
+        };
+
+        let path_utf16: Vec<u16> = root
+            .as_os_str()
+            .encode_wide()
+            .chain(std::iter::once(0))
+            .collect();
+        const BUFFER_SIZE: usize = MAX_PATH as usize + 1;
+
+        let mut volume_name_utf16 = [0u16; BUFFER_SIZE];
+        let mut serial_number = 0u32;
+        let mut max_component_length = 0u32;
+        let mut flags = 0u32;
+        let mut fs_type_utf16 = [0u16; BUFFER_SIZE];
+
+        unsafe {
+            GetVolumeInformationW(
+                PCWSTR(path_utf16.as_ptr()),
+                Some(&mut volume_name_utf16),
+                Some(&mut serial_number),
+                Some(&mut max_component_length),
+                Some(&mut flags),
+                Some(&mut fs_type_utf16),
+            )
+        }
+        .map_err(|e| Some(e.into()))?;
+
+        Ok(VolumeInformation {
+            volume_name: from_utf16_nul(&volume_name_utf16),
+            volume_serial_number: serial_number,
+            maximum_component_length: max_component_length,
+            file_system_flags: flags,
+            file_system_name: from_utf16_nul(&fs_type_utf16),
+        })
+    }
+
+    /// Query information about the volume `path` is on.
+    ///
+    /// `path` should be canonical, as the volume is found by looking it up in the mount table.
+    #[cfg(unix)]
+    pub fn query(path: &Path) -> Result<Self, Option<io::Error>> {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+
+        let mounts = std::fs::read_to_string("/proc/self/mounts").map_err(Some)?;
+        let (device, file_system_name) = find_mount(&mounts, path, fuseblk_type).ok_or(None)?;
+
+        let path_c = CString::new(path.as_os_str().as_bytes())
+            .map_err(|e| Some(io::Error::new(io::ErrorKind::InvalidInput, e)))?;
+        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statvfs(path_c.as_ptr(), &mut stat) } != 0 {
+            return Err(Some(io::Error::last_os_error()));
+        }
+
+        Ok(VolumeInformation {
+            volume_name: device,
+            volume_serial_number: stat.f_fsid as u32,
+            maximum_component_length: stat.f_namemax as u32,
+            file_system_flags: stat.f_flag as u32,
+            file_system_name,
+        })
+    }
+}
+
+/// Free space of a volume and the unit space is allocated in on it.
+#[derive(Debug, Clone, Copy)]
+pub struct VolumeSpace {
+    /// Free space available to the current user.
+    pub available: FileSize,
+    /// Size of the blocks (clusters on Windows) space is allocated in.
+    pub block_size: u64,
+}
+
+impl VolumeSpace {
+    /// Query the free space of the volume whose root is at `root`.
+    #[cfg(windows)]
+    pub fn query(root: &Path) -> io::Result<Self> {
+        use std::os::windows::ffi::OsStrExt;
+        use windows::{
+            core::PCWSTR,
+            Win32::Storage::FileSystem::{GetDiskFreeSpaceExW, GetDiskFreeSpaceW},
+        };
+
+        let path_utf16: Vec<u16> = root
+            .as_os_str()
+            .encode_wide()
+            .chain(std::iter::once(0))
+            .collect();
+
+        let mut available = 0u64;
+        let mut sectors_per_cluster = 0u32;
+        let mut bytes_per_sector = 0u32;
+        unsafe {
+            GetDiskFreeSpaceExW(
+                PCWSTR(path_utf16.as_ptr()),
+                Some(&mut available),
+                None,
+                None,
+            )?;
+            GetDiskFreeSpaceW(
+                PCWSTR(path_utf16.as_ptr()),
+                Some(&mut sectors_per_cluster),
+                Some(&mut bytes_per_sector),
+                None,
+                None,
+            )?;
+        }
+
+        Ok(VolumeSpace {
+            available: available.bytes(),
+            block_size: sectors_per_cluster as u64 * bytes_per_sector as u64,
+        })
+    }
+
+    /// Query the free space of the volume `path` is on.
+    #[cfg(unix)]
+    pub fn query(path: &Path) -> io::Result<Self> {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path_c = CString::new(path.as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statvfs(path_c.as_ptr(), &mut stat) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(VolumeSpace {
+            available: (stat.f_bavail as u64 * stat.f_frsize as u64).bytes(),
+            block_size: stat.f_frsize as u64,
+        })
+    }
+}
+
+#[cfg(windows)]
+fn from_utf16_nul(buffer: &[u16]) -> String {
+    let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+    String::from_utf16_lossy(&buffer[..len])
+}
+
+/// Find the device and file system type of the mount `path` is on, given the contents of a
+/// `mounts` file.
+///
+/// FUSE drivers like ntfs-3g and exfat-fuse show up as `fuseblk`, so the real type of their
+/// device is looked up with `fuseblk_type`, keeping `fuseblk` if it can't be found.
+#[cfg(unix)]
+fn find_mount(
+    mounts: &str,
+    path: &Path,
+    fuseblk_type: impl Fn(&str) -> Option<String>,
+) -> Option<(String, String)> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let device = fields.next()?;
+            let mount_point = unescape_mount_field(fields.next()?);
+            let file_system = fields.next()?;
+            Some((device, mount_point, file_system))
+        })
+        .filter(|(_, mount_point, _)| path.starts_with(mount_point))
+        // Later mounts shadow earlier ones on the same mount point
+        .max_by_key(|(_, mount_point, _)| mount_point.len())
+        .map(|(device, _, file_system)| {
+            let file_system = match file_system {
+                "fuseblk" => fuseblk_type(device).unwrap_or_else(|| file_system.to_string()),
+                _ => file_system.to_string(),
+            };
+            (device.to_string(), file_system)
+        })
+}
+
+/// Get the file system type udev detected on the block `device`, e.g. `ntfs` or `exfat`.
+#[cfg(unix)]
+fn fuseblk_type(device: &str) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+
+    let rdev = std::fs::metadata(device).ok()?.rdev();
+    let data = std::fs::read_to_string(format!(
+        "/run/udev/data/b{}:{}",
+        libc::major(rdev),
+        libc::minor(rdev)
+    ))
+    .ok()?;
+    data.lines()
+        .find_map(|line| line.strip_prefix("E:ID_FS_TYPE="))
+        .filter(|file_system| !file_system.is_empty())
+        .map(str::to_string)
+}
+
+/// List the root directories of the mounted volumes, i.e. the drives on Windows and the mount
+/// points of block devices elsewhere.
+#[cfg(windows)]
+pub fn mounted_volumes() -> Vec<PathBuf> {
+    ('A'..='Z')
+        .map(|letter| PathBuf::from(format!("{}:\\", letter)))
+        .filter(|root| root.exists())
+        .collect()
+}
+
+/// List the root directories of the mounted volumes, i.e. the drives on Windows and the mount
+/// points of block devices elsewhere.
+#[cfg(unix)]
+pub fn mounted_volumes() -> Vec<PathBuf> {
+    match std::fs::read_to_string("/proc/self/mounts") {
+        Ok(mounts) => mount_points(&mounts),
+        Err(_) => vec![PathBuf::from("/")],
+    }
+}
+
+/// Find the mount points of block devices in the contents of a `mounts` file, skipping loop
+/// devices as those are usually read-only images (e.g. snaps).
+#[cfg(unix)]
+fn mount_points(mounts: &str) -> Vec<PathBuf> {
+    let mut mount_points: Vec<_> = mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let device = fields.next()?;
+            let mount_point = unescape_mount_field(fields.next()?);
+            (mount_point == "/" || device.starts_with("/dev/") && !device.starts_with("/dev/loop"))
+                .then(|| PathBuf::from(mount_point))
+        })
+        .collect();
+    mount_points.sort();
+    mount_points.dedup();
+    mount_points
+}
+
+/// Unescape the octal escapes (e.g. `\040` for a space) used in the fields of a `mounts` file.
+#[cfg(unix)]
+fn unescape_mount_field(field: &str) -> String {
+    let mut result = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            let code: String = chars.clone().take(3).collect();
+            if let Ok(byte) = u8::from_str_radix(&code, 8) {
+                result.push(byte as char);
+                chars.nth(2);
+                continue;
+            }
+        }
+        result.push(c);
+    }
+    result
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_mount() {
+        let mounts = "\
+/dev/sda1 / ext4 rw 0 0
+/dev/sdb1 /mnt/games vfat rw 0 0
+/dev/sdc1 /mnt/games\\040hdd ntfs3 rw 0 0
+/dev/sdd1 /mnt/games ext4 rw 0 0
+";
+        assert_eq!(
+            find_mount(mounts, Path::new("/home/user"), |_| None),
+            Some(("/dev/sda1".to_string(), "ext4".to_string()))
+        );
+        assert_eq!(
+            find_mount(mounts, Path::new("/mnt/games hdd/Portal"), |_| None),
+            Some(("/dev/sdc1".to_string(), "ntfs3".to_string()))
+        );
+        assert_eq!(
+            find_mount(mounts, Path::new("/mnt/games/Portal"), |_| None),
+            Some(("/dev/sdd1".to_string(), "ext4".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_find_mount_fuseblk() {
+        let mounts = "\
+/dev/sda1 / ext4 rw 0 0
+/dev/sdb1 /mnt/games fuseblk rw 0 0
+/dev/sdc1 /mnt/usb fuseblk rw 0 0
+";
+        let fuseblk_type = |device: &str| (device == "/dev/sdb1").then(|| "ntfs".to_string());
+        assert_eq!(
+            find_mount(mounts, Path::new("/mnt/games/Portal"), fuseblk_type),
+            Some(("/dev/sdb1".to_string(), "ntfs".to_string()))
+        );
+        assert_eq!(
+            find_mount(mounts, Path::new("/mnt/usb/Portal"), fuseblk_type),
+            Some(("/dev/sdc1".to_string(), "fuseblk".to_string()))
+        );
+        assert_eq!(
+            find_mount(mounts, Path::new("/home/user"), fuseblk_type),
+            Some(("/dev/sda1".to_string(), "ext4".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_mount_points() {
+        let mounts = "\
+proc /proc proc rw 0 0
+/dev/sda1 / ext4 rw 0 0
+/dev/sdb1 /mnt/games\\040hdd ntfs3 rw 0 0
+/dev/loop0 /snap/core/1 squashfs ro 0 0
+tmpfs /tmp tmpfs rw 0 0
+/dev/sda1 / ext4 rw 0 0
+";
+        assert_eq!(
+            mount_points(mounts),
+            [PathBuf::from("/"), PathBuf::from("/mnt/games hdd")]
+        );
+    }
 }
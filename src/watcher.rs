@@ -0,0 +1,73 @@
+use log::warn;
+use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+
+/// Watches the directory of a project, and the locations its entries were moved to, for changes.
+pub struct ProjectWatcher {
+    watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    /// Locations watched besides the project directory, with the name of the entry each belongs to.
+    targets: BTreeMap<PathBuf, String>,
+}
+
+impl ProjectWatcher {
+    pub fn new(project_directory: &Path) -> notify::Result<Self> {
+        let (sender, events) = channel();
+        // Symlinks aren't followed, as the locations they point to are watched as targets
+        let mut watcher =
+            RecommendedWatcher::new(sender, Config::default().with_follow_symlinks(false))?;
+        watcher.watch(project_directory, RecursiveMode::Recursive)?;
+        Ok(Self {
+            watcher,
+            events,
+            targets: BTreeMap::new(),
+        })
+    }
+
+    pub fn targets(&self) -> &BTreeMap<PathBuf, String> {
+        &self.targets
+    }
+
+    /// Watch exactly the given locations besides the project directory.
+    pub fn set_targets(&mut self, targets: BTreeMap<PathBuf, String>) {
+        if targets == self.targets {
+            return;
+        }
+
+        for removed in self
+            .targets
+            .keys()
+            .filter(|path| !targets.contains_key(*path))
+        {
+            // Fails if the location is gone already, in which case it isn't watched anymore anyway
+            let _ = self.watcher.unwatch(removed);
+        }
+        for added in targets
+            .keys()
+            .filter(|path| !self.targets.contains_key(*path))
+        {
+            if let Err(err) = self.watcher.watch(added, RecursiveMode::Recursive) {
+                warn!(target: "watcher", "Can't watch {}: {}", added.display(), err);
+            }
+        }
+        self.targets = targets;
+    }
+
+    /// Get the paths that changed since the last call.
+    pub fn changed_paths(&self) -> Vec<PathBuf> {
+        self.events
+            .try_iter()
+            .filter_map(|event| match event {
+                Ok(event) if matches!(event.kind, EventKind::Access(_)) => None,
+                Ok(event) => Some(event.paths),
+                Err(err) => {
+                    warn!(target: "watcher", "{}", err);
+                    None
+                }
+            })
+            .flatten()
+            .collect()
+    }
+}
@@ -1,3 +1,5 @@
+mod path_completion;
 mod text_input;
 
-pub use text_input::{TextInput, TextInputState};
+pub use path_completion::{complete_path, expand_path};
+pub use text_input::{Completions, TextInput, TextInputState};
@@ -0,0 +1,132 @@
+use crate::widgets::Completions;
+use std::env;
+use std::fs::read_dir;
+use std::path::{is_separator, Path, PathBuf};
+
+/// Expand a leading `~` to the home directory and `$VAR`, `${VAR}` and `%VAR%` to the values of
+/// the environment variables. Unknown variables are left as they are.
+pub fn expand_path(input: &str) -> PathBuf {
+    PathBuf::from(expand_with(input, dirs::home_dir().as_deref(), |name| {
+        env::var(name).ok()
+    }))
+}
+
+fn expand_with(input: &str, home: Option<&Path>, var: impl Fn(&str) -> Option<String>) -> String {
+    let mut result = String::with_capacity(input.len());
+    let mut rest = input;
+
+    if let (Some(home), Some(after_tilde)) = (home, input.strip_prefix('~')) {
+        if after_tilde.is_empty() || after_tilde.starts_with(is_separator) {
+            result.push_str(&home.to_string_lossy());
+            rest = after_tilde;
+        }
+    }
+
+    while let Some(start) = rest.find(['$', '%']) {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let (name, len) = match (&rest[start..start + 1], after.strip_prefix('{')) {
+            ("$", Some(braced)) => match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            },
+            ("$", None) => {
+                let end = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                (&after[..end], end)
+            }
+            _ => match after.find('%') {
+                Some(end) => (&after[..end], end + 1),
+                None => ("", 0),
+            },
+        };
+        match (!name.is_empty()).then(|| var(name)).flatten() {
+            Some(value) => {
+                result.push_str(&value);
+                rest = &after[len..];
+            }
+            None => {
+                result.push_str(&rest[start..start + 1]);
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Complete the last segment of the path in `input` from the entries of its parent directory.
+/// Directories get a trailing separator, so that completing continues inside them.
+pub fn complete_path(input: &str) -> Completions {
+    let expanded = expand_path(input).to_string_lossy().into_owned();
+    let (directory, segment) = match expanded.rfind(is_separator) {
+        Some(index) => expanded.split_at(index + 1),
+        None => ("", expanded.as_str()),
+    };
+
+    let names = read_dir(if directory.is_empty() { "." } else { directory })
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| {
+                    let mut name = entry.file_name().to_string_lossy().into_owned();
+                    if entry.path().is_dir() {
+                        name.push(std::path::MAIN_SEPARATOR);
+                    }
+                    name
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Completions {
+        base: directory.to_string(),
+        candidates: matching_names(names, segment),
+    }
+}
+
+/// Keep the names starting with `segment`, hiding dotfiles unless the segment asks for them.
+fn matching_names(mut names: Vec<String>, segment: &str) -> Vec<String> {
+    names.retain(|name| {
+        name.starts_with(segment) && (segment.starts_with('.') || !name.starts_with('.'))
+    });
+    names.sort_by_cached_key(|name| name.to_lowercase());
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_with() {
+        let var = |name: &str| match name {
+            "GAMES" => Some("/mnt/games".to_string()),
+            "USER" => Some("steve".to_string()),
+            _ => None,
+        };
+        let home = Some(Path::new("/home/steve"));
+        assert_eq!(expand_with("~/Games", home, var), "/home/steve/Games");
+        assert_eq!(expand_with("~", home, var), "/home/steve");
+        assert_eq!(expand_with("~steve", home, var), "~steve");
+        assert_eq!(expand_with("$GAMES/Portal", home, var), "/mnt/games/Portal");
+        assert_eq!(expand_with("${GAMES}2", home, var), "/mnt/games2");
+        assert_eq!(expand_with("/home/%USER%/x", home, var), "/home/steve/x");
+        assert_eq!(expand_with("$MISSING/$", home, var), "$MISSING/$");
+        assert_eq!(expand_with("100%", home, var), "100%");
+    }
+
+    #[test]
+    fn test_matching_names() {
+        let names = ["games/", ".config/", "Games2/", "gamma.txt", "music/"]
+            .map(String::from)
+            .to_vec();
+        assert_eq!(matching_names(names.clone(), "ga"), ["games/", "gamma.txt"]);
+        assert_eq!(
+            matching_names(names.clone(), ""),
+            ["games/", "Games2/", "gamma.txt", "music/"]
+        );
+        assert_eq!(matching_names(names, "."), [".config/"]);
+    }
+}
@@ -10,11 +10,33 @@ pub struct TextInput {
     pub cursor_style: Style,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub struct TextInputState {
     pub input: Vec<char>,
     pub cursor: u16,
     pub scroll: u16,
+    /// Completes the input when Tab is pressed, if set.
+    completion_provider: Option<CompletionProvider>,
+    /// Candidates of the last completion, while Tab cycles through them.
+    completion: Option<CompletionState>,
+}
+
+/// Get the candidates for completing the end of an input.
+pub type CompletionProvider = fn(&str) -> Completions;
+
+/// Candidates for completing the end of an input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completions {
+    /// The part of the input the candidates are appended to, which might have been expanded.
+    pub base: String,
+    pub candidates: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+struct CompletionState {
+    completions: Completions,
+    /// The candidate in the input, or `None` if none has been picked yet.
+    selected: Option<usize>,
 }
 
 impl Default for TextInput {
@@ -78,6 +100,74 @@ impl TextInputState {
         self.input.iter().collect()
     }
 
+    /// Complete the input with `provider` when Tab is pressed.
+    pub fn with_completion_provider(mut self, provider: CompletionProvider) -> Self {
+        self.completion_provider = Some(provider);
+        self
+    }
+
+    /// Replace the input, putting the cursor at its end.
+    pub fn set_input(&mut self, input: &str) {
+        self.input = input.chars().collect();
+        self.cursor = self.input_len();
+    }
+
+    /// Get the candidates the input is being completed with and the one picked, to show them
+    /// while Tab cycles through them.
+    pub fn completion_candidates(&self) -> Option<(&[String], Option<usize>)> {
+        self.completion.as_ref().map(|completion| {
+            (
+                completion.completions.candidates.as_slice(),
+                completion.selected,
+            )
+        })
+    }
+
+    /// Complete the input, or pick the next (or previous if `backwards`) candidate if it's
+    /// already being completed.
+    ///
+    /// A single candidate is taken right away, so that the next Tab completes further. With more
+    /// candidates, the input is completed as far as they agree and the next Tabs cycle through
+    /// them.
+    fn complete(&mut self, backwards: bool) {
+        if let Some(completion) = &mut self.completion {
+            let len = completion.completions.candidates.len();
+            let selected = match (completion.selected, backwards) {
+                (None, false) => 0,
+                (None, true) => len - 1,
+                (Some(selected), false) => (selected + 1) % len,
+                (Some(selected), true) => (selected + len - 1) % len,
+            };
+            completion.selected = Some(selected);
+            let input = format!(
+                "{}{}",
+                completion.completions.base, completion.completions.candidates[selected]
+            );
+            self.set_input(&input);
+            return;
+        }
+
+        let Some(provider) = self.completion_provider else {
+            return;
+        };
+        let completions = provider(&self.input_as_string());
+        match completions.candidates.as_slice() {
+            [] => {}
+            [candidate] => {
+                let input = format!("{}{}", completions.base, candidate);
+                self.set_input(&input);
+            }
+            candidates => {
+                let input = format!("{}{}", completions.base, common_prefix(candidates));
+                self.set_input(&input);
+                self.completion = Some(CompletionState {
+                    completions,
+                    selected: None,
+                });
+            }
+        }
+    }
+
     fn ensure_valid_state(&mut self, width: u16) {
         // Ensure the cursor is within the bounds of the input string
         self.cursor = self.cursor.clip_to(0..=self.input_len());
@@ -99,6 +189,20 @@ impl TextInputState {
 
     /// Returns `true` if the key was handled, `false` otherwise.
     pub fn handle_key_event(&mut self, key_event: KeyEvent) -> bool {
+        if matches!(key_event.kind, KeyEventKind::Press | KeyEventKind::Repeat) {
+            match key_event.code {
+                KeyCode::Tab if self.completion_provider.is_some() => {
+                    self.complete(false);
+                    return true;
+                }
+                KeyCode::BackTab if self.completion_provider.is_some() => {
+                    self.complete(true);
+                    return true;
+                }
+                _ => self.completion = None,
+            }
+        }
+
         match key_event {
             KeyEvent {
                 kind: KeyEventKind::Press | KeyEventKind::Repeat,
@@ -203,6 +307,82 @@ impl Default for TextInputState {
             input: Vec::with_capacity(Self::DEFAULT_STRING_CAPACITY as usize),
             cursor: 0,
             scroll: 0,
+            completion_provider: None,
+            completion: None,
+        }
+    }
+}
+
+/// Get the longest prefix all of `candidates` share.
+fn common_prefix(candidates: &[String]) -> &str {
+    let Some((first, rest)) = candidates.split_first() else {
+        return "";
+    };
+    let len = rest.iter().fold(first.len(), |len, candidate| {
+        first[..len]
+            .char_indices()
+            .zip(candidate.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(len.min(candidate.len()), |((index, _), _)| index)
+    });
+    &first[..len]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(input: &str) -> Completions {
+        let candidates = ["games/", "gamma/", "music/"]
+            .iter()
+            .filter(|candidate| candidate.starts_with(input.trim_start_matches("/mnt/")))
+            .map(|candidate| candidate.to_string())
+            .collect();
+        Completions {
+            base: "/mnt/".to_string(),
+            candidates,
         }
     }
+
+    fn press(state: &mut TextInputState, code: KeyCode) {
+        state.handle_key_event(KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    #[test]
+    fn test_complete() {
+        let mut state = TextInputState::default().with_completion_provider(provider);
+        state.set_input("/mnt/m");
+        press(&mut state, KeyCode::Tab);
+        assert_eq!(state.input_as_string(), "/mnt/music/");
+        assert_eq!(state.completion_candidates(), None);
+
+        state.set_input("/mnt/g");
+        press(&mut state, KeyCode::Tab);
+        assert_eq!(state.input_as_string(), "/mnt/gam");
+        assert_eq!(state.cursor, 8);
+        press(&mut state, KeyCode::Tab);
+        assert_eq!(state.input_as_string(), "/mnt/games/");
+        press(&mut state, KeyCode::Tab);
+        assert_eq!(state.input_as_string(), "/mnt/gamma/");
+        press(&mut state, KeyCode::Tab);
+        assert_eq!(state.input_as_string(), "/mnt/games/");
+        press(&mut state, KeyCode::BackTab);
+        assert_eq!(state.input_as_string(), "/mnt/gamma/");
+
+        // Typing ends the cycling
+        press(&mut state, KeyCode::Char('x'));
+        assert_eq!(state.completion_candidates(), None);
+    }
+
+    #[test]
+    fn test_common_prefix() {
+        let candidates = ["games", "gamma", "gamé"].map(String::from);
+        assert_eq!(common_prefix(&candidates), "gam");
+        assert_eq!(common_prefix(&candidates[..1]), "games");
+        assert_eq!(
+            common_prefix(&["gamé".to_string(), "gamè".to_string()]),
+            "gam"
+        );
+        assert_eq!(common_prefix(&[]), "");
+    }
 }